@@ -185,12 +185,33 @@ function createSessionTools(sessionId: string) {
 
       tool(
         "get_pending_comments",
-        "Get all open/unresolved comments on your code changes that need attention. Use this to check if the user has left feedback on your work.",
-        {},
-        async () => {
+        "Get open/unresolved comments on your code changes that need attention, oldest first. Use this to check if the user has left feedback on your work. Results are paged (50 by default); if more remain, call again with the suggested offset.",
+        {
+          offset: z
+            .number()
+            .int()
+            .min(0)
+            .optional()
+            .describe("Number of comments to skip (default 0)"),
+          limit: z
+            .number()
+            .int()
+            .min(1)
+            .max(500)
+            .optional()
+            .describe("Maximum number of comments to return (default 50)"),
+        },
+        async (args) => {
           try {
+            const params = new URLSearchParams();
+            if (args.offset !== undefined) {
+              params.set("offset", String(args.offset));
+            }
+            if (args.limit !== undefined) {
+              params.set("limit", String(args.limit));
+            }
             const response = await fetch(
-              `${SESSION_SERVER_URL}/api/session/${sessionId}/comments`,
+              `${SESSION_SERVER_URL}/api/session/${sessionId}/comments?${params}`,
             );
             const data = (await response.json()) as {
               success: boolean;
@@ -201,28 +222,118 @@ function createSessionTools(sessionId: string) {
                 author: string;
                 content: string;
               }>;
+              total?: number;
               error?: string;
             };
 
             if (data.success && data.comments) {
+              const offset = args.offset ?? 0;
               if (data.comments.length === 0) {
                 return {
                   content: [
                     {
                       type: "text" as const,
-                      text: "No pending comments on your changes.",
+                      text:
+                        offset > 0
+                          ? `No pending comments past offset ${offset}.`
+                          : "No pending comments on your changes.",
                     },
                   ],
                 };
               }
+              const end = offset + data.comments.length;
+              const total = data.total ?? end;
+              const shown =
+                total > data.comments.length
+                  ? ` (showing ${offset + 1}-${end})`
+                  : "";
+              const more =
+                total > end
+                  ? `\n\n${total - end} more; call again with offset=${end}.`
+                  : "";
               const commentText =
-                `Found ${data.comments.length} pending comment(s):\n\n` +
+                `Found ${total} pending comment(s)${shown}:\n\n` +
                 data.comments
                   .map(
                     (c, i) =>
-                      `${i + 1}. [${c.id}] ${c.file_path}:${c.line_number || "file"}\n   Author: ${c.author}\n   "${c.content}"`,
+                      `${offset + i + 1}. [${c.id}] ${c.file_path}:${c.line_number || "file"}\n   Author: ${c.author}\n   "${c.content}"`,
                   )
-                  .join("\n\n");
+                  .join("\n\n") +
+                more;
+              return {
+                content: [{ type: "text" as const, text: commentText }],
+              };
+            }
+            return {
+              content: [
+                {
+                  type: "text" as const,
+                  text: `Error: ${data.error || "Failed to get comments"}`,
+                },
+              ],
+            };
+          } catch (error) {
+            return {
+              content: [
+                {
+                  type: "text" as const,
+                  text: `HTTP Error: ${error instanceof Error ? error.message : "Unknown error"}`,
+                },
+              ],
+            };
+          }
+        },
+      ),
+
+      tool(
+        "get_comments_by_file",
+        "Get open comments on your changes grouped by file. Use this to work through review feedback one file at a time.",
+        {},
+        async () => {
+          try {
+            const response = await fetch(
+              `${SESSION_SERVER_URL}/api/session/${sessionId}/comments/grouped`,
+            );
+            const data = (await response.json()) as {
+              success: boolean;
+              data?: {
+                files: Array<{
+                  file_path: string;
+                  open_count: number;
+                  comments: Array<{
+                    id: string;
+                    line_number?: number;
+                    author: string;
+                    content: string;
+                  }>;
+                }>;
+              };
+              error?: string;
+            };
+
+            if (data.success && data.data) {
+              if (data.data.files.length === 0) {
+                return {
+                  content: [
+                    {
+                      type: "text" as const,
+                      text: "No pending comments on your changes.",
+                    },
+                  ],
+                };
+              }
+              const commentText = data.data.files
+                .map(
+                  (f) =>
+                    `${f.file_path} (${f.open_count} open):\n` +
+                    f.comments
+                      .map(
+                        (c) =>
+                          `  [${c.id}] line ${c.line_number || "file"} - ${c.author}: "${c.content}"`,
+                      )
+                      .join("\n"),
+                )
+                .join("\n\n");
               return {
                 content: [{ type: "text" as const, text: commentText }],
               };
@@ -343,6 +454,47 @@ function createSessionTools(sessionId: string) {
         },
       ),
 
+      tool(
+        "reopen_comment",
+        "Reopen a resolved comment. Use this if a comment was resolved but the feedback has not actually been addressed.",
+        {
+          comment_id: z
+            .string()
+            .describe("The ID of the comment to reopen"),
+        },
+        async (args) => {
+          try {
+            const response = await fetch(
+              `${SESSION_SERVER_URL}/api/session/${sessionId}/comments/${args.comment_id}/reopen`,
+              { method: "POST" },
+            );
+            const data = (await response.json()) as {
+              success: boolean;
+              error?: string;
+            };
+            return {
+              content: [
+                {
+                  type: "text" as const,
+                  text: data.success
+                    ? `Comment ${args.comment_id} reopened`
+                    : `Error: ${data.error || "Failed to reopen"}`,
+                },
+              ],
+            };
+          } catch (error) {
+            return {
+              content: [
+                {
+                  type: "text" as const,
+                  text: `HTTP Error: ${error instanceof Error ? error.message : "Unknown error"}`,
+                },
+              ],
+            };
+          }
+        },
+      ),
+
       tool(
         "request_review",
         "Request user review of your changes with a message. Use this when you want the user to review your code changes.",
@@ -420,6 +572,13 @@ function emitError(error: string, details?: unknown): void {
   );
 }
 
+// Tell the app the session is still making progress; failures are ignored
+function sendHeartbeat(sessionId: string): void {
+  fetch(`${SESSION_SERVER_URL}/api/session/${sessionId}/heartbeat`, {
+    method: "POST",
+  }).catch(() => {});
+}
+
 // Graceful shutdown handler
 function setupShutdownHandlers(): void {
   const shutdown = (signal: string) => {
@@ -466,8 +625,10 @@ const CLAUDE_SESSIONS_TOOLS = [
   "mcp__claude-sessions__notify_ready",
   "mcp__claude-sessions__notify_busy",
   "mcp__claude-sessions__get_pending_comments",
+  "mcp__claude-sessions__get_comments_by_file",
   "mcp__claude-sessions__reply_to_comment",
   "mcp__claude-sessions__resolve_comment",
+  "mcp__claude-sessions__reopen_comment",
   "mcp__claude-sessions__request_review",
 ];
 
@@ -717,6 +878,9 @@ async function runAgent(input: AgentInput): Promise<void> {
     })) {
       // Forward all messages to stdout as JSON
       emit(message as OutputMessage);
+      if (claudeSessionsId) {
+        sendHeartbeat(claudeSessionsId);
+      }
     }
 
     // Signal completion
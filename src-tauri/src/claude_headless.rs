@@ -3,23 +3,615 @@
 //! Spawns Claude Agent SDK sidecar with JSON streaming output,
 //! parses the JSON messages, and emits Tauri events to the frontend.
 
+use crate::permissions::{
+    self, PendingPermission, PermissionBehavior, PermissionCancelled, PermissionRequest,
+    PermissionResponse,
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader};
-use std::process::{Command, Stdio};
-use std::sync::Mutex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
 use tauri::{AppHandle, Emitter};
+use tauri_plugin_shell::process::CommandChild;
 use tauri_plugin_shell::ShellExt;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, info, warn};
 
 /// Registry of running Claude processes, keyed by session_id
 static PROCESSES: once_cell::sync::Lazy<Mutex<HashMap<String, ClaudeProcess>>> =
     once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
 
+/// Lock the process registry. A panic while it was held leaves the map
+/// itself intact, so a poisoned lock is recovered instead of failing every
+/// later call.
+fn lock_processes() -> MutexGuard<'static, HashMap<String, ClaudeProcess>> {
+    PROCESSES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Run ids of processes that were interrupted and replaced by a new run.
+/// Their exit must not emit `claude-done`, since the session is still running.
+static INTERRUPTED_RUNS: once_cell::sync::Lazy<Mutex<HashSet<String>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Run ids replaced by an interrupt whose output is still being read. Their
+/// remaining messages are dropped so they can't land in the new run's
+/// transcript, stats or turn count. Cleared when the run's reader finishes.
+static SUPERSEDED_RUNS: once_cell::sync::Lazy<Mutex<HashSet<String>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Sessions whose agent has been asked to wind down cooperatively.
+/// Polled via `GET /api/session/:id/should-stop`; cleared when a new run starts.
+static STOP_REQUESTS: once_cell::sync::Lazy<Mutex<HashSet<String>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Serializes queue draining, so runs finishing together can't both claim
+/// the same free slot
+static QUEUE_DRAIN: once_cell::sync::Lazy<tokio::sync::Mutex<()>> =
+    once_cell::sync::Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// Settings key for how many Claude runs `queue_claude_run` lets run at
+/// once. Unset or 0 means no limit.
+const MAX_CONCURRENT_RUNS_KEY: &str = "max_concurrent_runs";
+
+/// Turn the next streamed message of each session belongs to. Kept outside
+/// `PROCESSES` so it carries across runs; seeded from the persisted results.
+static TURN_INDEXES: once_cell::sync::Lazy<Mutex<HashMap<String, u64>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Max characters of the launching prompt kept for display
+const PROMPT_PREVIEW_LEN: usize = 80;
+
+/// How long a CLI run waits for the user to answer a permission prompt
+const PERMISSION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How often a running session's stats are sent as `claude-stats` (when changed)
+const STATS_EMIT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How a process was launched, so an interrupt can restart it the same way
+#[derive(Debug, Clone)]
+enum Launcher {
+    Cli {
+        env: Option<HashMap<String, String>>,
+        clean_env: bool,
+        permission_prompt: bool,
+    },
+    Agent {
+        permission_mode: Option<String>,
+    },
+}
+
+/// Handle used to kill a running process
+enum ProcessKiller {
+    /// CLI child, shared with the thread waiting for its exit
+    Cli(Arc<Mutex<Child>>),
+    Sidecar(CommandChild),
+}
+
+impl ProcessKiller {
+    fn kill(self) -> Result<(), String> {
+        match self {
+            ProcessKiller::Cli(child) => child
+                .lock()
+                .map_err(|e| e.to_string())?
+                .kill()
+                .map_err(|e| format!("Failed to kill claude: {}", e)),
+            ProcessKiller::Sidecar(child) => child
+                .kill()
+                .map_err(|e| format!("Failed to kill sidecar: {}", e)),
+        }
+    }
+}
+
 /// A running Claude process with its stdin channel
 struct ClaudeProcess {
     stdin_tx: mpsc::UnboundedSender<String>,
-    // We don't store the Child directly since it's moved to the spawned thread
+    /// Unique per run, so a replaced run can't clobber its successor's entry
+    run_id: String,
+    /// Truncated launching prompt, for display
+    prompt_preview: String,
+    started_at: DateTime<Utc>,
+    cwd: String,
+    launcher: Launcher,
+    killer: Option<ProcessKiller>,
+    stats: RunningStats,
+    /// Id of the last assistant message counted in `stats`
+    last_assistant_id: Option<String>,
+}
+
+impl ClaudeProcess {
+    fn new(
+        stdin_tx: mpsc::UnboundedSender<String>,
+        prompt: &str,
+        cwd: &str,
+        launcher: Launcher,
+        killer: Option<ProcessKiller>,
+    ) -> Self {
+        let mut prompt_preview: String = prompt.chars().take(PROMPT_PREVIEW_LEN).collect();
+        if prompt.chars().count() > PROMPT_PREVIEW_LEN {
+            prompt_preview.push_str("...");
+        }
+        ClaudeProcess {
+            stdin_tx,
+            run_id: uuid::Uuid::new_v4().to_string(),
+            prompt_preview,
+            started_at: Utc::now(),
+            cwd: cwd.to_string(),
+            launcher,
+            killer,
+            stats: RunningStats::default(),
+            last_assistant_id: None,
+        }
+    }
+}
+
+/// Max stderr lines kept per run for the consolidated error
+const STDERR_BUFFER_LINES: usize = 200;
+
+/// Captured stderr for a run, reported once if the process fails
+#[derive(Default)]
+struct StderrBuffer {
+    lines: VecDeque<String>,
+}
+
+impl StderrBuffer {
+    /// Record a line, skipping immediate repeats and keeping only the tail
+    fn push(&mut self, line: &str) {
+        if self.lines.back().is_some_and(|last| last == line) {
+            return;
+        }
+        if self.lines.len() == STDERR_BUFFER_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.to_string());
+    }
+
+    fn joined(&self) -> String {
+        self.lines.iter().cloned().collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Emit a single `claude-error` for a run that exited unsuccessfully. A
+/// non-zero exit code (not a kill) also puts the session into "error".
+fn emit_run_failure(app: &AppHandle, session_id: &str, exit_code: Option<i32>, stderr: &str) {
+    if exit_code == Some(0) {
+        return;
+    }
+    let error = if stderr.trim().is_empty() {
+        match exit_code {
+            Some(code) => format!("Process exited with code {}", code),
+            None => "Process was terminated by a signal".to_string(),
+        }
+    } else {
+        stderr.to_string()
+    };
+    if exit_code.is_some() {
+        if let Err(e) = crate::db::set_session_error(session_id, &error) {
+            error!("[ClaudeHeadless] Failed to store session error: {}", e);
+        }
+    }
+    let failed = ClaudeRunFailed {
+        session_id: session_id.to_string(),
+        exit_code,
+        error,
+    };
+    if let Err(e) = app.emit("claude-error", &failed) {
+        warn!("[ClaudeHeadless] Failed to emit error event: {}", e);
+    }
+}
+
+fn lock_superseded_runs() -> MutexGuard<'static, HashSet<String>> {
+    SUPERSEDED_RUNS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Whether an interrupt replaced this run, so its output should be ignored
+fn is_superseded(run_id: &str) -> bool {
+    lock_superseded_runs().contains(run_id)
+}
+
+/// Remove a finished run from the registry. Returns false if the run was
+/// superseded by an interrupt, in which case no done event should be sent.
+fn finish_run(session_id: &str, run_id: &str) -> bool {
+    if let Ok(mut interrupted) = INTERRUPTED_RUNS.lock() {
+        if interrupted.remove(run_id) {
+            return false;
+        }
+    }
+    let mut processes = lock_processes();
+    if processes
+        .get(session_id)
+        .is_some_and(|p| p.run_id == run_id)
+    {
+        processes.remove(session_id);
+    }
+    true
+}
+
+/// Withdraw the permission prompts of a session whose process has exited,
+/// so the UI doesn't keep showing them
+fn cancel_session_permissions(app: &AppHandle, session_id: &str) {
+    for request_id in permissions::get_pending_for_session(session_id) {
+        let Some(request) = permissions::cancel_pending(&request_id) else {
+            continue;
+        };
+        info!(
+            "[ClaudeHeadless] Cancelled permission request {} for exited session {}",
+            request_id, session_id
+        );
+        let cancelled = PermissionCancelled {
+            request_id: request.request_id,
+            session_id: request.session_id,
+        };
+        if let Err(e) = app.emit("permission-cancelled", &cancelled) {
+            warn!("[ClaudeHeadless] Failed to emit permission cancel: {}", e);
+        }
+    }
+}
+
+fn lock_turn_indexes() -> MutexGuard<'static, HashMap<String, u64>> {
+    TURN_INDEXES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Start a session's turn counter after its persisted results, unless this
+/// app run already has one. Called before a run starts streaming.
+fn seed_turn_index(session_id: &str) {
+    let mut turn_indexes = lock_turn_indexes();
+    if turn_indexes.contains_key(session_id) {
+        return;
+    }
+    let completed = crate::db::count_run_results(session_id).unwrap_or_else(|e| {
+        warn!("[ClaudeHeadless] Failed to count past turns: {}", e);
+        0
+    });
+    turn_indexes.insert(session_id.to_string(), completed);
+}
+
+/// Turn index for a streamed message. A result closes its turn, so the
+/// following message (in this run or the next) starts the next one.
+fn next_turn_index(session_id: &str, msg: &ClaudeMessage) -> u64 {
+    let mut turn_indexes = lock_turn_indexes();
+    let turn_index = turn_indexes.entry(session_id.to_string()).or_insert(0);
+    let current = *turn_index;
+    if matches!(msg, ClaudeMessage::Result { .. }) {
+        *turn_index += 1;
+    }
+    current
+}
+
+/// Close a turn that ended without a result (e.g. an interrupted run)
+fn advance_turn_index(session_id: &str) {
+    *lock_turn_indexes()
+        .entry(session_id.to_string())
+        .or_insert(0) += 1;
+}
+
+/// Add a streamed message to its run's counters
+fn update_running_stats(session_id: &str, msg: &ClaudeMessage) {
+    let mut processes = lock_processes();
+    let Some(process) = processes.get_mut(session_id) else {
+        return;
+    };
+    match msg {
+        ClaudeMessage::Assistant { message, .. } => {
+            process.stats.tool_uses += message
+                .content
+                .iter()
+                .filter(|block| matches!(block, ContentBlock::ToolUse { .. }))
+                .count() as u64;
+
+            // The CLI sends each content block as its own message, repeating
+            // the API message's id and usage
+            if message.id.is_some() && message.id == process.last_assistant_id {
+                return;
+            }
+            process.last_assistant_id = message.id.clone();
+            process.stats.assistant_messages += 1;
+            if let Some(usage) = message.extra.get("usage") {
+                let tokens = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+                process.stats.input_tokens += tokens("input_tokens")
+                    + tokens("cache_creation_input_tokens")
+                    + tokens("cache_read_input_tokens");
+                process.stats.output_tokens += tokens("output_tokens");
+            }
+        }
+        ClaudeMessage::Result {
+            total_cost_usd: Some(cost_usd),
+            ..
+        } => process.stats.cost_usd = Some(*cost_usd),
+        _ => {}
+    }
+}
+
+/// Send `claude-stats` every `STATS_EMIT_INTERVAL` while the run is alive,
+/// skipping intervals where nothing changed
+fn spawn_stats_emitter(app: AppHandle, session_id: String, run_id: String) {
+    std::thread::spawn(move || {
+        let mut last_sent: Option<RunningStats> = None;
+        loop {
+            std::thread::sleep(STATS_EMIT_INTERVAL);
+            let stats = match lock_processes().get(&session_id) {
+                Some(process) if process.run_id == run_id => process.stats.clone(),
+                _ => break, // Finished or replaced by an interrupt
+            };
+            if last_sent.as_ref() == Some(&stats) {
+                continue;
+            }
+            let event = ClaudeStats {
+                session_id: session_id.clone(),
+                stats: stats.clone(),
+            };
+            if let Err(e) = app.emit("claude-stats", &event) {
+                warn!("[ClaudeHeadless] Failed to emit stats event: {}", e);
+            }
+            last_sent = Some(stats);
+        }
+    });
+}
+
+/// Store an assistant/user/result message in the session's transcript so it
+/// survives restarts, returning its seq. System messages are not kept.
+/// Result messages also record the run's cost.
+fn persist_run_message(session_id: &str, msg: &ClaudeMessage) -> Option<i64> {
+    if let ClaudeMessage::Result {
+        total_cost_usd: Some(cost_usd),
+        duration_ms,
+        ..
+    } = msg
+    {
+        let duration_ms = duration_ms.unwrap_or(0.0) as i64;
+        if let Err(e) = crate::db::record_session_cost(session_id, *cost_usd, duration_ms) {
+            warn!("[ClaudeHeadless] Failed to record cost: {}", e);
+        }
+    }
+
+    let role = match msg {
+        ClaudeMessage::User { .. } => "user",
+        ClaudeMessage::Assistant { .. } => "assistant",
+        ClaudeMessage::Result { .. } => "result",
+        ClaudeMessage::System { .. } => return None,
+    };
+    let content_json = match serde_json::to_string(msg) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("[ClaudeHeadless] Failed to serialize message: {}", e);
+            return None;
+        }
+    };
+    match crate::db::append_run_message(session_id, role, &content_json) {
+        Ok(seq) => Some(seq),
+        Err(e) => {
+            error!("[ClaudeHeadless] Failed to persist message: {}", e);
+            None
+        }
+    }
+}
+
+/// A control request the CLI sends on stdout when run with
+/// `--permission-prompt-tool stdio`
+#[derive(Debug, Deserialize)]
+struct ControlRequest {
+    #[serde(rename = "type")]
+    kind: String,
+    request_id: String,
+    request: ControlRequestBody,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "subtype")]
+enum ControlRequestBody {
+    #[serde(rename = "can_use_tool")]
+    CanUseTool {
+        tool_name: String,
+        input: serde_json::Value,
+        #[serde(default)]
+        tool_use_id: Option<String>,
+    },
+    #[serde(other)]
+    Unsupported,
+}
+
+fn parse_control_request(line: &str) -> Option<ControlRequest> {
+    serde_json::from_str::<ControlRequest>(line)
+        .ok()
+        .filter(|request| request.kind == "control_request")
+}
+
+/// Answer a CLI control request over stdin
+fn send_control_response(
+    stdin_tx: &mpsc::UnboundedSender<String>,
+    request_id: &str,
+    response: serde_json::Value,
+) {
+    let line = serde_json::json!({
+        "type": "control_response",
+        "response": { "subtype": "success", "request_id": request_id, "response": response },
+    });
+    if stdin_tx.send(line.to_string()).is_err() {
+        warn!(
+            "[ClaudeHeadless] stdin closed before control response {}",
+            request_id
+        );
+    }
+}
+
+/// The CLI's permission result for a user's decision. A denial carries the
+/// user's reason back to Claude.
+fn permission_result(
+    response: &PermissionResponse,
+    input: &serde_json::Value,
+) -> serde_json::Value {
+    match response.behavior {
+        PermissionBehavior::Allow => serde_json::json!({
+            "behavior": "allow",
+            "updatedInput": input,
+        }),
+        PermissionBehavior::Deny => serde_json::json!({
+            "behavior": "deny",
+            "message": response.message.as_deref().unwrap_or("User denied permission"),
+            "interrupt": response.interrupt.unwrap_or(false),
+        }),
+    }
+}
+
+fn denial(request_id: &str, message: &str) -> PermissionResponse {
+    PermissionResponse {
+        request_id: request_id.to_string(),
+        behavior: PermissionBehavior::Deny,
+        message: Some(message.to_string()),
+        interrupt: Some(true),
+        always_allow: None,
+    }
+}
+
+/// Ask the UI whether a tool may run, then answer the CLI once the user
+/// responds via `respond_to_permission` (or the request times out)
+fn request_permission(
+    app: &AppHandle,
+    session_id: &str,
+    control: ControlRequest,
+    stdin_tx: mpsc::UnboundedSender<String>,
+) {
+    let ControlRequestBody::CanUseTool {
+        tool_name,
+        input,
+        tool_use_id,
+    } = control.request
+    else {
+        warn!(
+            "[ClaudeHeadless] Unsupported control request {}",
+            control.request_id
+        );
+        let line = serde_json::json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "error",
+                "request_id": control.request_id,
+                "error": "Unsupported control request",
+            },
+        });
+        let _ = stdin_tx.send(line.to_string());
+        return;
+    };
+
+    if permissions::is_always_allowed(session_id, &tool_name) {
+        info!(
+            "[ClaudeHeadless] Tool {} auto-allowed for session {}",
+            tool_name, session_id
+        );
+        let allow = serde_json::json!({ "behavior": "allow", "updatedInput": input });
+        send_control_response(&stdin_tx, &control.request_id, allow);
+        return;
+    }
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let request = PermissionRequest {
+        request_id: request_id.clone(),
+        session_id: session_id.to_string(),
+        tool_name: tool_name.clone(),
+        tool_input: input.clone(),
+        tool_use_id: tool_use_id.unwrap_or_default(),
+        description: None,
+    };
+    info!(
+        "[ClaudeHeadless] Permission request for tool {} in session {}",
+        tool_name, session_id
+    );
+
+    // Register before emitting so a fast answer always finds the request
+    let (response_tx, response_rx) = oneshot::channel::<PermissionResponse>();
+    permissions::add_pending(
+        request_id.clone(),
+        PendingPermission {
+            request: request.clone(),
+            response_tx,
+        },
+    );
+    if let Err(e) = app.emit("permission-request", &request) {
+        warn!("[ClaudeHeadless] Failed to emit permission request: {}", e);
+        permissions::take_pending(&request_id);
+        let response = denial(&request_id, "Permission prompt could not be shown");
+        send_control_response(
+            &stdin_tx,
+            &control.request_id,
+            permission_result(&response, &input),
+        );
+        return;
+    }
+
+    let session_id = session_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let response = match tokio::time::timeout(PERMISSION_TIMEOUT, response_rx).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => denial(&request_id, "Permission request was cancelled"),
+            Err(_) => {
+                permissions::take_pending(&request_id);
+                info!(
+                    "[ClaudeHeadless] Permission request {} timed out",
+                    request_id
+                );
+                denial(&request_id, "Permission request timed out")
+            }
+        };
+
+        if response.always_allow == Some(true) && response.behavior == PermissionBehavior::Allow {
+            permissions::set_always_allowed(&session_id, &tool_name);
+            info!(
+                "[ClaudeHeadless] Tool {} now always-allowed for session {}",
+                tool_name, session_id
+            );
+        }
+        info!(
+            "[ClaudeHeadless] Permission response for {}: {:?}",
+            request_id, response.behavior
+        );
+        send_control_response(
+            &stdin_tx,
+            &control.request_id,
+            permission_result(&response, &input),
+        );
+    });
+}
+
+/// Messages already emitted by a running session, for a reconnecting frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveTranscript {
+    pub messages: Vec<ClaudeMessage>,
+    /// Seq of the last persisted message when the replay was read. Live
+    /// `claude-message` events with a `seq` at or below this are already in
+    /// `messages` and should be dropped.
+    pub last_seq: Option<i64>,
+}
+
+/// Details of a running Claude process for the "running sessions" panel
+#[derive(Debug, Clone, Serialize)]
+pub struct RunningSessionInfo {
+    pub session_id: String,
+    pub prompt_preview: String,
+    pub started_at: String,
+    pub elapsed_secs: u64,
+}
+
+/// Counters for a run so far, for a progress indicator between tool calls
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RunningStats {
+    pub tool_uses: u64,
+    pub assistant_messages: u64,
+    /// Includes cache reads and writes
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Latest total reported by a result; None until the first one
+    pub cost_usd: Option<f64>,
+}
+
+/// Periodic stats event sent to frontend while a run streams
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaudeStats {
+    pub session_id: String,
+    pub stats: RunningStats,
 }
 
 /// JSON message types from Claude's stream-json output
@@ -118,6 +710,11 @@ pub enum ContentBlock {
 #[derive(Debug, Clone, Serialize)]
 pub struct ClaudeEvent {
     pub session_id: String,
+    /// Groups the messages of one turn; increments after each result
+    pub turn_index: u64,
+    /// Position in the persisted transcript; None for messages that aren't
+    /// kept (system) or failed to save
+    pub seq: Option<i64>,
     pub message: ClaudeMessage,
 }
 
@@ -128,6 +725,15 @@ pub struct ClaudeError {
     pub error: String,
 }
 
+/// Consolidated failure event sent to frontend when a run exits non-zero
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaudeRunFailed {
+    pub session_id: String,
+    pub exit_code: Option<i32>,
+    /// Full captured stderr (deduplicated), or a fallback description
+    pub error: String,
+}
+
 /// Done event sent to frontend
 #[derive(Debug, Clone, Serialize)]
 pub struct ClaudeDone {
@@ -135,37 +741,142 @@ pub struct ClaudeDone {
     pub exit_code: Option<i32>,
 }
 
+/// Interrupted event sent to frontend when a run is redirected
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaudeInterrupted {
+    pub session_id: String,
+    pub new_prompt: String,
+}
+
+/// Try common paths for the claude binary, falling back to a PATH lookup.
+/// GUI apps on macOS don't inherit the shell's PATH, hence the explicit checks.
+fn resolve_claude_path() -> &'static str {
+    if std::path::Path::new("/opt/homebrew/bin/claude").exists() {
+        "/opt/homebrew/bin/claude"
+    } else if std::path::Path::new("/usr/local/bin/claude").exists() {
+        "/usr/local/bin/claude"
+    } else {
+        "claude" // fallback to PATH
+    }
+}
+
+/// Where the claude CLI was found and the version it reports
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaudeCliInfo {
+    pub path: String,
+    pub version: String,
+}
+
+/// Confirm the claude CLI is installed and report its location and version,
+/// so launch problems surface in diagnostics instead of mid-spawn
+#[tauri::command]
+pub async fn check_claude_cli() -> Result<ClaudeCliInfo, String> {
+    let claude_path = resolve_claude_path();
+
+    let output = Command::new(claude_path)
+        .arg("--version")
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                "Claude CLI not installed: `claude` was not found in /opt/homebrew/bin, /usr/local/bin or PATH"
+                    .to_string()
+            }
+            _ => format!("Failed to run {} --version: {}", claude_path, e),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("claude --version failed: {}", stderr.trim()));
+    }
+
+    // e.g. "1.0.33 (Claude Code)"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout
+        .split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+        .ok_or_else(|| format!("Unrecognized claude --version output: {}", stdout.trim()))?
+        .to_string();
+
+    let path = if claude_path == "claude" {
+        find_in_path(claude_path)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| claude_path.to_string())
+    } else {
+        claude_path.to_string()
+    };
+
+    info!("[ClaudeHeadless] Found claude {} at {}", version, path);
+    Ok(ClaudeCliInfo { path, version })
+}
+
+/// Full path of the first executable named `name` on PATH
+fn find_in_path(name: &str) -> Option<std::path::PathBuf> {
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Resolve the Claude uuid to `--resume`, either given directly or looked up
+/// from the app session id it was stored under
+fn resolve_resume_id(
+    resume_id: Option<String>,
+    resume_from_session: Option<String>,
+) -> Result<Option<String>, String> {
+    match (resume_id, resume_from_session) {
+        (Some(_), Some(_)) => {
+            Err("Pass either resume_id or resume_from_session, not both".to_string())
+        }
+        (Some(id), None) => Ok(Some(id)),
+        (None, Some(app_session_id)) => crate::db::get_session_claude_id(&app_session_id)
+            .map_err(|e| e.to_string())?
+            .map(Some)
+            .ok_or_else(|| {
+                format!(
+                    "Session {} has never been run headlessly (no Claude session id stored)",
+                    app_session_id
+                )
+            }),
+        (None, None) => Ok(None),
+    }
+}
+
 /// Start a new Claude headless session
+///
+/// To continue a previous conversation, pass either Claude's own `resume_id`
+/// or `resume_from_session` (an app session id whose stored Claude uuid is used).
+///
+/// `env` is applied on top of the inherited environment (e.g. a per-session
+/// `ANTHROPIC_BASE_URL`); its values are never logged. With `clean_env`, only
+/// PATH and HOME are inherited, for a reproducible environment.
+///
+/// With `permission_prompt`, tools that need approval raise a
+/// `permission-request` event and wait for `respond_to_permission`; the
+/// prompt and the answers are sent to Claude as stream-json on stdin.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn start_claude_headless(
     app: AppHandle,
     session_id: String,
     prompt: String,
     cwd: String,
     resume_id: Option<String>,
+    resume_from_session: Option<String>,
+    env: Option<HashMap<String, String>>,
+    clean_env: Option<bool>,
+    permission_prompt: Option<bool>,
 ) -> Result<(), String> {
-    // Check if process already running for this session
-    {
-        let processes = PROCESSES.lock().map_err(|e| e.to_string())?;
-        if processes.contains_key(&session_id) {
-            return Err(format!(
-                "Claude process already running for session {}",
-                session_id
-            ));
-        }
-    }
+    let clean_env = clean_env.unwrap_or(false);
+    let permission_prompt = permission_prompt.unwrap_or(false);
+    let resume_id = resolve_resume_id(resume_id, resume_from_session)?;
+
+    check_can_start(&app, &session_id)?;
+    clear_stop_request(&session_id);
 
     // Build command - use full path to claude
-    // Try common paths for claude binary
-    let claude_path = if std::path::Path::new("/opt/homebrew/bin/claude").exists() {
-        "/opt/homebrew/bin/claude"
-    } else if std::path::Path::new("/usr/local/bin/claude").exists() {
-        "/usr/local/bin/claude"
-    } else {
-        "claude" // fallback to PATH
-    };
+    let claude_path = resolve_claude_path();
 
-    println!("[ClaudeHeadless] Using claude at: {}", claude_path);
+    info!("[ClaudeHeadless] Using claude at: {}", claude_path);
 
     let mut cmd = Command::new(claude_path);
     // --print (-p) means print response and exit
@@ -177,51 +888,102 @@ pub async fn start_claude_headless(
         cmd.args(["--resume", id]);
     }
 
-    // Add the prompt as a positional argument at the end
-    cmd.arg(&prompt);
+    if permission_prompt {
+        // The prompt goes over stdin instead, which stays open for permission answers
+        cmd.args([
+            "--input-format",
+            "stream-json",
+            "--permission-prompt-tool",
+            "stdio",
+        ]);
+    } else {
+        // Add the prompt as a positional argument at the end
+        cmd.arg(&prompt);
+    }
 
-    println!(
+    debug!(
         "[ClaudeHeadless] Running: {} --print --output-format stream-json --verbose '{}'",
         claude_path,
         &prompt[..prompt.len().min(50)]
     );
 
-    // Inherit all environment variables from parent, then override specific ones
     cmd.current_dir(&cwd)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .envs(std::env::vars()) // Inherit ALL parent environment
-        .env("TERM", "xterm-256color")
+        .stderr(Stdio::piped());
+
+    if clean_env {
+        // Start from nothing but what's needed to find binaries and config
+        cmd.env_clear();
+        for key in ["PATH", "HOME"] {
+            if let Some(value) = std::env::var_os(key) {
+                cmd.env(key, value);
+            }
+        }
+        info!("[ClaudeHeadless] Using clean environment");
+    } else {
+        cmd.envs(std::env::vars()); // Inherit ALL parent environment
+    }
+
+    // Then override specific ones
+    cmd.env("TERM", "xterm-256color")
         .env("LANG", "en_US.UTF-8")
         .env("LC_ALL", "en_US.UTF-8");
 
+    // Per-session overrides win over everything inherited
+    if let Some(ref overrides) = env {
+        if !overrides.is_empty() {
+            let mut keys: Vec<&String> = overrides.keys().collect();
+            keys.sort();
+            debug!("[ClaudeHeadless] Env overrides: {:?}", keys);
+            cmd.envs(overrides);
+        }
+    }
+
     // Spawn process
     let mut child = cmd
         .spawn()
         .map_err(|e| format!("Failed to spawn claude: {}", e))?;
-    println!(
+    info!(
         "[ClaudeHeadless] Spawned process with PID: {:?}",
         child.id()
     );
 
-    // Take stdin - we'll drop it immediately for --print mode
-    // (Claude doesn't need stdin in print mode)
-    let stdin = child.stdin.take();
-    drop(stdin); // Close stdin to signal we won't send more input
-    println!("[ClaudeHeadless] Closed stdin (not needed for --print mode)");
-
-    // Create channel for sending input to stdin (for future multi-turn support)
-    let (stdin_tx, _stdin_rx) = mpsc::unbounded_channel::<String>();
-
-    // Store process reference
-    {
-        let mut processes = PROCESSES.lock().map_err(|e| e.to_string())?;
-        processes.insert(session_id.clone(), ClaudeProcess { stdin_tx });
-    }
+    // Create channel for sending input to stdin
+    let (stdin_tx, mut stdin_rx) = mpsc::unbounded_channel::<String>();
 
-    let session_id_clone = session_id.clone();
-    let app_clone = app.clone();
+    let stdin = child.stdin.take();
+    let stdin: Arc<Mutex<Option<ChildStdin>>> = if permission_prompt {
+        let stdin = Arc::new(Mutex::new(stdin));
+        let stdin_writer = stdin.clone();
+        std::thread::spawn(move || {
+            while let Some(line) = stdin_rx.blocking_recv() {
+                let Ok(mut guard) = stdin_writer.lock() else {
+                    break;
+                };
+                // None once the run's result arrived and stdin was closed
+                let Some(stdin) = guard.as_mut() else {
+                    break;
+                };
+                if let Err(e) = writeln!(stdin, "{}", line).and_then(|_| stdin.flush()) {
+                    warn!("[ClaudeHeadless] Failed to write to stdin: {}", e);
+                    break;
+                }
+            }
+        });
+        let user_message = serde_json::json!({
+            "type": "user",
+            "message": { "role": "user", "content": prompt },
+        });
+        let _ = stdin_tx.send(user_message.to_string());
+        stdin
+    } else {
+        // Claude doesn't need stdin in print mode
+        drop(stdin); // Close stdin to signal we won't send more input
+        debug!("[ClaudeHeadless] Closed stdin (not needed for --print mode)");
+        Arc::new(Mutex::new(None))
+    };
+    let stdin_tx_stdout = stdin_tx.clone();
 
     // Take stdout for reading
     let stdout = child
@@ -235,40 +997,88 @@ pub async fn start_claude_headless(
         .take()
         .ok_or_else(|| "Failed to capture stderr".to_string())?;
 
+    // Share the child with the exit watcher so it can still be killed on interrupt
+    let child = Arc::new(Mutex::new(child));
+
+    seed_turn_index(&session_id);
+
+    // Store process reference
+    let process = ClaudeProcess::new(
+        stdin_tx,
+        &prompt,
+        &cwd,
+        Launcher::Cli {
+            env,
+            clean_env,
+            permission_prompt,
+        },
+        Some(ProcessKiller::Cli(child.clone())),
+    );
+    let run_id = process.run_id.clone();
+    {
+        let mut processes = lock_processes();
+        processes.insert(session_id.clone(), process);
+    }
+    spawn_stats_emitter(app.clone(), session_id.clone(), run_id.clone());
+
+    let session_id_clone = session_id.clone();
+    let app_clone = app.clone();
+
     let session_id_stdout = session_id.clone();
     let app_stdout = app.clone();
+    let run_id_stdout = run_id.clone();
 
     // Spawn stdout reader thread
     std::thread::spawn(move || {
-        println!("[ClaudeHeadless] stdout reader thread started");
+        debug!("[ClaudeHeadless] stdout reader thread started");
         let reader = BufReader::new(stdout);
         for line in reader.lines() {
             match line {
                 Ok(line) if line.is_empty() => {
-                    println!("[ClaudeHeadless] Skipping empty line");
+                    debug!("[ClaudeHeadless] Skipping empty line");
                     continue;
                 }
+                Ok(_) if is_superseded(&run_id_stdout) => break,
                 Ok(line) => {
-                    println!(
+                    debug!(
                         "[ClaudeHeadless] Got line: {}",
                         &line[..line.len().min(200)]
                     );
+                    if let Some(control) = parse_control_request(&line) {
+                        request_permission(
+                            &app_stdout,
+                            &session_id_stdout,
+                            control,
+                            stdin_tx_stdout.clone(),
+                        );
+                        continue;
+                    }
                     // Parse JSON line
                     match serde_json::from_str::<ClaudeMessage>(&line) {
                         Ok(msg) => {
-                            println!("[ClaudeHeadless] Parsed message type: {:?}", msg);
+                            debug!("[ClaudeHeadless] Parsed message type: {:?}", msg);
+                            if matches!(msg, ClaudeMessage::Result { .. }) {
+                                // The run is over; EOF lets a stream-json input run exit
+                                if let Ok(mut stdin) = stdin.lock() {
+                                    stdin.take();
+                                }
+                            }
+                            let seq = persist_run_message(&session_id_stdout, &msg);
+                            update_running_stats(&session_id_stdout, &msg);
                             let event = ClaudeEvent {
                                 session_id: session_id_stdout.clone(),
+                                turn_index: next_turn_index(&session_id_stdout, &msg),
+                                seq,
                                 message: msg,
                             };
                             // Emit to frontend
                             if let Err(e) = app_stdout.emit("claude-message", &event) {
-                                eprintln!("[ClaudeHeadless] Failed to emit event: {}", e);
+                                warn!("[ClaudeHeadless] Failed to emit event: {}", e);
                             }
                         }
                         Err(e) => {
                             // Log parse error but continue
-                            eprintln!(
+                            warn!(
                                 "[ClaudeHeadless] JSON parse error: {} for line: {}",
                                 e, line
                             );
@@ -276,34 +1086,41 @@ pub async fn start_claude_headless(
                     }
                 }
                 Err(e) => {
-                    eprintln!("[ClaudeHeadless] Read error: {}", e);
+                    warn!("[ClaudeHeadless] Read error: {}", e);
                     break;
                 }
             }
         }
+        lock_superseded_runs().remove(&run_id_stdout);
     });
 
     let session_id_stderr = session_id.clone();
     let app_stderr = app.clone();
+    let stderr_buffer = Arc::new(Mutex::new(StderrBuffer::default()));
+    let stderr_buffer_reader = stderr_buffer.clone();
 
     // Spawn stderr reader thread
-    std::thread::spawn(move || {
+    let stderr_thread = std::thread::spawn(move || {
         let reader = BufReader::new(stderr);
         for line in reader.lines() {
             match line {
                 Ok(line) if line.is_empty() => continue,
                 Ok(line) => {
+                    if let Ok(mut buffer) = stderr_buffer_reader.lock() {
+                        buffer.push(&line);
+                    }
+
                     // Emit stderr as error event
                     let error = ClaudeError {
                         session_id: session_id_stderr.clone(),
                         error: line,
                     };
                     if let Err(e) = app_stderr.emit("claude-stderr", &error) {
-                        eprintln!("[ClaudeHeadless] Failed to emit stderr event: {}", e);
+                        warn!("[ClaudeHeadless] Failed to emit stderr event: {}", e);
                     }
                 }
                 Err(e) => {
-                    eprintln!("[ClaudeHeadless] Stderr read error: {}", e);
+                    warn!("[ClaudeHeadless] Stderr read error: {}", e);
                     break;
                 }
             }
@@ -312,15 +1129,32 @@ pub async fn start_claude_headless(
 
     // Spawn thread to wait for process exit
     std::thread::spawn(move || {
-        let exit_status = child.wait();
-        let exit_code = exit_status.ok().and_then(|s| s.code());
+        // Poll instead of blocking in wait() so the child stays lockable for kill()
+        let exit_code = loop {
+            let status = match child.lock() {
+                Ok(mut child) => child.try_wait(),
+                Err(_) => break None,
+            };
+            match status {
+                Ok(Some(status)) => break status.code(),
+                Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+                Err(_) => break None,
+            }
+        };
 
         // Remove from registry
-        {
-            if let Ok(mut processes) = PROCESSES.lock() {
-                processes.remove(&session_id_clone);
-            }
+        if !finish_run(&session_id_clone, &run_id) {
+            return;
         }
+        cancel_session_permissions(&app_clone, &session_id_clone);
+
+        // Let the stderr reader drain so the consolidated error is complete
+        let _ = stderr_thread.join();
+        let stderr_text = stderr_buffer
+            .lock()
+            .map(|buffer| buffer.joined())
+            .unwrap_or_default();
+        emit_run_failure(&app_clone, &session_id_clone, exit_code, &stderr_text);
 
         // Emit done event
         let done = ClaudeDone {
@@ -328,8 +1162,12 @@ pub async fn start_claude_headless(
             exit_code,
         };
         if let Err(e) = app_clone.emit("claude-done", &done) {
-            eprintln!("[ClaudeHeadless] Failed to emit done event: {}", e);
+            warn!("[ClaudeHeadless] Failed to emit done event: {}", e);
         }
+
+        // Only now, so a queued follow-up for this session can't receive
+        // this run's done event
+        drain_run_queue(app_clone);
     });
 
     Ok(())
@@ -373,16 +1211,8 @@ pub async fn start_claude_agent(
     resume_id: Option<String>,
     permission_mode: Option<String>,
 ) -> Result<(), String> {
-    // Check if process already running for this session
-    {
-        let processes = PROCESSES.lock().map_err(|e| e.to_string())?;
-        if processes.contains_key(&session_id) {
-            return Err(format!(
-                "Claude process already running for session {}",
-                session_id
-            ));
-        }
-    }
+    check_can_start(&app, &session_id)?;
+    clear_stop_request(&session_id);
 
     // Find Claude Code CLI path
     let claude_code_path = if std::path::Path::new("/opt/homebrew/bin/claude").exists() {
@@ -393,6 +1223,10 @@ pub async fn start_claude_agent(
         None // SDK will try to find it
     };
 
+    let launcher = Launcher::Agent {
+        permission_mode: permission_mode.clone(),
+    };
+
     // Build input JSON for the sidecar
     let input = AgentServiceInput {
         action: if resume_id.is_some() {
@@ -400,7 +1234,7 @@ pub async fn start_claude_agent(
         } else {
             "query".to_string()
         },
-        prompt,
+        prompt: prompt.clone(),
         session_id: resume_id, // SDK session ID for resume
         claude_sessions_id: Some(session_id.clone()), // Our session ID for custom tools
         cwd: cwd.clone(),
@@ -415,7 +1249,7 @@ pub async fn start_claude_agent(
     let input_json =
         serde_json::to_string(&input).map_err(|e| format!("Failed to serialize input: {}", e))?;
 
-    println!(
+    debug!(
         "[ClaudeAgent] Starting sidecar with input: {}",
         &input_json[..input_json.len().min(200)]
     );
@@ -424,7 +1258,7 @@ pub async fn start_claude_agent(
     let shell = app.shell();
 
     // Spawn the sidecar
-    let (mut rx, _child) = shell
+    let (mut rx, child) = shell
         .sidecar("agent-service")
         .map_err(|e| format!("Failed to create sidecar command: {}", e))?
         .args([&input_json])
@@ -434,11 +1268,22 @@ pub async fn start_claude_agent(
     // Create channel for sending input (for future multi-turn support)
     let (stdin_tx, _stdin_rx) = mpsc::unbounded_channel::<String>();
 
+    seed_turn_index(&session_id);
+
     // Store process reference
+    let process = ClaudeProcess::new(
+        stdin_tx,
+        &prompt,
+        &cwd,
+        launcher,
+        Some(ProcessKiller::Sidecar(child)),
+    );
+    let run_id = process.run_id.clone();
     {
-        let mut processes = PROCESSES.lock().map_err(|e| e.to_string())?;
-        processes.insert(session_id.clone(), ClaudeProcess { stdin_tx });
+        let mut processes = lock_processes();
+        processes.insert(session_id.clone(), process);
     }
+    spawn_stats_emitter(app.clone(), session_id.clone(), run_id.clone());
 
     let session_id_clone = session_id.clone();
     let app_clone = app.clone();
@@ -447,14 +1292,16 @@ pub async fn start_claude_agent(
     tauri::async_runtime::spawn(async move {
         use tauri_plugin_shell::process::CommandEvent;
 
+        let mut stderr_buffer = StderrBuffer::default();
+
         while let Some(event) = rx.recv().await {
             match event {
                 CommandEvent::Stdout(line) => {
                     let line_str = String::from_utf8_lossy(&line);
-                    if line_str.is_empty() {
+                    if line_str.is_empty() || is_superseded(&run_id) {
                         continue;
                     }
-                    println!(
+                    debug!(
                         "[ClaudeAgent] stdout: {}",
                         &line_str[..line_str.len().min(200)]
                     );
@@ -462,16 +1309,20 @@ pub async fn start_claude_agent(
                     // Parse JSON line
                     match serde_json::from_str::<ClaudeMessage>(&line_str) {
                         Ok(msg) => {
+                            let seq = persist_run_message(&session_id_clone, &msg);
+                            update_running_stats(&session_id_clone, &msg);
                             let event = ClaudeEvent {
                                 session_id: session_id_clone.clone(),
+                                turn_index: next_turn_index(&session_id_clone, &msg),
+                                seq,
                                 message: msg,
                             };
                             if let Err(e) = app_clone.emit("claude-message", &event) {
-                                eprintln!("[ClaudeAgent] Failed to emit event: {}", e);
+                                warn!("[ClaudeAgent] Failed to emit event: {}", e);
                             }
                         }
                         Err(e) => {
-                            eprintln!(
+                            warn!(
                                 "[ClaudeAgent] JSON parse error: {} for line: {}",
                                 e, line_str
                             );
@@ -483,26 +1334,35 @@ pub async fn start_claude_agent(
                     if line_str.is_empty() {
                         continue;
                     }
-                    eprintln!("[ClaudeAgent] stderr: {}", line_str);
+                    warn!("[ClaudeAgent] stderr: {}", line_str);
+                    stderr_buffer.push(line_str.trim_end());
 
                     let error = ClaudeError {
                         session_id: session_id_clone.clone(),
                         error: line_str.to_string(),
                     };
                     if let Err(e) = app_clone.emit("claude-stderr", &error) {
-                        eprintln!("[ClaudeAgent] Failed to emit stderr event: {}", e);
+                        warn!("[ClaudeAgent] Failed to emit stderr event: {}", e);
                     }
                 }
                 CommandEvent::Terminated(payload) => {
-                    println!(
+                    info!(
                         "[ClaudeAgent] Process terminated with code: {:?}",
                         payload.code
                     );
 
                     // Remove from registry
-                    if let Ok(mut processes) = PROCESSES.lock() {
-                        processes.remove(&session_id_clone);
+                    if !finish_run(&session_id_clone, &run_id) {
+                        break;
                     }
+                    cancel_session_permissions(&app_clone, &session_id_clone);
+
+                    emit_run_failure(
+                        &app_clone,
+                        &session_id_clone,
+                        payload.code,
+                        &stderr_buffer.joined(),
+                    );
 
                     // Emit done event
                     let done = ClaudeDone {
@@ -510,13 +1370,15 @@ pub async fn start_claude_agent(
                         exit_code: payload.code,
                     };
                     if let Err(e) = app_clone.emit("claude-done", &done) {
-                        eprintln!("[ClaudeAgent] Failed to emit done event: {}", e);
+                        warn!("[ClaudeAgent] Failed to emit done event: {}", e);
                     }
+                    drain_run_queue(app_clone.clone());
                     break;
                 }
                 _ => {}
             }
         }
+        lock_superseded_runs().remove(&run_id);
     });
 
     Ok(())
@@ -525,7 +1387,7 @@ pub async fn start_claude_agent(
 /// Send input to a running Claude session (for multi-turn conversations)
 #[tauri::command]
 pub async fn send_claude_input(session_id: String, input: String) -> Result<(), String> {
-    let processes = PROCESSES.lock().map_err(|e| e.to_string())?;
+    let processes = lock_processes();
 
     let process = processes
         .get(&session_id)
@@ -542,7 +1404,7 @@ pub async fn send_claude_input(session_id: String, input: String) -> Result<(),
 /// Stop a running Claude session
 #[tauri::command]
 pub async fn stop_claude_session(session_id: String) -> Result<(), String> {
-    let mut processes = PROCESSES.lock().map_err(|e| e.to_string())?;
+    let mut processes = lock_processes();
 
     if processes.remove(&session_id).is_some() {
         // Dropping the process will close stdin, which should terminate claude
@@ -555,16 +1417,467 @@ pub async fn stop_claude_session(session_id: String) -> Result<(), String> {
     }
 }
 
+/// Kill every running Claude process and empty the registry.
+/// Runs queued so far are held first, so the freed slots don't start them.
+/// Returns the ids of the sessions that were stopped.
+pub fn stop_all_processes() -> Vec<String> {
+    hold_queued_runs();
+    let drained: Vec<(String, ClaudeProcess)> = lock_processes().drain().collect();
+
+    drained
+        .into_iter()
+        .map(|(session_id, process)| {
+            if let Some(killer) = process.killer {
+                if let Err(e) = killer.kill() {
+                    warn!("[ClaudeHeadless] Failed to stop {}: {}", session_id, e);
+                }
+            }
+            info!("[ClaudeHeadless] Stopped session {}", session_id);
+            session_id
+        })
+        .collect()
+}
+
+/// Stop all running Claude sessions at once. Queued runs stay held until
+/// `resume_queued_runs`.
+#[tauri::command]
+pub async fn stop_all_claude_sessions() -> Result<Vec<String>, String> {
+    Ok(stop_all_processes())
+}
+
+/// Ask a session's agent to stop at its next convenient point.
+/// Unlike `stop_claude_session`, nothing is killed; the agent polls the flag.
+#[tauri::command]
+pub async fn request_session_stop(session_id: String) -> Result<(), String> {
+    let mut requests = STOP_REQUESTS.lock().map_err(|e| e.to_string())?;
+    requests.insert(session_id);
+    Ok(())
+}
+
+/// Whether a stop has been requested for this session
+pub fn should_stop(session_id: &str) -> bool {
+    STOP_REQUESTS
+        .lock()
+        .map(|requests| requests.contains(session_id))
+        .unwrap_or(false)
+}
+
+fn clear_stop_request(session_id: &str) {
+    if let Ok(mut requests) = STOP_REQUESTS.lock() {
+        requests.remove(session_id);
+    }
+}
+
+/// Interrupt a running Claude session and redirect it with a new prompt.
+/// The current run is killed and a new one is started with `--resume` so the
+/// conversation continues. Unlike `stop_claude_session`, this is not a hard stop.
+#[tauri::command]
+pub async fn interrupt_claude_session(
+    app: AppHandle,
+    session_id: String,
+    new_prompt: String,
+) -> Result<(), String> {
+    // Claude's own session uuid is needed to resume the conversation
+    let resume_id = crate::db::get_session_claude_id(&session_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| {
+            format!(
+                "Session {} has no stored Claude session id to resume",
+                session_id
+            )
+        })?;
+
+    let process = {
+        let mut processes = lock_processes();
+        processes
+            .remove(&session_id)
+            .ok_or_else(|| format!("No running Claude process for session {}", session_id))?
+    };
+
+    // Mark the old run as superseded so its exit doesn't emit claude-done
+    // and its remaining output is ignored
+    if let Ok(mut interrupted) = INTERRUPTED_RUNS.lock() {
+        interrupted.insert(process.run_id.clone());
+    }
+    lock_superseded_runs().insert(process.run_id.clone());
+    if let Some(killer) = process.killer {
+        if let Err(e) = killer.kill() {
+            if let Ok(mut interrupted) = INTERRUPTED_RUNS.lock() {
+                interrupted.remove(&process.run_id);
+            }
+            lock_superseded_runs().remove(&process.run_id);
+            return Err(e);
+        }
+    }
+    // The old run's exit skips cleanup, so close any prompt it left open
+    cancel_session_permissions(&app, &session_id);
+
+    info!(
+        "[ClaudeHeadless] Interrupted session {}, resuming {}",
+        session_id, resume_id
+    );
+    let interrupted = ClaudeInterrupted {
+        session_id: session_id.clone(),
+        new_prompt: new_prompt.clone(),
+    };
+    if let Err(e) = app.emit("claude-interrupted", &interrupted) {
+        warn!("[ClaudeHeadless] Failed to emit interrupted event: {}", e);
+    }
+
+    // The interrupted turn never got a result; the resumed run starts the next one
+    advance_turn_index(&session_id);
+    match process.launcher {
+        Launcher::Cli {
+            env,
+            clean_env,
+            permission_prompt,
+        } => {
+            start_claude_headless(
+                app,
+                session_id.clone(),
+                new_prompt,
+                process.cwd,
+                Some(resume_id),
+                None,
+                env,
+                Some(clean_env),
+                Some(permission_prompt),
+            )
+            .await
+        }
+        Launcher::Agent { permission_mode } => {
+            start_claude_agent(
+                app,
+                session_id.clone(),
+                new_prompt,
+                process.cwd,
+                Some(resume_id),
+                permission_mode,
+            )
+            .await
+        }
+    }
+}
+
 /// Check if a Claude session is running
 #[tauri::command]
 pub async fn is_claude_running(session_id: String) -> Result<bool, String> {
-    let processes = PROCESSES.lock().map_err(|e| e.to_string())?;
+    let processes = lock_processes();
     Ok(processes.contains_key(&session_id))
 }
 
 /// Get list of all running Claude session IDs
 #[tauri::command]
 pub async fn get_running_claude_sessions() -> Result<Vec<String>, String> {
-    let processes = PROCESSES.lock().map_err(|e| e.to_string())?;
+    let processes = lock_processes();
     Ok(processes.keys().cloned().collect())
 }
+
+/// Everything the current run of a session has emitted so far (system
+/// messages excepted), so a reloaded frontend can rebuild its transcript
+/// before continuing with live events
+#[tauri::command]
+pub async fn get_live_session_transcript(session_id: String) -> Result<LiveTranscript, String> {
+    let started_at = {
+        let processes = lock_processes();
+        processes
+            .get(&session_id)
+            .map(|process| process.started_at)
+            .ok_or_else(|| format!("No running Claude process for session {}", session_id))?
+    };
+
+    let run_messages = crate::db::get_run_messages(&session_id).map_err(|e| e.to_string())?;
+    let last_seq = run_messages.last().map(|m| m.seq);
+    let messages = run_messages
+        .into_iter()
+        .filter(|m| m.created_at >= started_at)
+        .map(|m| {
+            serde_json::from_str(&m.content_json)
+                .map_err(|e| format!("Corrupt run message {}: {}", m.id, e))
+        })
+        .collect::<Result<Vec<ClaudeMessage>, String>>()?;
+
+    Ok(LiveTranscript { messages, last_seq })
+}
+
+/// Counters for a session's current run
+#[tauri::command]
+pub async fn get_running_session_stats(session_id: String) -> Result<RunningStats, String> {
+    let processes = lock_processes();
+    processes
+        .get(&session_id)
+        .map(|process| process.stats.clone())
+        .ok_or_else(|| format!("No running Claude process for session {}", session_id))
+}
+
+/// Number of running Claude processes
+pub fn running_session_count() -> usize {
+    lock_processes().len()
+}
+
+/// How a queued run is launched once a slot frees up (stored as the
+/// queue entry's `options_json`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "launcher", rename_all = "lowercase")]
+pub enum RunOptions {
+    Cli {
+        #[serde(default)]
+        resume_id: Option<String>,
+        #[serde(default)]
+        env: Option<HashMap<String, String>>,
+        #[serde(default)]
+        clean_env: Option<bool>,
+        #[serde(default)]
+        permission_prompt: Option<bool>,
+    },
+    Agent {
+        #[serde(default)]
+        resume_id: Option<String>,
+        #[serde(default)]
+        permission_mode: Option<String>,
+    },
+}
+
+fn max_concurrent_runs(app: &AppHandle) -> Option<u64> {
+    crate::get_u64_setting(app, MAX_CONCURRENT_RUNS_KEY).filter(|&max| max > 0)
+}
+
+/// Whether another run fits under the `max_concurrent_runs` setting
+fn has_free_slot(app: &AppHandle) -> bool {
+    match max_concurrent_runs(app) {
+        Some(max) => (running_session_count() as u64) < max,
+        None => true,
+    }
+}
+
+/// Refuse a second process for a session, or one over the
+/// `max_concurrent_runs` limit (`queue_claude_run` waits for a slot instead)
+fn check_can_start(app: &AppHandle, session_id: &str) -> Result<(), String> {
+    let max_runs = max_concurrent_runs(app);
+    let processes = lock_processes();
+    if processes.contains_key(session_id) {
+        return Err(format!(
+            "Claude process already running for session {}",
+            session_id
+        ));
+    }
+    if let Some(max) = max_runs {
+        if processes.len() as u64 >= max {
+            return Err(format!(
+                "Already running {} Claude sessions (max_concurrent_runs is {}); queue the run instead",
+                processes.len(),
+                max
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Runs queued before this time are held: left over from a previous launch,
+/// or queued when everything was stopped. They wait for `resume_queued_runs`
+/// rather than starting on their own.
+static HELD_BEFORE: once_cell::sync::Lazy<Mutex<Option<DateTime<Utc>>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// Hold every run queued so far
+fn hold_queued_runs() {
+    *HELD_BEFORE.lock().unwrap_or_else(PoisonError::into_inner) = Some(Utc::now());
+}
+
+fn is_held(run: &crate::db::PendingRun) -> bool {
+    HELD_BEFORE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .is_some_and(|cutoff| run.queued_at < cutoff)
+}
+
+/// Called at startup: hold runs queued by a previous launch and tell the UI
+/// about them with a `claude-queue-restored` event
+pub fn hold_restored_runs(app: &AppHandle) {
+    hold_queued_runs();
+    let restored = match crate::db::get_pending_runs() {
+        Ok(restored) => restored,
+        Err(e) => {
+            warn!("[ClaudeHeadless] Failed to read run queue: {}", e);
+            return;
+        }
+    };
+    if restored.is_empty() {
+        return;
+    }
+    info!(
+        "[ClaudeHeadless] Holding {} run(s) queued before launch",
+        restored.len()
+    );
+    if let Err(e) = app.emit("claude-queue-restored", &restored) {
+        warn!(
+            "[ClaudeHeadless] Failed to emit queue restored event: {}",
+            e
+        );
+    }
+}
+
+/// Let runs left queued by a previous launch start as slots free up
+#[tauri::command]
+pub async fn resume_queued_runs(app: AppHandle) -> Result<(), String> {
+    *HELD_BEFORE.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    drain_run_queue(app);
+    Ok(())
+}
+
+async fn launch_run(
+    app: AppHandle,
+    session_id: String,
+    prompt: String,
+    cwd: String,
+    options: RunOptions,
+) -> Result<(), String> {
+    match options {
+        RunOptions::Cli {
+            resume_id,
+            env,
+            clean_env,
+            permission_prompt,
+        } => {
+            start_claude_headless(
+                app,
+                session_id,
+                prompt,
+                cwd,
+                resume_id,
+                None,
+                env,
+                clean_env,
+                permission_prompt,
+            )
+            .await
+        }
+        RunOptions::Agent {
+            resume_id,
+            permission_mode,
+        } => start_claude_agent(app, session_id, prompt, cwd, resume_id, permission_mode).await,
+    }
+}
+
+/// Start a run now if a slot is free and the session isn't already running;
+/// otherwise queue it to start once one is. Returns the queue entry, or
+/// None if the run started immediately.
+#[tauri::command]
+pub async fn queue_claude_run(
+    app: AppHandle,
+    session_id: String,
+    prompt: String,
+    cwd: String,
+    options: RunOptions,
+) -> Result<Option<crate::db::PendingRun>, String> {
+    // Held across the start so a concurrent drain can't take the same slot
+    let _drain = QUEUE_DRAIN.lock().await;
+
+    let session_running = lock_processes().contains_key(&session_id);
+    if !session_running && has_free_slot(&app) {
+        launch_run(app, session_id, prompt, cwd, options).await?;
+        return Ok(None);
+    }
+
+    let options_json = serde_json::to_string(&options).map_err(|e| e.to_string())?;
+    let run = crate::db::enqueue_run(&session_id, &prompt, &cwd, &options_json)
+        .map_err(|e| e.to_string())?;
+    info!(
+        "[ClaudeHeadless] Queued run {} for session {}",
+        run.id, session_id
+    );
+    Ok(Some(run))
+}
+
+/// Start queued runs, oldest first, while slots are free. Runs for a session
+/// that is still busy wait for that session to finish. A run is only removed
+/// from the queue once it has started; one that fails to start stays queued
+/// (skipped until the next drain) so its prompt isn't lost.
+pub fn drain_run_queue(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let _drain = QUEUE_DRAIN.lock().await;
+        let mut failed: HashSet<String> = HashSet::new();
+
+        while has_free_slot(&app) {
+            let queued = match crate::db::get_pending_runs() {
+                Ok(queued) => queued,
+                Err(e) => {
+                    warn!("[ClaudeHeadless] Failed to read run queue: {}", e);
+                    return;
+                }
+            };
+            let running: HashSet<String> = lock_processes().keys().cloned().collect();
+            let Some(run) = queued.into_iter().find(|run| {
+                !running.contains(&run.session_id) && !failed.contains(&run.id) && !is_held(run)
+            }) else {
+                return;
+            };
+
+            info!(
+                "[ClaudeHeadless] Starting queued run {} for session {}",
+                run.id, run.session_id
+            );
+            let started = match serde_json::from_str::<RunOptions>(&run.options_json) {
+                Ok(options) => {
+                    launch_run(
+                        app.clone(),
+                        run.session_id.clone(),
+                        run.prompt.clone(),
+                        run.cwd.clone(),
+                        options,
+                    )
+                    .await
+                }
+                Err(e) => Err(format!("Invalid run options: {}", e)),
+            };
+            match started {
+                Ok(()) => {
+                    if let Err(e) = crate::db::delete_pending_run(&run.id) {
+                        error!("[ClaudeHeadless] Failed to dequeue run {}: {}", run.id, e);
+                    }
+                }
+                Err(e) => {
+                    failed.insert(run.id.clone());
+                    emit_run_failure(
+                        &app,
+                        &run.session_id,
+                        None,
+                        &format!("Queued run failed to start (it stays queued): {}", e),
+                    );
+                }
+            }
+        }
+    });
+}
+
+/// Runs waiting for a free slot, oldest first
+#[tauri::command]
+pub async fn get_queued_runs() -> Result<Vec<crate::db::PendingRun>, String> {
+    crate::db::get_pending_runs().map_err(|e| e.to_string())
+}
+
+/// Remove a run from the queue before it starts
+#[tauri::command]
+pub async fn cancel_queued_run(id: String) -> Result<(), String> {
+    let removed = crate::db::delete_pending_run(&id).map_err(|e| e.to_string())?;
+    if !removed {
+        return Err(format!("No queued run with id {}", id));
+    }
+    Ok(())
+}
+
+/// Get details (prompt preview, start time, elapsed) for all running Claude sessions
+#[tauri::command]
+pub async fn get_running_claude_session_details() -> Result<Vec<RunningSessionInfo>, String> {
+    let processes = lock_processes();
+    let now = Utc::now();
+    Ok(processes
+        .iter()
+        .map(|(session_id, process)| RunningSessionInfo {
+            session_id: session_id.clone(),
+            prompt_preview: process.prompt_preview.clone(),
+            started_at: process.started_at.to_rfc3339(),
+            elapsed_secs: (now - process.started_at).num_seconds().max(0) as u64,
+        })
+        .collect())
+}
@@ -3,13 +3,39 @@
 //! Claude Code stores sessions at:
 //! ~/.claude/projects/[encoded-path]/[session-uuid].jsonl
 //!
-//! Path encoding: slashes become dashes (e.g., /Users/samb -> -Users-samb)
+//! Path encoding: every character other than an ASCII letter or digit becomes
+//! a dash (e.g., /Users/samb/.config -> -Users-samb--config)
 
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+use tauri::{AppHandle, Emitter};
+use tracing::{debug, info, warn};
+
+/// Active session file watchers, keyed by Claude session id. Dropping one stops it.
+static WATCHERS: Lazy<Mutex<HashMap<String, RecommendedWatcher>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Max characters returned by `get_last_assistant_message`, and the default
+/// for `get_session_first_prompt`
+const PREVIEW_MAX_CHARS: usize = 200;
+
+/// A cached first prompt along with the mtime of the file it was read from
+type CachedPrompt = (SystemTime, Option<String>);
+
+/// First user prompt per session file, keyed by path and tagged with the file's
+/// mtime so a rewritten file is re-read. Stores the untruncated text.
+static FIRST_PROMPT_CACHE: Lazy<Mutex<HashMap<PathBuf, CachedPrompt>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Chunk size used when reading a session file backwards
+const REVERSE_READ_CHUNK: u64 = 64 * 1024;
 
 /// A message from Claude's session storage
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,12 +77,48 @@ pub struct SessionMessage {
     pub content: serde_json::Value,
     pub timestamp: Option<String>,
     pub model: Option<String>,
+    /// True if any `tool_result` block in `content` has `is_error: true`
+    pub has_errors: bool,
+}
+
+/// Payload of `claude-session-appended`: messages written since the last event
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionAppended {
+    pub claude_session_id: String,
+    pub messages: Vec<SessionMessage>,
+}
+
+/// Where Claude stores a project's sessions, for diagnosing missing history
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaudeProjectDir {
+    pub path: String,
+    pub exists: bool,
+}
+
+/// A project directory under `~/.claude/projects`
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaudeProject {
+    pub encoded_name: String,
+    pub decoded_path: String, // Best guess; the encoding is lossy
+    pub session_count: usize,
 }
 
 /// Encode a project path like Claude Code does
-/// /Users/samb/path -> -Users-samb-path
+/// /Users/samb/my.app -> -Users-samb-my-app
 fn encode_project_path(path: &str) -> String {
-    path.replace('/', "-")
+    // Claude encodes the cwd, which never has a trailing separator
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    let path = if trimmed.is_empty() { path } else { trimmed };
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Approximate inverse of `encode_project_path`: every dash becomes a path
+/// separator, so dashes, dots and spaces in the original path are lost
+/// (-Users-samb-my-app -> /Users/samb/my/app)
+fn decode_project_name(encoded: &str) -> String {
+    encoded.replace('-', "/")
 }
 
 /// Get the Claude projects directory
@@ -71,7 +133,7 @@ fn find_session_file(session_id: &str, project_path: &str) -> Option<PathBuf> {
     let session_dir = projects_dir.join(&encoded_path);
 
     if !session_dir.exists() {
-        eprintln!("[ClaudeSessions] Session directory not found: {:?}", session_dir);
+        warn!("[ClaudeSessions] Session directory not found: {:?}", session_dir);
         return None;
     }
 
@@ -79,81 +141,547 @@ fn find_session_file(session_id: &str, project_path: &str) -> Option<PathBuf> {
     if session_file.exists() {
         Some(session_file)
     } else {
-        eprintln!("[ClaudeSessions] Session file not found: {:?}", session_file);
+        warn!("[ClaudeSessions] Session file not found: {:?}", session_file);
         None
     }
 }
 
+/// Find a session file by uuid in any project directory, regardless of path encoding.
+/// Used when a workspace folder has moved and its encoded directory no longer matches.
+pub fn find_claude_sessions_by_uuid(session_id: &str) -> Result<Option<PathBuf>, String> {
+    validate_session_id(session_id)?;
+    let projects_dir = get_claude_projects_dir()
+        .ok_or_else(|| "Could not find Claude projects directory".to_string())?;
+
+    if !projects_dir.exists() {
+        return Ok(None);
+    }
+
+    let entries = std::fs::read_dir(&projects_dir)
+        .map_err(|e| format!("Failed to read Claude projects directory: {}", e))?;
+
+    let file_name = format!("{}.jsonl", session_id);
+    for entry in entries.flatten() {
+        let candidate = entry.path().join(&file_name);
+        if candidate.is_file() {
+            return Ok(Some(candidate));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Find a session file under its project's encoded directory, falling back to
+/// scanning every project directory (e.g. after the worktree was moved).
+/// Rejects ids that aren't UUIDs, since they become part of the path.
+fn locate_session_file(session_id: &str, project_path: &str) -> Result<Option<PathBuf>, String> {
+    validate_session_id(session_id)?;
+    if let Some(path) = find_session_file(session_id, project_path) {
+        return Ok(Some(path));
+    }
+
+    let found = find_claude_sessions_by_uuid(session_id)?;
+    if let Some(path) = &found {
+        info!("[ClaudeSessions] Found relocated session file: {:?}", path);
+    }
+    Ok(found)
+}
+
 /// Load messages from a Claude session file
 #[tauri::command]
 pub async fn load_claude_session_messages(
     claude_session_id: String,
     project_path: String,
 ) -> Result<Vec<SessionMessage>, String> {
-    let session_file = find_session_file(&claude_session_id, &project_path)
+    let session_file = locate_session_file(&claude_session_id, &project_path)?
         .ok_or_else(|| format!("Session file not found for {}", claude_session_id))?;
 
-    println!("[ClaudeSessions] Loading messages from: {:?}", session_file);
+    debug!("[ClaudeSessions] Loading messages from: {:?}", session_file);
 
     let file = File::open(&session_file)
         .map_err(|e| format!("Failed to open session file: {}", e))?;
 
-    let reader = BufReader::new(file);
+    let mut reader = BufReader::new(file);
     let mut messages = Vec::new();
+    let mut buffer = Vec::new();
+    let mut offset = 0u64;
 
-    for line in reader.lines() {
-        let line = match line {
-            Ok(l) => l,
+    loop {
+        buffer.clear();
+        let read = match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
             Err(e) => {
-                eprintln!("[ClaudeSessions] Error reading line: {}", e);
-                continue;
+                warn!("[ClaudeSessions] Error reading line: {}", e);
+                break;
             }
         };
 
-        if line.is_empty() {
-            continue;
+        let line = String::from_utf8_lossy(&buffer);
+        let line = line.trim_end_matches(['\n', '\r']);
+        if let Some(session_msg) = parse_session_line(line, offset) {
+            messages.push(session_msg);
         }
+        offset += read as u64;
+    }
+
+    debug!("[ClaudeSessions] Loaded {} messages", messages.len());
+    Ok(messages)
+}
+
+/// Parse one `.jsonl` line into a user/assistant message; other entries yield `None`.
+/// `offset` is the line's byte offset in the file. It builds the id of messages
+/// without a uuid, so every loader gives such a message the same id.
+fn parse_session_line(line: &str, offset: u64) -> Option<SessionMessage> {
+    if line.is_empty() {
+        return None;
+    }
+
+    // Parse the line as JSON
+    let msg: ClaudeSessionMessage = match serde_json::from_str(line) {
+        Ok(m) => m,
+        Err(e) => {
+            // Skip non-message lines (like file-history-snapshot)
+            if !line.contains("\"type\":\"user\"") && !line.contains("\"type\":\"assistant\"") {
+                return None;
+            }
+            warn!("[ClaudeSessions] Parse error: {} for line: {}", e, &line[..line.len().min(100)]);
+            return None;
+        }
+    };
+
+    // Only process user and assistant messages
+    if msg.msg_type != "user" && msg.msg_type != "assistant" {
+        return None;
+    }
 
-        // Parse the line as JSON
-        let msg: ClaudeSessionMessage = match serde_json::from_str(&line) {
-            Ok(m) => m,
+    // Skip messages without content
+    let message_content = msg.message?;
+
+    // Convert to our output format
+    Some(SessionMessage {
+        id: msg.uuid.unwrap_or_else(|| format!("{}-{}", msg.msg_type, offset)),
+        msg_type: msg.msg_type,
+        has_errors: has_tool_errors(&message_content.content),
+        content: message_content.content,
+        timestamp: msg.timestamp,
+        model: message_content.model,
+    })
+}
+
+/// Whether a message's content holds a failed tool call's result.
+/// Tool results arrive in user messages as `{"type": "tool_result", "is_error": true, ...}`.
+fn has_tool_errors(content: &serde_json::Value) -> bool {
+    content.as_array().is_some_and(|blocks| {
+        blocks.iter().any(|block| {
+            block.get("type").and_then(|t| t.as_str()) == Some("tool_result")
+                && block.get("is_error").and_then(|e| e.as_bool()) == Some(true)
+        })
+    })
+}
+
+/// Read the complete lines appended since `offset`, each with its byte offset,
+/// advancing `offset` past them. A partially written last line is left for the
+/// next call; a file that shrank (rewritten) is re-read from the start.
+fn read_appended_lines(path: &Path, offset: &mut u64) -> std::io::Result<Vec<(u64, String)>> {
+    let mut file = File::open(path)?;
+    if file.metadata()?.len() < *offset {
+        *offset = 0;
+    }
+    file.seek(SeekFrom::Start(*offset))?;
+
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+    let complete = match buffer.iter().rposition(|&b| b == b'\n') {
+        Some(idx) => idx + 1,
+        None => return Ok(Vec::new()),
+    };
+    let mut line_offset = *offset;
+    *offset += complete as u64;
+
+    Ok(buffer[..complete]
+        .split_inclusive(|&b| b == b'\n')
+        .map(|line| {
+            let start = line_offset;
+            line_offset += line.len() as u64;
+            let text = String::from_utf8_lossy(line);
+            (start, text.trim_end_matches(['\n', '\r']).to_string())
+        })
+        .collect())
+}
+
+/// Watch a session file and emit `claude-session-appended` with each batch of
+/// newly written messages. Existing history is not re-sent; load it with
+/// `load_claude_session_messages` first. If the file doesn't exist yet, its
+/// directory (or the whole projects directory) is watched until it appears.
+#[tauri::command]
+pub async fn watch_claude_session(
+    app: AppHandle,
+    claude_session_id: String,
+    project_path: String,
+) -> Result<(), String> {
+    let projects_dir = get_claude_projects_dir()
+        .ok_or_else(|| "Could not find Claude projects directory".to_string())?;
+
+    let session_file = match locate_session_file(&claude_session_id, &project_path)? {
+        Some(path) => path,
+        None => projects_dir
+            .join(encode_project_path(&project_path))
+            .join(format!("{}.jsonl", claude_session_id)),
+    };
+
+    let (watch_dir, mode) = match session_file.parent() {
+        Some(dir) if dir.exists() => (dir.to_path_buf(), RecursiveMode::NonRecursive),
+        _ if projects_dir.exists() => (projects_dir, RecursiveMode::Recursive),
+        _ => return Err("Claude projects directory does not exist".to_string()),
+    };
+
+    let mut offset = std::fs::metadata(&session_file).map(|m| m.len()).unwrap_or(0);
+    let session_id = claude_session_id.clone();
+    let file_name = session_file.file_name().map(|name| name.to_os_string());
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let event = match res {
+            Ok(event) => event,
             Err(e) => {
-                // Skip non-message lines (like file-history-snapshot)
-                if !line.contains("\"type\":\"user\"") && !line.contains("\"type\":\"assistant\"") {
-                    continue;
-                }
-                eprintln!("[ClaudeSessions] Parse error: {} for line: {}", e, &line[..line.len().min(100)]);
-                continue;
+                warn!("[ClaudeSessions] Watch error for {}: {}", session_id, e);
+                return;
             }
         };
-
-        // Only process user and assistant messages
-        if msg.msg_type != "user" && msg.msg_type != "assistant" {
-            continue;
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
         }
+        // Match by file name: watchers may report canonicalized paths
+        let Some(path) = event
+            .paths
+            .iter()
+            .find(|p| p.file_name().map(|name| name.to_os_string()) == file_name)
+        else {
+            return;
+        };
 
-        // Skip messages without content
-        let message_content = match &msg.message {
-            Some(m) => m,
-            None => continue,
+        let lines = match read_appended_lines(path, &mut offset) {
+            Ok(lines) => lines,
+            Err(e) => {
+                warn!("[ClaudeSessions] Failed to read appended lines: {}", e);
+                return;
+            }
         };
+        let messages: Vec<SessionMessage> = lines
+            .iter()
+            .filter_map(|(line_offset, line)| parse_session_line(line, *line_offset))
+            .collect();
+
+        if !messages.is_empty() {
+            let _ = app.emit(
+                "claude-session-appended",
+                SessionAppended {
+                    claude_session_id: session_id.clone(),
+                    messages,
+                },
+            );
+        }
+    })
+    .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+    watcher
+        .watch(&watch_dir, mode)
+        .map_err(|e| format!("Failed to watch {:?}: {}", watch_dir, e))?;
+
+    info!(
+        "[ClaudeSessions] Watching {:?} for {}",
+        watch_dir, claude_session_id
+    );
+
+    // Replacing an existing watcher drops (and stops) it
+    WATCHERS
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(claude_session_id, watcher);
+
+    Ok(())
+}
+
+/// Stop watching a session file
+#[tauri::command]
+pub async fn unwatch_claude_session(claude_session_id: String) -> Result<(), String> {
+    let mut watchers = WATCHERS.lock().map_err(|e| e.to_string())?;
+
+    if watchers.remove(&claude_session_id).is_some() {
+        Ok(())
+    } else {
+        Err(format!("Not watching Claude session {}", claude_session_id))
+    }
+}
+
+/// Extract the first (or with `from_end`, the last) text block from a message's
+/// content (string or block array). Tool results and other non-text blocks are skipped.
+fn text_block(content: &serde_json::Value, from_end: bool) -> Option<String> {
+    let text_of = |block: &serde_json::Value| {
+        if block.get("type").and_then(|t| t.as_str()) == Some("text") {
+            block.get("text").and_then(|t| t.as_str()).map(String::from)
+        } else {
+            None
+        }
+    };
+    match content {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Array(blocks) if from_end => blocks.iter().rev().find_map(text_of),
+        serde_json::Value::Array(blocks) => blocks.iter().find_map(text_of),
+        _ => None,
+    }
+    .map(|text| text.trim().to_string())
+    .filter(|text| !text.is_empty())
+}
+
+/// Cut `text` to at most `max_chars` characters, marking the cut with an ellipsis
+fn truncate_preview(text: String, max_chars: usize) -> String {
+    if text.chars().count() > max_chars {
+        let truncated: String = text.chars().take(max_chars).collect();
+        format!("{}…", truncated.trim_end())
+    } else {
+        text
+    }
+}
 
-        // Convert to our output format
-        let session_msg = SessionMessage {
-            id: msg.uuid.unwrap_or_else(|| format!("{}-{}", msg.msg_type, messages.len())),
-            msg_type: msg.msg_type,
-            content: message_content.content.clone(),
-            timestamp: msg.timestamp,
-            model: message_content.model.clone(),
+/// Walk a file's lines from last to first, stopping when `f` returns `Some`.
+/// `f` also gets the line's byte offset. Only reads as much of the file as needed.
+fn find_line_from_end<T>(
+    file: &mut File,
+    mut f: impl FnMut(&str, u64) -> Option<T>,
+) -> std::io::Result<Option<T>> {
+    let mut pos = file.seek(SeekFrom::End(0))?;
+    let mut buffer: Vec<u8> = Vec::new();
+
+    while pos > 0 {
+        let chunk_len = REVERSE_READ_CHUNK.min(pos);
+        pos -= chunk_len;
+        file.seek(SeekFrom::Start(pos))?;
+        let mut chunk = vec![0u8; chunk_len as usize];
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+
+        // Every line after the first newline in the buffer is complete
+        while let Some(idx) = buffer.iter().rposition(|&b| b == b'\n') {
+            let line = String::from_utf8_lossy(&buffer[idx + 1..]).to_string();
+            buffer.truncate(idx);
+            if let Some(found) = f(&line, pos + idx as u64 + 1) {
+                return Ok(Some(found));
+            }
+        }
+    }
+
+    Ok(f(&String::from_utf8_lossy(&buffer), 0))
+}
+
+/// Get a truncated preview of the most recent assistant text in a session.
+/// Reads the file from the end, so it is cheap even for long sessions.
+#[tauri::command]
+pub async fn get_last_assistant_message(
+    claude_session_id: String,
+    project_path: String,
+) -> Result<Option<String>, String> {
+    let session_file = locate_session_file(&claude_session_id, &project_path)?
+        .ok_or_else(|| format!("Session file not found for {}", claude_session_id))?;
+
+    let mut file = File::open(&session_file)
+        .map_err(|e| format!("Failed to open session file: {}", e))?;
+
+    let text = find_line_from_end(&mut file, |line, _| {
+        if !line.contains("\"assistant\"") {
+            return None;
+        }
+        let msg: ClaudeSessionMessage = serde_json::from_str(line).ok()?;
+        if msg.msg_type != "assistant" {
+            return None;
+        }
+        text_block(&msg.message?.content, true)
+    })
+    .map_err(|e| format!("Failed to read session file: {}", e))?;
+
+    Ok(text.map(|text| truncate_preview(text, PREVIEW_MAX_CHARS)))
+}
+
+/// Scan a session file from the top for the first user message with text
+fn read_first_prompt(path: &Path) -> Result<Option<String>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open session file: {}", e))?;
+
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read session file: {}", e))?;
+        if !line.contains("\"user\"") {
+            continue;
+        }
+        let Ok(msg) = serde_json::from_str::<ClaudeSessionMessage>(&line) else {
+            continue;
         };
+        if msg.msg_type != "user" {
+            continue;
+        }
+        if let Some(text) = msg.message.and_then(|m| text_block(&m.content, false)) {
+            return Ok(Some(text));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Get the session's opening user prompt, truncated to `max_chars`
+/// (default 200). Used as the session's title in resume lists; results are
+/// cached until the file's mtime changes.
+#[tauri::command]
+pub async fn get_session_first_prompt(
+    claude_session_id: String,
+    project_path: String,
+    max_chars: Option<usize>,
+) -> Result<Option<String>, String> {
+    let session_file = locate_session_file(&claude_session_id, &project_path)?
+        .ok_or_else(|| format!("Session file not found for {}", claude_session_id))?;
 
-        messages.push(session_msg);
+    let mtime = std::fs::metadata(&session_file)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to stat session file: {}", e))?;
+
+    let cached = FIRST_PROMPT_CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&session_file)
+        .filter(|(cached_mtime, _)| *cached_mtime == mtime)
+        .map(|(_, prompt)| prompt.clone());
+
+    let prompt = match cached {
+        Some(prompt) => prompt,
+        None => {
+            let prompt = read_first_prompt(&session_file)?;
+            FIRST_PROMPT_CACHE
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(session_file, (mtime, prompt.clone()));
+            prompt
+        }
+    };
+
+    let max_chars = max_chars.unwrap_or(PREVIEW_MAX_CHARS);
+    Ok(prompt.map(|text| truncate_preview(text, max_chars)))
+}
+
+/// Claude session ids are hyphenated UUIDs; anything else (slashes, `..`)
+/// could point the file path outside the projects directory
+fn validate_session_id(session_id: &str) -> Result<(), String> {
+    if session_id.len() == 36 && uuid::Uuid::try_parse(session_id).is_ok() {
+        Ok(())
+    } else {
+        Err(format!("Invalid Claude session id: {:?}", session_id))
+    }
+}
+
+/// Load only the last `n` user/assistant messages of a session, oldest first.
+/// Reads the file backwards in chunks, so huge transcripts open quickly.
+#[tauri::command]
+pub async fn tail_claude_session_messages(
+    claude_session_id: String,
+    project_path: String,
+    n: usize,
+) -> Result<Vec<SessionMessage>, String> {
+    let session_file = locate_session_file(&claude_session_id, &project_path)?
+        .ok_or_else(|| format!("Session file not found for {}", claude_session_id))?;
+    if n == 0 {
+        return Ok(Vec::new());
     }
 
-    println!("[ClaudeSessions] Loaded {} messages", messages.len());
+    let mut file = File::open(&session_file)
+        .map_err(|e| format!("Failed to open session file: {}", e))?;
+
+    let mut messages = Vec::new();
+    find_line_from_end(&mut file, |line, offset| {
+        let message = parse_session_line(line.trim(), offset)?;
+        messages.push(message);
+        (messages.len() == n).then_some(())
+    })
+    .map_err(|e| format!("Failed to read session file: {}", e))?;
+
+    messages.reverse();
     Ok(messages)
 }
 
+/// Delete a Claude session's `.jsonl` file from disk
+#[tauri::command]
+pub async fn delete_claude_session_file(
+    claude_session_id: String,
+    project_path: String,
+) -> Result<(), String> {
+    let session_file = locate_session_file(&claude_session_id, &project_path)?
+        .ok_or_else(|| format!("Session file not found for {}", claude_session_id))?;
+
+    std::fs::remove_file(&session_file)
+        .map_err(|e| format!("Failed to delete session file: {}", e))?;
+
+    info!("[ClaudeSessions] Deleted session file: {:?}", session_file);
+    Ok(())
+}
+
+/// Resolve the `~/.claude/projects` directory Claude uses for a project path
+#[tauri::command]
+pub async fn get_claude_project_dir(project_path: String) -> Result<ClaudeProjectDir, String> {
+    let projects_dir = get_claude_projects_dir()
+        .ok_or_else(|| "Could not find Claude projects directory".to_string())?;
+    let dir = projects_dir.join(encode_project_path(&project_path));
+
+    Ok(ClaudeProjectDir {
+        path: dir.to_string_lossy().to_string(),
+        exists: dir.is_dir(),
+    })
+}
+
+/// Every project Claude has history for, most recently used first
+#[tauri::command]
+pub async fn list_claude_projects() -> Result<Vec<ClaudeProject>, String> {
+    let projects_dir = get_claude_projects_dir()
+        .ok_or_else(|| "Could not find Claude projects directory".to_string())?;
+
+    if !projects_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = std::fs::read_dir(&projects_dir)
+        .map_err(|e| format!("Failed to read Claude projects directory: {}", e))?;
+
+    let mut projects = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let Ok(files) = std::fs::read_dir(&path) else {
+            continue;
+        };
+
+        let mut session_count = 0;
+        let mut last_modified = SystemTime::UNIX_EPOCH;
+        for file in files.flatten() {
+            if file.path().extension().map_or(false, |ext| ext == "jsonl") {
+                session_count += 1;
+                if let Ok(modified) = file.metadata().and_then(|m| m.modified()) {
+                    last_modified = last_modified.max(modified);
+                }
+            }
+        }
+
+        let encoded_name = entry.file_name().to_string_lossy().to_string();
+        projects.push((
+            last_modified,
+            ClaudeProject {
+                decoded_path: decode_project_name(&encoded_name),
+                encoded_name,
+                session_count,
+            },
+        ));
+    }
+
+    projects.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    Ok(projects.into_iter().map(|(_, project)| project).collect())
+}
+
 /// List all sessions for a project path
 #[tauri::command]
 pub async fn list_claude_sessions(project_path: String) -> Result<Vec<String>, String> {
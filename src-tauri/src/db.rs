@@ -1,9 +1,12 @@
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
-use rusqlite::{params, Connection, Result};
+use rusqlite::{params, Connection, OptionalExtension, Result, Transaction};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, MutexGuard};
+use tracing::{info, warn};
 
 // Global database connection
 static DB: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
@@ -29,8 +32,10 @@ pub struct Session {
     pub cwd: String,
     pub workspace_id: Option<String>,
     pub worktree_name: Option<String>,
-    pub status: String,              // "ready" or "busy"
+    pub status: String,              // See SessionStatus
     pub base_commit: Option<String>, // Git commit SHA to diff against (stable reference)
+    pub tags: Vec<String>,           // Sorted; not synced
+    pub last_error: Option<String>,  // Why the session entered "error"; not synced
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     // Sync fields
@@ -39,6 +44,114 @@ pub struct Session {
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
+/// Valid values for `sessions.status`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStatus {
+    Ready,
+    Busy,
+    Error,
+    Waiting,
+}
+
+impl SessionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionStatus::Ready => "ready",
+            SessionStatus::Busy => "busy",
+            SessionStatus::Error => "error",
+            SessionStatus::Waiting => "waiting",
+        }
+    }
+}
+
+impl fmt::Display for SessionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SessionStatus {
+    type Err = String;
+
+    /// Parse a status, ignoring surrounding whitespace and case
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "ready" => Ok(SessionStatus::Ready),
+            "busy" => Ok(SessionStatus::Busy),
+            "error" => Ok(SessionStatus::Error),
+            "waiting" => Ok(SessionStatus::Waiting),
+            _ => Err(format!(
+                "Invalid session status '{}': must be one of ready, busy, error, waiting",
+                s
+            )),
+        }
+    }
+}
+
+/// Valid values for `diff_comments.severity`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentSeverity {
+    Blocker,
+    #[default]
+    Suggestion,
+    Question,
+    Praise,
+}
+
+impl CommentSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentSeverity::Blocker => "blocker",
+            CommentSeverity::Suggestion => "suggestion",
+            CommentSeverity::Question => "question",
+            CommentSeverity::Praise => "praise",
+        }
+    }
+}
+
+impl fmt::Display for CommentSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CommentSeverity {
+    type Err = String;
+
+    /// Parse a severity, ignoring surrounding whitespace and case
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "blocker" => Ok(CommentSeverity::Blocker),
+            "suggestion" => Ok(CommentSeverity::Suggestion),
+            "question" => Ok(CommentSeverity::Question),
+            "praise" => Ok(CommentSeverity::Praise),
+            _ => Err(format!(
+                "Invalid comment severity '{}': must be one of blocker, suggestion, question, praise",
+                s
+            )),
+        }
+    }
+}
+
+/// Who wrote a comment, stored in `diff_comments.author_kind`. Comments
+/// written before it was tracked have none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentAuthorKind {
+    User,
+    Agent,
+}
+
+impl CommentAuthorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentAuthorKind::User => "user",
+            CommentAuthorKind::Agent => "agent",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InboxMessage {
     pub id: String,
@@ -48,6 +161,9 @@ pub struct InboxMessage {
     pub created_at: DateTime<Utc>,
     pub read_at: Option<DateTime<Utc>>,
     pub first_read_at: Option<DateTime<Utc>>, // Set once when first read, never cleared
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub ack_reply: Option<String>, // Optional canned reply for the agent to poll
+    pub starred: bool,             // Kept by clear_inbox
     // Sync fields
     pub convex_id: Option<String>,
     pub sync_status: String,
@@ -60,10 +176,12 @@ pub struct DiffComment {
     pub session_id: String,
     pub file_path: String,
     pub line_number: Option<i32>, // Line in diff (null for file-level comments)
+    pub end_line_number: Option<i32>, // Last line of a multi-line selection (null for single-line)
     pub line_type: Option<String>, // "add", "delete", "context" or null
     pub author: String,           // "user" or session_id (Claude)
     pub content: String,
     pub status: String,            // "open", "resolved"
+    pub severity: String,          // See CommentSeverity
     pub parent_id: Option<String>, // For threaded replies
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -73,6 +191,102 @@ pub struct DiffComment {
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
+/// Content captured around a commented diff line, used to re-locate the
+/// comment after the diff is regenerated against a moved base
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentAnchor {
+    pub content: String,
+    #[serde(default)]
+    pub before: Vec<String>, // Lines directly above, top to bottom
+    #[serde(default)]
+    pub after: Vec<String>, // Lines directly below, top to bottom
+}
+
+#[derive(Debug, Clone)]
+pub struct AnchoredComment {
+    pub comment: DiffComment,
+    pub anchor: Option<CommentAnchor>,
+}
+
+/// A base commit a session was compared against, and when it was set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaseCommitEntry {
+    pub base_commit: String,
+    pub set_at: DateTime<Utc>,
+}
+
+/// A message from a headless run, stored as it streams
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunMessage {
+    pub id: String,
+    pub session_id: String,
+    pub seq: i64,             // Order within the session, starting at 0
+    pub role: String,         // "assistant", "user" or "result"
+    pub content_json: String, // The full stream-json message
+    pub created_at: DateTime<Utc>,
+}
+
+/// A run waiting for a free process slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingRun {
+    pub id: String,
+    pub session_id: String,
+    pub prompt: String,
+    pub cwd: String,
+    pub options_json: String, // How to launch it; see claude_headless::RunOptions
+    pub queued_at: DateTime<Utc>,
+}
+
+/// Cost of one session's runs within a summary range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCost {
+    pub session_id: String,
+    pub session_name: Option<String>, // None once the session has been deleted
+    pub cost_usd: f64,
+    pub duration_ms: i64,
+    pub runs: i64,
+}
+
+/// Totals across all sessions for a time range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostSummary {
+    pub total_cost_usd: f64,
+    pub total_duration_ms: i64,
+    pub sessions: Vec<SessionCost>, // Most expensive first
+}
+
+/// A busy session whose agent has stopped sending heartbeats
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StalledSession {
+    pub id: String,
+    pub name: String,
+    pub last_heartbeat_at: DateTime<Utc>,
+}
+
+/// A reusable prompt for starting runs. The body is stored raw; `{{cwd}}`
+/// and `{{branch}}` placeholders are filled in by the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTemplate {
+    pub name: String,
+    pub body: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Rows copied from an imported database, per table
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TableImportCounts {
+    pub imported: u32,
+    pub skipped: u32, // Already present by id
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ImportStats {
+    pub workspaces: TableImportCounts,
+    pub sessions: TableImportCounts,
+    pub inbox_messages: TableImportCounts,
+    pub comments: TableImportCounts,
+}
+
 // Sync queue item for offline mutations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncQueueItem {
@@ -86,169 +300,497 @@ pub struct SyncQueueItem {
     pub last_error: Option<String>,
 }
 
-pub fn get_db_path() -> PathBuf {
-    // Use platform-specific app data directory
+/// Platform-specific app data directory, created if missing
+pub fn get_app_data_dir() -> PathBuf {
     let data_dir = dirs::data_local_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join("com.samb.claude-sessions");
 
     std::fs::create_dir_all(&data_dir).ok();
-    data_dir.join("sessions.db")
+    data_dir
 }
 
-pub fn init_db() -> Result<()> {
-    let db_path = get_db_path();
-    println!("[DB] Initializing database at: {:?}", db_path);
-
-    let conn = Connection::open(&db_path)?;
-
-    // Create workspaces table
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS workspaces (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            folder TEXT NOT NULL,
-            script_path TEXT,
-            origin_branch TEXT NOT NULL DEFAULT 'main',
-            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
-        )",
-        [],
-    )?;
-
-    // Migration: Add origin_branch column if it doesn't exist
-    let _ = conn.execute(
-        "ALTER TABLE workspaces ADD COLUMN origin_branch TEXT NOT NULL DEFAULT 'main'",
-        [],
-    );
-
-    // Create sessions table
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS sessions (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            cwd TEXT NOT NULL,
-            workspace_id TEXT,
-            worktree_name TEXT,
-            status TEXT NOT NULL DEFAULT 'busy',
-            base_commit TEXT,
-            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY (workspace_id) REFERENCES workspaces(id)
-        )",
-        [],
-    )?;
+pub fn get_db_path() -> PathBuf {
+    get_app_data_dir().join("sessions.db")
+}
 
-    // Migration: Add base_commit column if it doesn't exist
-    let _ = conn.execute("ALTER TABLE sessions ADD COLUMN base_commit TEXT", []);
+/// One schema change. `AddColumn` is skipped when the column already exists,
+/// so databases created before versioning can replay every migration.
+enum MigrationStep {
+    Sql(&'static str),
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+}
 
-    // Migration: Add claude_session_id column for session persistence
-    let _ = conn.execute("ALTER TABLE sessions ADD COLUMN claude_session_id TEXT", []);
+struct Migration {
+    version: i64,
+    name: &'static str,
+    steps: &'static [MigrationStep],
+}
 
-    // Create inbox_messages table
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS inbox_messages (
+use MigrationStep::{AddColumn, Sql};
+
+/// Numbered migrations, applied in order. Append new ones; never edit applied ones.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create core tables",
+        steps: &[
+            Sql("CREATE TABLE IF NOT EXISTS workspaces (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                folder TEXT NOT NULL,
+                script_path TEXT,
+                origin_branch TEXT NOT NULL DEFAULT 'main',
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )"),
+            Sql("CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                cwd TEXT NOT NULL,
+                workspace_id TEXT,
+                worktree_name TEXT,
+                status TEXT NOT NULL DEFAULT 'busy',
+                base_commit TEXT,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (workspace_id) REFERENCES workspaces(id)
+            )"),
+            Sql("CREATE TABLE IF NOT EXISTS inbox_messages (
+                id TEXT PRIMARY KEY,
+                session_id TEXT NOT NULL,
+                message TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                read_at TEXT,
+                first_read_at TEXT,
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            )"),
+            Sql("CREATE TABLE IF NOT EXISTS diff_comments (
+                id TEXT PRIMARY KEY,
+                session_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                line_number INTEGER,
+                line_type TEXT,
+                author TEXT NOT NULL,
+                content TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'open',
+                parent_id TEXT,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+                FOREIGN KEY (parent_id) REFERENCES diff_comments(id) ON DELETE CASCADE
+            )"),
+            Sql("CREATE TABLE IF NOT EXISTS sync_queue (
+                id TEXT PRIMARY KEY,
+                entity_type TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                operation TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                attempts INTEGER DEFAULT 0,
+                last_error TEXT
+            )"),
+        ],
+    },
+    Migration {
+        version: 2,
+        name: "add workspace origin branch",
+        steps: &[AddColumn {
+            table: "workspaces",
+            column: "origin_branch",
+            definition: "TEXT NOT NULL DEFAULT 'main'",
+        }],
+    },
+    Migration {
+        version: 3,
+        name: "add session base commit and claude session id",
+        steps: &[
+            AddColumn {
+                table: "sessions",
+                column: "base_commit",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "sessions",
+                column: "claude_session_id",
+                definition: "TEXT",
+            },
+        ],
+    },
+    Migration {
+        version: 4,
+        name: "add inbox read and acknowledgement columns",
+        steps: &[
+            AddColumn {
+                table: "inbox_messages",
+                column: "first_read_at",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "inbox_messages",
+                column: "acknowledged_at",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "inbox_messages",
+                column: "ack_reply",
+                definition: "TEXT",
+            },
+        ],
+    },
+    Migration {
+        version: 5,
+        name: "add comment ranges and anchors",
+        steps: &[
+            AddColumn {
+                table: "diff_comments",
+                column: "end_line_number",
+                definition: "INTEGER",
+            },
+            AddColumn {
+                table: "diff_comments",
+                column: "anchor_content",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "diff_comments",
+                column: "anchor_before",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "diff_comments",
+                column: "anchor_after",
+                definition: "TEXT",
+            },
+        ],
+    },
+    Migration {
+        version: 6,
+        name: "add sync columns",
+        steps: &[
+            AddColumn {
+                table: "workspaces",
+                column: "convex_id",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "workspaces",
+                column: "sync_status",
+                definition: "TEXT NOT NULL DEFAULT 'pending'",
+            },
+            AddColumn {
+                table: "workspaces",
+                column: "deleted_at",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "sessions",
+                column: "convex_id",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "sessions",
+                column: "sync_status",
+                definition: "TEXT NOT NULL DEFAULT 'pending'",
+            },
+            AddColumn {
+                table: "sessions",
+                column: "deleted_at",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "inbox_messages",
+                column: "convex_id",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "inbox_messages",
+                column: "sync_status",
+                definition: "TEXT NOT NULL DEFAULT 'pending'",
+            },
+            AddColumn {
+                table: "inbox_messages",
+                column: "deleted_at",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "diff_comments",
+                column: "convex_id",
+                definition: "TEXT",
+            },
+            AddColumn {
+                table: "diff_comments",
+                column: "sync_status",
+                definition: "TEXT NOT NULL DEFAULT 'pending'",
+            },
+            AddColumn {
+                table: "diff_comments",
+                column: "deleted_at",
+                definition: "TEXT",
+            },
+        ],
+    },
+    Migration {
+        version: 7,
+        name: "create session tags",
+        steps: &[
+            Sql("CREATE TABLE IF NOT EXISTS session_tags (
+                session_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (session_id, tag),
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            )"),
+            Sql("CREATE INDEX IF NOT EXISTS idx_session_tags_tag ON session_tags(tag)"),
+        ],
+    },
+    Migration {
+        version: 8,
+        name: "add inbox starred flag",
+        steps: &[AddColumn {
+            table: "inbox_messages",
+            column: "starred",
+            definition: "INTEGER NOT NULL DEFAULT 0",
+        }],
+    },
+    Migration {
+        version: 9,
+        name: "create run messages",
+        steps: &[
+            Sql("CREATE TABLE IF NOT EXISTS run_messages (
+                id TEXT PRIMARY KEY,
+                session_id TEXT NOT NULL,
+                seq INTEGER NOT NULL,
+                role TEXT NOT NULL,
+                content_json TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            )"),
+            Sql("CREATE INDEX IF NOT EXISTS idx_run_messages_session ON run_messages(session_id, seq)"),
+        ],
+    },
+    Migration {
+        version: 10,
+        name: "create base commit history",
+        steps: &[
+            Sql("CREATE TABLE IF NOT EXISTS base_commit_history (
+                session_id TEXT NOT NULL,
+                base_commit TEXT NOT NULL,
+                set_at TEXT NOT NULL,
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            )"),
+            Sql("CREATE INDEX IF NOT EXISTS idx_base_commit_history_session ON base_commit_history(session_id)"),
+        ],
+    },
+    Migration {
+        version: 11,
+        name: "create recent folders",
+        steps: &[Sql("CREATE TABLE IF NOT EXISTS recent_folders (
+            path TEXT PRIMARY KEY,
+            last_used TEXT NOT NULL
+        )")],
+    },
+    Migration {
+        version: 12,
+        name: "create session costs",
+        steps: &[
+            Sql("CREATE TABLE IF NOT EXISTS session_costs (
+                id TEXT PRIMARY KEY,
+                session_id TEXT NOT NULL,
+                cost_usd REAL NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                recorded_at TEXT NOT NULL
+            )"),
+            Sql("CREATE INDEX IF NOT EXISTS idx_session_costs_recorded_at ON session_costs(recorded_at)"),
+        ],
+    },
+    Migration {
+        version: 13,
+        name: "add session last error",
+        steps: &[AddColumn {
+            table: "sessions",
+            column: "last_error",
+            definition: "TEXT",
+        }],
+    },
+    Migration {
+        version: 14,
+        name: "add session heartbeat",
+        steps: &[AddColumn {
+            table: "sessions",
+            column: "last_heartbeat_at",
+            definition: "TEXT",
+        }],
+    },
+    Migration {
+        version: 15,
+        name: "create session templates",
+        steps: &[Sql("CREATE TABLE IF NOT EXISTS session_templates (
+            name TEXT PRIMARY KEY,
+            body TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )")],
+    },
+    Migration {
+        version: 16,
+        name: "create pending runs",
+        steps: &[Sql("CREATE TABLE IF NOT EXISTS pending_runs (
             id TEXT PRIMARY KEY,
             session_id TEXT NOT NULL,
-            message TEXT NOT NULL,
-            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            read_at TEXT,
-            first_read_at TEXT,
-            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
-        )",
-        [],
+            prompt TEXT NOT NULL,
+            cwd TEXT NOT NULL,
+            options_json TEXT NOT NULL,
+            queued_at TEXT NOT NULL
+        )")],
+    },
+    Migration {
+        version: 17,
+        name: "add diff_comments.severity",
+        steps: &[AddColumn {
+            table: "diff_comments",
+            column: "severity",
+            definition: "TEXT NOT NULL DEFAULT 'suggestion'",
+        }],
+    },
+    Migration {
+        version: 18,
+        name: "create workspace UI state",
+        steps: &[Sql("CREATE TABLE IF NOT EXISTS workspace_ui_state (
+            workspace_id TEXT PRIMARY KEY,
+            state_json TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )")],
+    },
+    Migration {
+        version: 19,
+        name: "add diff_comments.author_kind",
+        steps: &[AddColumn {
+            table: "diff_comments",
+            column: "author_kind",
+            definition: "TEXT",
+        }],
+    },
+];
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get(0),
     )?;
+    Ok(count > 0)
+}
 
-    // Migration: Add first_read_at column if it doesn't exist
-    let _ = conn.execute(
-        "ALTER TABLE inbox_messages ADD COLUMN first_read_at TEXT",
-        [],
-    );
-
-    // Create diff_comments table
+/// Apply every migration newer than the recorded schema version, each in its own transaction
+fn run_migrations(conn: &mut Connection) -> Result<()> {
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS diff_comments (
-            id TEXT PRIMARY KEY,
-            session_id TEXT NOT NULL,
-            file_path TEXT NOT NULL,
-            line_number INTEGER,
-            line_type TEXT,
-            author TEXT NOT NULL,
-            content TEXT NOT NULL,
-            status TEXT NOT NULL DEFAULT 'open',
-            parent_id TEXT,
-            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
-            FOREIGN KEY (parent_id) REFERENCES diff_comments(id) ON DELETE CASCADE
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
         )",
         [],
     )?;
 
-    // ========== SYNC MIGRATIONS ==========
+    let current = current_schema_version(conn)?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+        for step in migration.steps {
+            match step {
+                Sql(sql) => {
+                    tx.execute(sql, [])?;
+                }
+                AddColumn {
+                    table,
+                    column,
+                    definition,
+                } => {
+                    if !column_exists(&tx, table, column)? {
+                        tx.execute(
+                            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                            [],
+                        )?;
+                    }
+                }
+            }
+        }
+        tx.execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.name, Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+        info!(
+            "[DB] Applied migration {}: {}",
+            migration.version, migration.name
+        );
+    }
 
-    // Migration: Add sync columns to workspaces
-    let _ = conn.execute("ALTER TABLE workspaces ADD COLUMN convex_id TEXT", []);
-    let _ = conn.execute(
-        "ALTER TABLE workspaces ADD COLUMN sync_status TEXT NOT NULL DEFAULT 'pending'",
-        [],
-    );
-    let _ = conn.execute("ALTER TABLE workspaces ADD COLUMN deleted_at TEXT", []);
+    Ok(())
+}
 
-    // Migration: Add sync columns to sessions
-    let _ = conn.execute("ALTER TABLE sessions ADD COLUMN convex_id TEXT", []);
-    let _ = conn.execute(
-        "ALTER TABLE sessions ADD COLUMN sync_status TEXT NOT NULL DEFAULT 'pending'",
+fn current_schema_version(conn: &Connection) -> Result<i64> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
         [],
-    );
-    let _ = conn.execute("ALTER TABLE sessions ADD COLUMN deleted_at TEXT", []);
+        |row| row.get(0),
+    )
+}
 
-    // Migration: Add sync columns to inbox_messages
-    let _ = conn.execute("ALTER TABLE inbox_messages ADD COLUMN convex_id TEXT", []);
-    let _ = conn.execute(
-        "ALTER TABLE inbox_messages ADD COLUMN sync_status TEXT NOT NULL DEFAULT 'pending'",
-        [],
-    );
-    let _ = conn.execute("ALTER TABLE inbox_messages ADD COLUMN deleted_at TEXT", []);
+/// Highest migration applied to the open database
+pub fn get_schema_version() -> Result<i64> {
+    with_db(current_schema_version)
+}
 
-    // Migration: Add sync columns to diff_comments
-    let _ = conn.execute("ALTER TABLE diff_comments ADD COLUMN convex_id TEXT", []);
-    let _ = conn.execute(
-        "ALTER TABLE diff_comments ADD COLUMN sync_status TEXT NOT NULL DEFAULT 'pending'",
-        [],
-    );
-    let _ = conn.execute("ALTER TABLE diff_comments ADD COLUMN deleted_at TEXT", []);
+pub fn init_db() -> Result<()> {
+    let db_path = get_db_path();
+    info!("[DB] Initializing database at: {:?}", db_path);
 
-    // Create sync_queue table for offline mutations
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS sync_queue (
-            id TEXT PRIMARY KEY,
-            entity_type TEXT NOT NULL,
-            entity_id TEXT NOT NULL,
-            operation TEXT NOT NULL,
-            payload TEXT NOT NULL,
-            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            attempts INTEGER DEFAULT 0,
-            last_error TEXT
-        )",
-        [],
-    )?;
+    let mut conn = Connection::open(&db_path)?;
+    run_migrations(&mut conn)?;
 
     // Store connection globally
-    *DB.lock().unwrap() = Some(conn);
+    *lock_db() = Some(conn);
 
-    println!("[DB] Database initialized successfully");
+    info!("[DB] Database initialized successfully");
     Ok(())
 }
 
+/// Lock the global connection. A panic inside a `with_db`/`with_tx` closure
+/// poisons the lock, but the connection stays usable (an open transaction
+/// rolls back as it unwinds), so recover instead of failing every later call.
+fn lock_db() -> MutexGuard<'static, Option<Connection>> {
+    DB.lock().unwrap_or_else(|poisoned| {
+        warn!("[DB] Recovering database lock poisoned by a panic");
+        DB.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
 pub fn with_db<F, T>(f: F) -> Result<T>
 where
     F: FnOnce(&Connection) -> Result<T>,
 {
-    let guard = DB.lock().unwrap();
+    let guard = lock_db();
     let conn = guard.as_ref().ok_or(rusqlite::Error::InvalidQuery)?;
     f(conn)
 }
 
+/// Run several statements atomically: commits if the closure returns `Ok`,
+/// rolls back (on drop) if it returns `Err`.
+pub fn with_tx<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce(&Transaction) -> Result<T>,
+{
+    let mut guard = lock_db();
+    let conn = guard.as_mut().ok_or(rusqlite::Error::InvalidQuery)?;
+    let tx = conn.transaction()?;
+    let result = f(&tx)?;
+    tx.commit()?;
+    Ok(result)
+}
+
 // Workspace CRUD
 pub fn create_workspace(workspace: &Workspace) -> Result<()> {
     with_db(|conn| {
@@ -310,110 +852,31 @@ pub fn get_all_workspaces() -> Result<Vec<Workspace>> {
     })
 }
 
-pub fn delete_workspace(id: &str) -> Result<()> {
-    with_db(|conn| {
-        conn.execute("DELETE FROM workspaces WHERE id = ?1", params![id])?;
-        Ok(())
-    })
-}
-
-// Session CRUD
-pub fn create_session(session: &Session) -> Result<()> {
-    with_db(|conn| {
-        conn.execute(
-            "INSERT INTO sessions (id, name, cwd, workspace_id, worktree_name, status, base_commit, created_at, updated_at, convex_id, sync_status, deleted_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-            params![
-                session.id,
-                session.name,
-                session.cwd,
-                session.workspace_id,
-                session.worktree_name,
-                session.status,
-                session.base_commit,
-                session.created_at.to_rfc3339(),
-                session.updated_at.to_rfc3339(),
-                session.convex_id,
-                session.sync_status,
-                session.deleted_at.map(|dt| dt.to_rfc3339())
-            ],
-        )?;
-        Ok(())
-    })
-}
-
-pub fn get_all_sessions() -> Result<Vec<Session>> {
-    with_db(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT id, name, cwd, workspace_id, worktree_name, status, base_commit, created_at, updated_at, convex_id, sync_status, deleted_at
-             FROM sessions
-             WHERE deleted_at IS NULL
-             ORDER BY created_at"
-        )?;
-        let sessions = stmt
-            .query_map([], |row| {
-                let created_at_str: String = row.get(7)?;
-                let updated_at_str: String = row.get(8)?;
-                let deleted_at_str: Option<String> = row.get(11)?;
-                Ok(Session {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    cwd: row.get(2)?,
-                    workspace_id: row.get(3)?,
-                    worktree_name: row.get(4)?,
-                    status: row.get(5)?,
-                    base_commit: row.get(6)?,
-                    created_at: DateTime::parse_from_rfc3339(&created_at_str)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    convex_id: row.get(9)?,
-                    sync_status: row
-                        .get::<_, Option<String>>(10)?
-                        .unwrap_or_else(|| "pending".to_string()),
-                    deleted_at: deleted_at_str.and_then(|s| {
-                        DateTime::parse_from_rfc3339(&s)
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .ok()
-                    }),
-                })
-            })?
-            .collect::<Result<Vec<_>>>()?;
-        Ok(sessions)
-    })
-}
-
-pub fn get_session(id: &str) -> Result<Option<Session>> {
+pub fn get_workspace(id: &str) -> Result<Option<Workspace>> {
     with_db(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT id, name, cwd, workspace_id, worktree_name, status, base_commit, created_at, updated_at, convex_id, sync_status, deleted_at
-             FROM sessions WHERE id = ?1"
+            "SELECT id, name, folder, script_path, origin_branch, created_at, convex_id, sync_status, deleted_at
+             FROM workspaces WHERE id = ?1"
         )?;
         let mut rows = stmt.query(params![id])?;
 
         if let Some(row) = rows.next()? {
-            let created_at_str: String = row.get(7)?;
-            let updated_at_str: String = row.get(8)?;
-            let deleted_at_str: Option<String> = row.get(11)?;
-            Ok(Some(Session {
+            let created_at_str: String = row.get(5)?;
+            let deleted_at_str: Option<String> = row.get(8)?;
+            Ok(Some(Workspace {
                 id: row.get(0)?,
                 name: row.get(1)?,
-                cwd: row.get(2)?,
-                workspace_id: row.get(3)?,
-                worktree_name: row.get(4)?,
-                status: row.get(5)?,
-                base_commit: row.get(6)?,
+                folder: row.get(2)?,
+                script_path: row.get(3)?,
+                origin_branch: row
+                    .get::<_, Option<String>>(4)?
+                    .unwrap_or_else(|| "main".to_string()),
                 created_at: DateTime::parse_from_rfc3339(&created_at_str)
                     .map(|dt| dt.with_timezone(&Utc))
                     .unwrap_or_else(|_| Utc::now()),
-                updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .unwrap_or_else(|_| Utc::now()),
-                convex_id: row.get(9)?,
+                convex_id: row.get(6)?,
                 sync_status: row
-                    .get::<_, Option<String>>(10)?
+                    .get::<_, Option<String>>(7)?
                     .unwrap_or_else(|| "pending".to_string()),
                 deleted_at: deleted_at_str.and_then(|s| {
                     DateTime::parse_from_rfc3339(&s)
@@ -427,363 +890,1575 @@ pub fn get_session(id: &str) -> Result<Option<Session>> {
     })
 }
 
-pub fn update_session_status(id: &str, status: &str) -> Result<()> {
+/// Counts for a workspace dashboard: (sessions, open root comments, unread messages).
+/// Soft-deleted sessions and everything attached to them are excluded.
+pub fn get_workspace_stats(workspace_id: &str) -> Result<(u32, u32, u32)> {
     with_db(|conn| {
-        conn.execute(
-            "UPDATE sessions SET status = ?1, updated_at = ?2 WHERE id = ?3",
-            params![status, Utc::now().to_rfc3339(), id],
-        )?;
-        Ok(())
+        conn.query_row(
+            "SELECT
+                (SELECT COUNT(*) FROM sessions
+                 WHERE workspace_id = ?1 AND deleted_at IS NULL),
+                (SELECT COUNT(*) FROM diff_comments c
+                 JOIN sessions s ON s.id = c.session_id
+                 WHERE s.workspace_id = ?1 AND s.deleted_at IS NULL
+                   AND c.status = 'open' AND c.parent_id IS NULL),
+                (SELECT COUNT(*) FROM inbox_messages m
+                 JOIN sessions s ON s.id = m.session_id
+                 WHERE s.workspace_id = ?1 AND s.deleted_at IS NULL
+                   AND m.read_at IS NULL)",
+            params![workspace_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
     })
 }
 
-pub fn update_session_base_commit(id: &str, base_commit: &str) -> Result<()> {
-    with_db(|conn| {
-        conn.execute(
-            "UPDATE sessions SET base_commit = ?1, updated_at = ?2 WHERE id = ?3",
-            params![base_commit, Utc::now().to_rfc3339(), id],
+pub fn delete_workspace(id: &str) -> Result<()> {
+    with_tx(|tx| {
+        tx.execute("DELETE FROM workspaces WHERE id = ?1", params![id])?;
+        tx.execute(
+            "DELETE FROM workspace_ui_state WHERE workspace_id = ?1",
+            params![id],
         )?;
         Ok(())
     })
 }
 
-pub fn update_session_claude_id(id: &str, claude_session_id: &str) -> Result<()> {
+/// Store the frontend's layout blob for a workspace, replacing any previous one
+pub fn save_workspace_ui_state(workspace_id: &str, state_json: &str) -> Result<()> {
     with_db(|conn| {
         conn.execute(
-            "UPDATE sessions SET claude_session_id = ?1, updated_at = ?2 WHERE id = ?3",
-            params![claude_session_id, Utc::now().to_rfc3339(), id],
+            "INSERT INTO workspace_ui_state (workspace_id, state_json, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(workspace_id) DO UPDATE SET state_json = excluded.state_json, updated_at = excluded.updated_at",
+            params![workspace_id, state_json, Utc::now().to_rfc3339()],
         )?;
         Ok(())
     })
 }
 
-pub fn get_session_claude_id(id: &str) -> Result<Option<String>> {
+pub fn get_workspace_ui_state(workspace_id: &str) -> Result<Option<String>> {
     with_db(|conn| {
-        let mut stmt = conn.prepare("SELECT claude_session_id FROM sessions WHERE id = ?1")?;
-        let result = stmt.query_row(params![id], |row| row.get::<_, Option<String>>(0));
-        match result {
-            Ok(id) => Ok(id),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(e.into()),
-        }
+        conn.query_row(
+            "SELECT state_json FROM workspace_ui_state WHERE workspace_id = ?1",
+            params![workspace_id],
+            |row| row.get(0),
+        )
+        .optional()
     })
 }
 
-pub fn delete_session(id: &str) -> Result<()> {
-    with_db(|conn| {
-        conn.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
+// Session CRUD
+pub fn create_session(session: &Session) -> Result<()> {
+    with_db(|conn| insert_session(conn, session))
+}
+
+/// Insert several sessions atomically: either all are created or none
+pub fn create_sessions(sessions: &[Session]) -> Result<()> {
+    with_tx(|tx| {
+        for session in sessions {
+            insert_session(tx, session)?;
+        }
         Ok(())
     })
 }
 
-pub fn rename_session(id: &str, name: &str) -> Result<()> {
-    with_db(|conn| {
-        conn.execute(
-            "UPDATE sessions SET name = ?1, updated_at = ?2 WHERE id = ?3",
-            params![name, Utc::now().to_rfc3339(), id],
+fn insert_session(conn: &Connection, session: &Session) -> Result<()> {
+    conn.execute(
+        "INSERT INTO sessions (id, name, cwd, workspace_id, worktree_name, status, base_commit, created_at, updated_at, convex_id, sync_status, deleted_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            session.id,
+            session.name,
+            session.cwd,
+            session.workspace_id,
+            session.worktree_name,
+            session.status,
+            session.base_commit,
+            session.created_at.to_rfc3339(),
+            session.updated_at.to_rfc3339(),
+            session.convex_id,
+            session.sync_status,
+            session.deleted_at.map(|dt| dt.to_rfc3339())
+        ],
+    )?;
+    Ok(())
+}
+
+/// Max folders kept by `record_recent_folder`; older ones are dropped
+const RECENT_FOLDERS_MAX: u32 = 20;
+
+/// Mark a folder as just used, keeping only the most recent entries
+pub fn record_recent_folder(path: &str) -> Result<()> {
+    with_tx(|tx| {
+        tx.execute(
+            "INSERT INTO recent_folders (path, last_used) VALUES (?1, ?2)
+             ON CONFLICT(path) DO UPDATE SET last_used = excluded.last_used",
+            params![path, Utc::now().to_rfc3339()],
+        )?;
+        tx.execute(
+            "DELETE FROM recent_folders WHERE path NOT IN (
+                SELECT path FROM recent_folders ORDER BY last_used DESC LIMIT ?1
+             )",
+            params![RECENT_FOLDERS_MAX],
         )?;
         Ok(())
     })
 }
 
-pub fn update_session_cwd(id: &str, cwd: &str) -> Result<()> {
+/// Recently used folders, most recent first
+pub fn get_recent_folders(limit: u32) -> Result<Vec<String>> {
+    with_db(|conn| {
+        let mut stmt =
+            conn.prepare("SELECT path FROM recent_folders ORDER BY last_used DESC LIMIT ?1")?;
+        let folders = stmt
+            .query_map(params![limit], |row| row.get(0))?
+            .collect::<Result<Vec<String>>>()?;
+        Ok(folders)
+    })
+}
+
+// ========== SESSION TEMPLATES ==========
+
+/// Create a template, or replace the body of the one with this name
+pub fn save_template(name: &str, body: &str) -> Result<()> {
     with_db(|conn| {
         conn.execute(
-            "UPDATE sessions SET cwd = ?1, updated_at = ?2 WHERE id = ?3",
-            params![cwd, Utc::now().to_rfc3339(), id],
+            "INSERT INTO session_templates (name, body, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET body = excluded.body, updated_at = excluded.updated_at",
+            params![name, body, Utc::now().to_rfc3339()],
         )?;
         Ok(())
     })
 }
 
-// Inbox Message CRUD
+/// All templates, by name
+pub fn get_templates() -> Result<Vec<SessionTemplate>> {
+    with_db(|conn| {
+        let mut stmt =
+            conn.prepare("SELECT name, body, updated_at FROM session_templates ORDER BY name ASC")?;
+        let templates = stmt
+            .query_map([], |row| {
+                let updated_at_str: String = row.get(2)?;
+                Ok(SessionTemplate {
+                    name: row.get(0)?,
+                    body: row.get(1)?,
+                    updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(templates)
+    })
+}
+
+/// Returns false if no template has this name
+pub fn delete_template(name: &str) -> Result<bool> {
+    with_db(|conn| {
+        let deleted = conn.execute(
+            "DELETE FROM session_templates WHERE name = ?1",
+            params![name],
+        )?;
+        Ok(deleted > 0)
+    })
+}
+
+/// Session columns plus newline-joined tags, for queries joined with session_tags.
+/// Must be followed by `GROUP BY s.id`.
+const SESSION_WITH_TAGS_COLUMNS: &str =
+    "s.id, s.name, s.cwd, s.workspace_id, s.worktree_name, s.status, s.base_commit, s.created_at, s.updated_at, s.convex_id, s.sync_status, s.deleted_at,
+     GROUP_CONCAT(t.tag, char(10)), s.last_error";
+
+fn session_with_tags_from_row(row: &rusqlite::Row) -> Result<Session> {
+    let created_at_str: String = row.get(7)?;
+    let updated_at_str: String = row.get(8)?;
+    let deleted_at_str: Option<String> = row.get(11)?;
+    let mut tags: Vec<String> = row
+        .get::<_, Option<String>>(12)?
+        .map(|joined| joined.split('\n').map(String::from).collect())
+        .unwrap_or_default();
+    tags.sort();
+    Ok(Session {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        cwd: row.get(2)?,
+        workspace_id: row.get(3)?,
+        worktree_name: row.get(4)?,
+        status: row.get(5)?,
+        base_commit: row.get(6)?,
+        tags,
+        last_error: row.get(13)?,
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        convex_id: row.get(9)?,
+        sync_status: row
+            .get::<_, Option<String>>(10)?
+            .unwrap_or_else(|| "pending".to_string()),
+        deleted_at: deleted_at_str.and_then(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Utc))
+                .ok()
+        }),
+    })
+}
+
+pub fn get_all_sessions() -> Result<Vec<Session>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM sessions s
+             LEFT JOIN session_tags t ON t.session_id = s.id
+             WHERE s.deleted_at IS NULL
+             GROUP BY s.id
+             ORDER BY s.created_at",
+            SESSION_WITH_TAGS_COLUMNS
+        ))?;
+        let sessions = stmt
+            .query_map([], session_with_tags_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(sessions)
+    })
+}
+
+/// Sessions carrying `tag` (each still lists all of its tags)
+pub fn get_sessions_by_tag(tag: &str) -> Result<Vec<Session>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM sessions s
+             LEFT JOIN session_tags t ON t.session_id = s.id
+             WHERE s.deleted_at IS NULL
+               AND s.id IN (SELECT session_id FROM session_tags WHERE tag = ?1)
+             GROUP BY s.id
+             ORDER BY s.created_at",
+            SESSION_WITH_TAGS_COLUMNS
+        ))?;
+        let sessions = stmt
+            .query_map(params![tag], session_with_tags_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(sessions)
+    })
+}
+
+fn load_session_tags(conn: &Connection, session_id: &str) -> Result<Vec<String>> {
+    let mut stmt =
+        conn.prepare("SELECT tag FROM session_tags WHERE session_id = ?1 ORDER BY tag")?;
+    let tags = stmt
+        .query_map(params![session_id], |row| row.get(0))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(tags)
+}
+
+pub fn get_session_tags(session_id: &str) -> Result<Vec<String>> {
+    with_db(|conn| load_session_tags(conn, session_id))
+}
+
+pub fn add_session_tag(session_id: &str, tag: &str) -> Result<()> {
+    with_db(|conn| {
+        conn.execute(
+            "INSERT OR IGNORE INTO session_tags (session_id, tag) VALUES (?1, ?2)",
+            params![session_id, tag],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn remove_session_tag(session_id: &str, tag: &str) -> Result<()> {
+    with_db(|conn| {
+        conn.execute(
+            "DELETE FROM session_tags WHERE session_id = ?1 AND tag = ?2",
+            params![session_id, tag],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn get_session(id: &str) -> Result<Option<Session>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, cwd, workspace_id, worktree_name, status, base_commit, created_at, updated_at, convex_id, sync_status, deleted_at, last_error
+             FROM sessions WHERE id = ?1"
+        )?;
+        let mut rows = stmt.query(params![id])?;
+
+        if let Some(row) = rows.next()? {
+            let created_at_str: String = row.get(7)?;
+            let updated_at_str: String = row.get(8)?;
+            let deleted_at_str: Option<String> = row.get(11)?;
+            Ok(Some(Session {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                cwd: row.get(2)?,
+                workspace_id: row.get(3)?,
+                worktree_name: row.get(4)?,
+                status: row.get(5)?,
+                base_commit: row.get(6)?,
+                tags: load_session_tags(conn, id)?,
+                last_error: row.get(12)?,
+                created_at: DateTime::parse_from_rfc3339(&created_at_str)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+                updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
+                    .map(|dt| dt.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+                convex_id: row.get(9)?,
+                sync_status: row
+                    .get::<_, Option<String>>(10)?
+                    .unwrap_or_else(|| "pending".to_string()),
+                deleted_at: deleted_at_str.and_then(|s| {
+                    DateTime::parse_from_rfc3339(&s)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .ok()
+                }),
+            }))
+        } else {
+            Ok(None)
+        }
+    })
+}
+
+/// Set a session's status. Leaving "error" clears the stored error.
+pub fn update_session_status(id: &str, status: SessionStatus) -> Result<()> {
+    with_db(|conn| {
+        conn.execute(
+            "UPDATE sessions
+             SET status = ?1, updated_at = ?2,
+                 last_error = CASE WHEN ?1 = 'error' THEN last_error ELSE NULL END
+             WHERE id = ?3",
+            params![status.as_str(), Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    })
+}
+
+/// Record that the session's agent is still making progress
+pub fn record_heartbeat(id: &str) -> Result<()> {
+    with_db(|conn| {
+        conn.execute(
+            "UPDATE sessions SET last_heartbeat_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    })
+}
+
+/// Busy sessions with no heartbeat or status change since `cutoff`.
+/// Sessions that never sent a heartbeat aren't monitored and are skipped.
+pub fn get_stalled_sessions(cutoff: DateTime<Utc>) -> Result<Vec<StalledSession>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, last_heartbeat_at
+             FROM sessions
+             WHERE status = ?1 AND deleted_at IS NULL AND last_heartbeat_at IS NOT NULL
+               AND MAX(julianday(last_heartbeat_at), julianday(updated_at)) < julianday(?2)
+             ORDER BY last_heartbeat_at ASC",
+        )?;
+        let sessions = stmt
+            .query_map(
+                params![SessionStatus::Busy.as_str(), cutoff.to_rfc3339()],
+                |row| {
+                    let heartbeat_str: String = row.get(2)?;
+                    Ok(StalledSession {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        last_heartbeat_at: DateTime::parse_from_rfc3339(&heartbeat_str)
+                            .map(|dt| dt.with_timezone(&Utc))
+                            .unwrap_or_else(|_| Utc::now()),
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(sessions)
+    })
+}
+
+/// Put a session into "error" with the reason, e.g. a crashed run's stderr
+pub fn set_session_error(id: &str, message: &str) -> Result<()> {
+    with_db(|conn| {
+        conn.execute(
+            "UPDATE sessions SET status = ?1, last_error = ?2, updated_at = ?3 WHERE id = ?4",
+            params![
+                SessionStatus::Error.as_str(),
+                message,
+                Utc::now().to_rfc3339(),
+                id
+            ],
+        )?;
+        Ok(())
+    })
+}
+
+/// Set a session's base commit, recording it in the base commit history.
+/// Sessions created before the history existed get their old base recorded
+/// first (dated at session creation) so it isn't lost.
+pub fn update_session_base_commit(id: &str, base_commit: &str) -> Result<()> {
+    with_tx(|tx| {
+        let has_history: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM base_commit_history WHERE session_id = ?1)",
+            params![id],
+            |row| row.get(0),
+        )?;
+        if !has_history {
+            let previous: Option<(Option<String>, String)> = tx
+                .query_row(
+                    "SELECT base_commit, created_at FROM sessions WHERE id = ?1",
+                    params![id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            if let Some((Some(previous), created_at)) = previous {
+                tx.execute(
+                    "INSERT INTO base_commit_history (session_id, base_commit, set_at) VALUES (?1, ?2, ?3)",
+                    params![id, previous, created_at],
+                )?;
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+        tx.execute(
+            "UPDATE sessions SET base_commit = ?1, updated_at = ?2 WHERE id = ?3",
+            params![base_commit, now, id],
+        )?;
+        push_base_commit_history(tx, id, base_commit, &now)?;
+        Ok(())
+    })
+}
+
+fn push_base_commit_history(
+    conn: &Connection,
+    session_id: &str,
+    base_commit: &str,
+    set_at: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO base_commit_history (session_id, base_commit, set_at) VALUES (?1, ?2, ?3)",
+        params![session_id, base_commit, set_at],
+    )?;
+    Ok(())
+}
+
+/// Every base commit the session has had, oldest first
+pub fn get_base_commit_history(session_id: &str) -> Result<Vec<BaseCommitEntry>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT base_commit, set_at FROM base_commit_history
+             WHERE session_id = ?1
+             ORDER BY set_at ASC, rowid ASC",
+        )?;
+        let entries = stmt
+            .query_map(params![session_id], |row| {
+                let set_at_str: String = row.get(1)?;
+                Ok(BaseCommitEntry {
+                    base_commit: row.get(0)?,
+                    set_at: DateTime::parse_from_rfc3339(&set_at_str)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(entries)
+    })
+}
+
+/// Whether `base_commit` appears in the session's base commit history
+pub fn has_base_commit_in_history(session_id: &str, base_commit: &str) -> Result<bool> {
+    with_db(|conn| {
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM base_commit_history WHERE session_id = ?1 AND base_commit = ?2)",
+            params![session_id, base_commit],
+            |row| row.get(0),
+        )
+    })
+}
+
+pub fn update_session_claude_id(id: &str, claude_session_id: &str) -> Result<()> {
+    with_db(|conn| {
+        conn.execute(
+            "UPDATE sessions SET claude_session_id = ?1, updated_at = ?2 WHERE id = ?3",
+            params![claude_session_id, Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn get_session_claude_id(id: &str) -> Result<Option<String>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare("SELECT claude_session_id FROM sessions WHERE id = ?1")?;
+        let result = stmt.query_row(params![id], |row| row.get::<_, Option<String>>(0));
+        match result {
+            Ok(id) => Ok(id),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    })
+}
+
+pub fn delete_session(id: &str) -> Result<()> {
+    with_tx(|tx| {
+        tx.execute(
+            "DELETE FROM session_tags WHERE session_id = ?1",
+            params![id],
+        )?;
+        tx.execute(
+            "DELETE FROM run_messages WHERE session_id = ?1",
+            params![id],
+        )?;
+        tx.execute(
+            "DELETE FROM base_commit_history WHERE session_id = ?1",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
+        Ok(())
+    })
+}
+
+/// Rename a session. Comments the session's agent wrote under its old name
+/// are re-attributed so threads show one consistent name.
+pub fn rename_session(id: &str, name: &str) -> Result<()> {
+    with_tx(|tx| {
+        let old_name: Option<String> = tx
+            .query_row(
+                "SELECT name FROM sessions WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        tx.execute(
+            "UPDATE sessions SET name = ?1, updated_at = ?2 WHERE id = ?3",
+            params![name, Utc::now().to_rfc3339(), id],
+        )?;
+        if let Some(old_name) = old_name.filter(|old| old != name) {
+            update_comments_author(tx, id, &old_name, name)?;
+        }
+        Ok(())
+    })
+}
+
+/// Change the author of a session's comments from `old_author` to
+/// `new_author`. Returns the number of comments updated.
+fn update_comments_author(
+    conn: &Connection,
+    session_id: &str,
+    old_author: &str,
+    new_author: &str,
+) -> Result<u32> {
+    let updated = conn.execute(
+        "UPDATE diff_comments SET author = ?1, updated_at = ?2
+         WHERE session_id = ?3 AND author = ?4",
+        params![new_author, Utc::now().to_rfc3339(), session_id, old_author],
+    )?;
+    Ok(updated as u32)
+}
+
+pub fn update_session_cwd(id: &str, cwd: &str) -> Result<()> {
+    with_db(|conn| {
+        conn.execute(
+            "UPDATE sessions SET cwd = ?1, updated_at = ?2 WHERE id = ?3",
+            params![cwd, Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    })
+}
+
+// Inbox Message CRUD
 pub fn create_inbox_message(session_id: &str, message: &str) -> Result<InboxMessage> {
     with_db(|conn| {
-        let id = uuid::Uuid::new_v4().to_string();
-        let created_at = Utc::now();
+        let id = uuid::Uuid::new_v4().to_string();
+        let created_at = Utc::now();
+
+        conn.execute(
+            "INSERT INTO inbox_messages (id, session_id, message, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, session_id, message, created_at.to_rfc3339()],
+        )?;
+
+        // Get session name for the response
+        let session_name: String = conn
+            .query_row(
+                "SELECT name FROM sessions WHERE id = ?1",
+                params![session_id],
+                |row| row.get(0),
+            )
+            .unwrap_or_else(|_| "Unknown".to_string());
+
+        Ok(InboxMessage {
+            id,
+            session_id: session_id.to_string(),
+            session_name,
+            message: message.to_string(),
+            created_at,
+            read_at: None,
+            first_read_at: None,
+            acknowledged_at: None,
+            ack_reply: None,
+            starred: false,
+            convex_id: None,
+            sync_status: "pending".to_string(),
+            deleted_at: None,
+        })
+    })
+}
+
+/// Inbox columns (joined with the session name), read by `inbox_message_from_row`
+const INBOX_MESSAGE_COLUMNS: &str =
+    "m.id, m.session_id, s.name, m.message, m.created_at, m.read_at, m.first_read_at,
+     m.acknowledged_at, m.ack_reply, m.starred";
+
+fn inbox_message_from_row(row: &rusqlite::Row) -> Result<InboxMessage> {
+    let created_at_str: String = row.get(4)?;
+    let read_at_str: Option<String> = row.get(5)?;
+    let first_read_at_str: Option<String> = row.get(6)?;
+    let acknowledged_at_str: Option<String> = row.get(7)?;
+    Ok(InboxMessage {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        session_name: row
+            .get::<_, Option<String>>(2)?
+            .unwrap_or_else(|| "Unknown".to_string()),
+        message: row.get(3)?,
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        read_at: read_at_str.and_then(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Utc))
+                .ok()
+        }),
+        first_read_at: first_read_at_str.and_then(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Utc))
+                .ok()
+        }),
+        acknowledged_at: acknowledged_at_str.and_then(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Utc))
+                .ok()
+        }),
+        ack_reply: row.get(8)?,
+        starred: row.get(9)?,
+        convex_id: None,
+        sync_status: "pending".to_string(),
+        deleted_at: None,
+    })
+}
+
+/// One page of inbox messages, newest first, plus the total number matching
+/// the filters. No limit returns everything from `offset` on.
+pub fn get_inbox_messages_paged(
+    limit: Option<u32>,
+    offset: u32,
+    unread_only: bool,
+    session_id: Option<&str>,
+) -> Result<(Vec<InboxMessage>, u32)> {
+    const FILTER: &str = "(?1 IS NULL OR m.session_id = ?1) AND (?2 = 0 OR m.read_at IS NULL)";
+    with_db(|conn| {
+        let total: u32 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM inbox_messages m WHERE {}", FILTER),
+            params![session_id, unread_only],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM inbox_messages m
+             LEFT JOIN sessions s ON m.session_id = s.id
+             WHERE {}
+             ORDER BY m.created_at DESC
+             LIMIT ?3 OFFSET ?4",
+            INBOX_MESSAGE_COLUMNS, FILTER
+        ))?;
+        // SQLite treats a negative LIMIT as no limit
+        let limit = limit.map(i64::from).unwrap_or(-1);
+        let messages = stmt
+            .query_map(
+                params![session_id, unread_only, limit, offset],
+                inbox_message_from_row,
+            )?
+            .collect::<Result<Vec<_>>>()?;
+        Ok((messages, total))
+    })
+}
+
+pub fn get_inbox_messages_for_session(session_id: &str) -> Result<Vec<InboxMessage>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM inbox_messages m
+             LEFT JOIN sessions s ON m.session_id = s.id
+             WHERE m.session_id = ?1
+             ORDER BY m.created_at DESC",
+            INBOX_MESSAGE_COLUMNS
+        ))?;
+        let messages = stmt
+            .query_map(params![session_id], inbox_message_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(messages)
+    })
+}
+
+/// Oldest unread message for a session: where the user stopped reading
+pub fn get_first_unread_message(session_id: &str) -> Result<Option<InboxMessage>> {
+    with_db(|conn| {
+        conn.query_row(
+            &format!(
+                "SELECT {}
+                 FROM inbox_messages m
+                 LEFT JOIN sessions s ON m.session_id = s.id
+                 WHERE m.session_id = ?1 AND m.read_at IS NULL
+                 ORDER BY m.created_at ASC
+                 LIMIT 1",
+                INBOX_MESSAGE_COLUMNS
+            ),
+            params![session_id],
+            inbox_message_from_row,
+        )
+        .optional()
+    })
+}
+
+pub fn get_starred_messages() -> Result<Vec<InboxMessage>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM inbox_messages m
+             LEFT JOIN sessions s ON m.session_id = s.id
+             WHERE m.starred = 1
+             ORDER BY m.created_at DESC",
+            INBOX_MESSAGE_COLUMNS
+        ))?;
+        let messages = stmt
+            .query_map([], inbox_message_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(messages)
+    })
+}
+
+/// Returns false if no message has this id
+pub fn set_message_starred(id: &str, starred: bool) -> Result<bool> {
+    with_db(|conn| {
+        let updated = conn.execute(
+            "UPDATE inbox_messages SET starred = ?1 WHERE id = ?2",
+            params![starred, id],
+        )?;
+        Ok(updated > 0)
+    })
+}
+
+pub fn mark_message_read(id: &str) -> Result<()> {
+    let now = Utc::now().to_rfc3339();
+    with_db(|conn| {
+        // Set read_at, and set first_read_at only if it's NULL (first time reading)
+        conn.execute(
+            "UPDATE inbox_messages SET read_at = ?1, first_read_at = COALESCE(first_read_at, ?1) WHERE id = ?2",
+            params![now, id],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn mark_message_unread(id: &str) -> Result<()> {
+    with_db(|conn| {
+        // Only clear read_at, keep first_read_at to indicate it was previously read
+        conn.execute(
+            "UPDATE inbox_messages SET read_at = NULL WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    })
+}
+
+// Mark all messages for a session as read
+pub fn mark_session_messages_read(session_id: &str) -> Result<u32> {
+    let now = Utc::now().to_rfc3339();
+    with_db(|conn| {
+        let count = conn.execute(
+            "UPDATE inbox_messages SET read_at = ?1, first_read_at = COALESCE(first_read_at, ?1)
+             WHERE session_id = ?2 AND read_at IS NULL",
+            params![now, session_id],
+        )?;
+        Ok(count as u32)
+    })
+}
+
+// Mark all messages for a session as unread, keeping first_read_at like
+// mark_message_unread. Returns how many were read before.
+pub fn mark_session_messages_unread(session_id: &str) -> Result<u32> {
+    with_db(|conn| {
+        let count = conn.execute(
+            "UPDATE inbox_messages SET read_at = NULL
+             WHERE session_id = ?1 AND read_at IS NOT NULL",
+            params![session_id],
+        )?;
+        Ok(count as u32)
+    })
+}
+
+// Acknowledge a message, optionally leaving a short reply for the agent.
+// With `session_id`, only a message belonging to that session matches.
+// Returns false if no matching message exists.
+pub fn acknowledge_message(
+    id: &str,
+    session_id: Option<&str>,
+    reply: Option<&str>,
+) -> Result<bool> {
+    let now = Utc::now().to_rfc3339();
+    with_db(|conn| {
+        let count = conn.execute(
+            "UPDATE inbox_messages SET acknowledged_at = ?1, ack_reply = ?2
+             WHERE id = ?3 AND (?4 IS NULL OR session_id = ?4)",
+            params![now, reply, id, session_id],
+        )?;
+        Ok(count > 0)
+    })
+}
+
+pub fn delete_inbox_message(id: &str) -> Result<()> {
+    with_db(|conn| {
+        conn.execute("DELETE FROM inbox_messages WHERE id = ?1", params![id])?;
+        Ok(())
+    })
+}
+
+/// Delete all inbox messages except starred ones
+pub fn clear_inbox() -> Result<()> {
+    with_db(|conn| {
+        conn.execute("DELETE FROM inbox_messages WHERE starred = 0", [])?;
+        Ok(())
+    })
+}
+
+/// Delete inbox messages and diff comments whose session no longer exists.
+/// Foreign keys aren't enforced on this connection, so these can accumulate.
+/// Returns (messages_deleted, comments_deleted).
+pub fn prune_orphans() -> Result<(u32, u32)> {
+    with_tx(|tx| {
+        let messages = tx.execute(
+            "DELETE FROM inbox_messages WHERE session_id NOT IN (SELECT id FROM sessions)",
+            [],
+        )?;
+        let comments = tx.execute(
+            "DELETE FROM diff_comments WHERE session_id NOT IN (SELECT id FROM sessions)",
+            [],
+        )?;
+        Ok((messages as u32, comments as u32))
+    })
+}
+
+// Diff Comment CRUD
+// The comment and its anchor (if any) are written in one transaction.
+#[allow(clippy::too_many_arguments)]
+pub fn create_comment(
+    session_id: &str,
+    file_path: &str,
+    line_number: Option<i32>,
+    end_line_number: Option<i32>,
+    line_type: Option<&str>,
+    author: &str,
+    content: &str,
+    parent_id: Option<&str>,
+    severity: CommentSeverity,
+    anchor: Option<&CommentAnchor>,
+    author_kind: CommentAuthorKind,
+) -> Result<DiffComment> {
+    with_tx(|tx| {
+        let comment = insert_comment(
+            tx,
+            session_id,
+            file_path,
+            line_number,
+            end_line_number,
+            line_type,
+            author,
+            content,
+            parent_id,
+            severity,
+            author_kind,
+        )?;
+        if let Some(anchor) = anchor {
+            set_comment_anchor(tx, &comment.id, anchor)?;
+        }
+        Ok(comment)
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn insert_comment(
+    conn: &Connection,
+    session_id: &str,
+    file_path: &str,
+    line_number: Option<i32>,
+    end_line_number: Option<i32>,
+    line_type: Option<&str>,
+    author: &str,
+    content: &str,
+    parent_id: Option<&str>,
+    severity: CommentSeverity,
+    author_kind: CommentAuthorKind,
+) -> Result<DiffComment> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = Utc::now();
+
+    conn.execute(
+        "INSERT INTO diff_comments (id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at, end_line_number, severity, author_kind)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'open', ?8, ?9, ?9, ?10, ?11, ?12)",
+        params![id, session_id, file_path, line_number, line_type, author, content, parent_id, now.to_rfc3339(), end_line_number, severity.as_str(), author_kind.as_str()],
+    )?;
+
+    Ok(DiffComment {
+        id,
+        session_id: session_id.to_string(),
+        file_path: file_path.to_string(),
+        line_number,
+        end_line_number,
+        line_type: line_type.map(String::from),
+        author: author.to_string(),
+        content: content.to_string(),
+        status: "open".to_string(),
+        severity: severity.to_string(),
+        parent_id: parent_id.map(String::from),
+        created_at: now,
+        updated_at: now,
+        convex_id: None,
+        sync_status: "pending".to_string(),
+        deleted_at: None,
+    })
+}
+
+/// Map a row selected as `id, session_id, file_path, line_number, line_type,
+/// author, content, status, parent_id, created_at, updated_at, end_line_number,
+/// severity`
+fn comment_from_row(row: &rusqlite::Row) -> Result<DiffComment> {
+    let created_at_str: String = row.get(9)?;
+    let updated_at_str: String = row.get(10)?;
+    Ok(DiffComment {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        file_path: row.get(2)?,
+        line_number: row.get(3)?,
+        end_line_number: row.get(11)?,
+        line_type: row.get(4)?,
+        author: row.get(5)?,
+        content: row.get(6)?,
+        status: row.get(7)?,
+        severity: row.get(12)?,
+        parent_id: row.get(8)?,
+        created_at: DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+        convex_id: None,
+        sync_status: "pending".to_string(),
+        deleted_at: None,
+    })
+}
+
+pub fn get_comments_for_session(session_id: &str) -> Result<Vec<DiffComment>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at, end_line_number, severity
+             FROM diff_comments
+             WHERE session_id = ?1
+             ORDER BY created_at ASC"
+        )?;
+        let comments = stmt
+            .query_map(params![session_id], comment_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(comments)
+    })
+}
+
+/// A session's comments (replies included) written by the user, or with
+/// `agent` set, by an agent, going by `author_kind`. Comments from before
+/// it was recorded fall back to the author name: `user_author` (the user's
+/// display name) or "user" count as the user, anything else as an agent.
+pub fn get_comments_for_session_by_author(
+    session_id: &str,
+    user_author: &str,
+    agent: bool,
+) -> Result<Vec<DiffComment>> {
+    let author_filter = if agent {
+        "(author_kind = 'agent' OR (author_kind IS NULL AND author NOT IN ('user', ?2)))"
+    } else {
+        "(author_kind = 'user' OR (author_kind IS NULL AND author IN ('user', ?2)))"
+    };
+    with_db(|conn| {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at, end_line_number, severity
+             FROM diff_comments
+             WHERE session_id = ?1 AND {}
+             ORDER BY created_at ASC",
+            author_filter
+        ))?;
+        let comments = stmt
+            .query_map(params![session_id, user_author], comment_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(comments)
+    })
+}
+
+pub fn get_open_comments_for_session(session_id: &str) -> Result<Vec<DiffComment>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at, end_line_number, severity
+             FROM diff_comments
+             WHERE session_id = ?1 AND status = 'open' AND parent_id IS NULL
+             ORDER BY created_at ASC"
+        )?;
+        let comments = stmt
+            .query_map(params![session_id], comment_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(comments)
+    })
+}
+
+/// Open top-level comments grouped by file (sorted by path), each group in
+/// line order with file-level comments first
+pub fn get_open_comments_grouped_by_file(
+    session_id: &str,
+) -> Result<Vec<(String, Vec<DiffComment>)>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at, end_line_number, severity
+             FROM diff_comments
+             WHERE session_id = ?1 AND status = 'open' AND parent_id IS NULL
+             ORDER BY file_path ASC, line_number IS NOT NULL, line_number ASC, created_at ASC"
+        )?;
+        let comments = stmt
+            .query_map(params![session_id], comment_from_row)?
+            .collect::<Result<Vec<_>>>()?;
 
-        conn.execute(
-            "INSERT INTO inbox_messages (id, session_id, message, created_at) VALUES (?1, ?2, ?3, ?4)",
-            params![id, session_id, message, created_at.to_rfc3339()],
+        let mut groups: Vec<(String, Vec<DiffComment>)> = Vec::new();
+        for comment in comments {
+            match groups.last_mut() {
+                Some((file_path, group)) if *file_path == comment.file_path => group.push(comment),
+                _ => groups.push((comment.file_path.clone(), vec![comment])),
+            }
+        }
+        Ok(groups)
+    })
+}
+
+/// Open top-level blocker comments, which should hold back approving the session
+pub fn get_blocking_comment_count(session_id: &str) -> Result<u32> {
+    with_db(|conn| {
+        conn.query_row(
+            "SELECT COUNT(*) FROM diff_comments
+             WHERE session_id = ?1 AND status = 'open' AND parent_id IS NULL AND severity = ?2",
+            params![session_id, CommentSeverity::Blocker.as_str()],
+            |row| row.get(0),
+        )
+    })
+}
+
+/// Files with open top-level comments and how many each has, sorted by path
+pub fn get_files_with_open_comments(session_id: &str) -> Result<Vec<(String, u32)>> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT file_path, COUNT(*)
+             FROM diff_comments
+             WHERE session_id = ?1 AND status = 'open' AND parent_id IS NULL
+             GROUP BY file_path
+             ORDER BY file_path ASC",
         )?;
+        let files = stmt
+            .query_map(params![session_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(files)
+    })
+}
 
-        // Get session name for the response
-        let session_name: String = conn
-            .query_row(
-                "SELECT name FROM sessions WHERE id = ?1",
-                params![session_id],
-                |row| row.get(0),
-            )
-            .unwrap_or_else(|_| "Unknown".to_string());
+/// One page of open top-level comments, plus the total number of them
+pub fn get_open_comments_for_session_paged(
+    session_id: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<(Vec<DiffComment>, u32)> {
+    with_db(|conn| {
+        let total: u32 = conn.query_row(
+            "SELECT COUNT(*) FROM diff_comments
+             WHERE session_id = ?1 AND status = 'open' AND parent_id IS NULL",
+            params![session_id],
+            |row| row.get(0),
+        )?;
 
-        Ok(InboxMessage {
-            id,
-            session_id: session_id.to_string(),
-            session_name,
-            message: message.to_string(),
-            created_at,
-            read_at: None,
-            first_read_at: None,
-            convex_id: None,
-            sync_status: "pending".to_string(),
-            deleted_at: None,
-        })
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at, end_line_number, severity
+             FROM diff_comments
+             WHERE session_id = ?1 AND status = 'open' AND parent_id IS NULL
+             ORDER BY created_at ASC
+             LIMIT ?2 OFFSET ?3"
+        )?;
+        let comments = stmt
+            .query_map(params![session_id, limit, offset], comment_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok((comments, total))
     })
 }
 
-pub fn get_all_inbox_messages() -> Result<Vec<InboxMessage>> {
+fn set_comment_anchor(conn: &Connection, id: &str, anchor: &CommentAnchor) -> Result<()> {
+    conn.execute(
+        "UPDATE diff_comments SET anchor_content = ?1, anchor_before = ?2, anchor_after = ?3 WHERE id = ?4",
+        params![
+            anchor.content,
+            anchor.before.join("\n"),
+            anchor.after.join("\n"),
+            id
+        ],
+    )?;
+    Ok(())
+}
+
+// All comments on a file (roots and replies) with their anchoring context
+pub fn get_comments_for_file_with_anchors(
+    session_id: &str,
+    file_path: &str,
+) -> Result<Vec<AnchoredComment>> {
     with_db(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT m.id, m.session_id, s.name, m.message, m.created_at, m.read_at, m.first_read_at
-             FROM inbox_messages m
-             LEFT JOIN sessions s ON m.session_id = s.id
-             ORDER BY m.created_at DESC",
+            "SELECT id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at, end_line_number, severity,
+                    anchor_content, anchor_before, anchor_after
+             FROM diff_comments
+             WHERE session_id = ?1 AND file_path = ?2
+             ORDER BY created_at ASC"
         )?;
-        let messages = stmt
-            .query_map([], |row| {
-                let created_at_str: String = row.get(4)?;
-                let read_at_str: Option<String> = row.get(5)?;
-                let first_read_at_str: Option<String> = row.get(6)?;
-                Ok(InboxMessage {
-                    id: row.get(0)?,
-                    session_id: row.get(1)?,
-                    session_name: row
-                        .get::<_, Option<String>>(2)?
-                        .unwrap_or_else(|| "Unknown".to_string()),
-                    message: row.get(3)?,
-                    created_at: DateTime::parse_from_rfc3339(&created_at_str)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    read_at: read_at_str.and_then(|s| {
-                        DateTime::parse_from_rfc3339(&s)
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .ok()
-                    }),
-                    first_read_at: first_read_at_str.and_then(|s| {
-                        DateTime::parse_from_rfc3339(&s)
-                            .map(|dt| dt.with_timezone(&Utc))
-                            .ok()
+        let comments = stmt
+            .query_map(params![session_id, file_path], |row| {
+                let anchor_content: Option<String> = row.get(13)?;
+                let anchor_before: Option<String> = row.get(14)?;
+                let anchor_after: Option<String> = row.get(15)?;
+                let split_lines = |s: Option<String>| {
+                    s.filter(|s| !s.is_empty())
+                        .map(|s| s.split('\n').map(String::from).collect())
+                        .unwrap_or_default()
+                };
+                Ok(AnchoredComment {
+                    comment: comment_from_row(row)?,
+                    anchor: anchor_content.map(|content| CommentAnchor {
+                        content,
+                        before: split_lines(anchor_before),
+                        after: split_lines(anchor_after),
                     }),
-                    convex_id: None,
-                    sync_status: "pending".to_string(),
-                    deleted_at: None,
                 })
             })?
             .collect::<Result<Vec<_>>>()?;
-        Ok(messages)
+        Ok(comments)
     })
 }
 
-pub fn mark_message_read(id: &str) -> Result<()> {
+// Move a comment (and its replies) to a new line after re-anchoring.
+// A range end shifts by the same amount so the span keeps its length.
+pub fn update_comment_line(id: &str, line_number: i32) -> Result<()> {
     let now = Utc::now().to_rfc3339();
     with_db(|conn| {
-        // Set read_at, and set first_read_at only if it's NULL (first time reading)
         conn.execute(
-            "UPDATE inbox_messages SET read_at = ?1, first_read_at = COALESCE(first_read_at, ?1) WHERE id = ?2",
-            params![now, id],
+            "UPDATE diff_comments
+             SET end_line_number = end_line_number + (?1 - line_number), line_number = ?1, updated_at = ?2
+             WHERE id = ?3 OR parent_id = ?3",
+            params![line_number, now, id],
         )?;
         Ok(())
     })
 }
 
-pub fn mark_message_unread(id: &str) -> Result<()> {
-    with_db(|conn| {
-        // Only clear read_at, keep first_read_at to indicate it was previously read
-        conn.execute(
-            "UPDATE inbox_messages SET read_at = NULL WHERE id = ?1",
+/// Manually move a root comment and its replies to another file/line, e.g.
+/// after re-anchoring reported it outdated. The anchor is replaced with the
+/// new line's, so later re-anchoring tracks the comment from there.
+/// Returns None if no root comment has this id.
+pub fn relocate_comment(
+    id: &str,
+    file_path: &str,
+    line_number: i32,
+    line_type: &str,
+    anchor: &CommentAnchor,
+) -> Result<Option<DiffComment>> {
+    let now = Utc::now().to_rfc3339();
+    with_tx(|tx| {
+        let updated = tx.execute(
+            "UPDATE diff_comments
+             SET file_path = ?1, end_line_number = end_line_number + (?2 - line_number),
+                 line_number = ?2, line_type = ?3, updated_at = ?4
+             WHERE (id = ?5 AND parent_id IS NULL) OR parent_id = ?5",
+            params![file_path, line_number, line_type, now, id],
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+
+        set_comment_anchor(tx, id, anchor)?;
+
+        tx.query_row(
+            "SELECT id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at, end_line_number, severity
+             FROM diff_comments WHERE id = ?1",
             params![id],
+            comment_from_row,
+        )
+        .map(Some)
+    })
+}
+
+// Count (open, resolved) comments for a session. Only root comments are
+// counted: replies share their thread's status and have no status of their own.
+pub fn get_comment_counts(session_id: &str) -> Result<(u32, u32)> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT status, COUNT(*) FROM diff_comments
+             WHERE session_id = ?1 AND parent_id IS NULL
+             GROUP BY status",
         )?;
-        Ok(())
+        let mut rows = stmt.query(params![session_id])?;
+        let (mut open, mut resolved) = (0u32, 0u32);
+        while let Some(row) = rows.next()? {
+            let status: String = row.get(0)?;
+            let count: u32 = row.get(1)?;
+            match status.as_str() {
+                "open" => open = count,
+                "resolved" => resolved = count,
+                _ => {}
+            }
+        }
+        Ok((open, resolved))
     })
 }
 
-// Mark all messages for a session as read
-pub fn mark_session_messages_read(session_id: &str) -> Result<u32> {
+pub fn reply_to_comment(
+    parent_id: &str,
+    author: &str,
+    content: &str,
+    author_kind: CommentAuthorKind,
+) -> Result<DiffComment> {
+    // Look up the parent and insert the reply in one transaction, so the reply
+    // can't be attached to a parent that was deleted in between
+    with_tx(|tx| {
+        // Get parent comment to copy session_id, file_path, line range
+        let mut stmt = tx.prepare(
+            "SELECT session_id, file_path, line_number, line_type, end_line_number FROM diff_comments WHERE id = ?1",
+        )?;
+        let parent = stmt.query_row(params![parent_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<i32>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<i32>>(4)?,
+            ))
+        })?;
+        drop(stmt);
+
+        insert_comment(
+            tx,
+            &parent.0,
+            &parent.1,
+            parent.2,
+            parent.4,
+            parent.3.as_deref(),
+            author,
+            content,
+            Some(parent_id),
+            CommentSeverity::default(),
+            author_kind,
+        )
+    })
+}
+
+/// Returns false if no comment has this id
+pub fn resolve_comment(id: &str) -> Result<bool> {
     let now = Utc::now().to_rfc3339();
     with_db(|conn| {
-        let count = conn.execute(
-            "UPDATE inbox_messages SET read_at = ?1, first_read_at = COALESCE(first_read_at, ?1)
-             WHERE session_id = ?2 AND read_at IS NULL",
-            params![now, session_id],
+        let updated = conn.execute(
+            "UPDATE diff_comments SET status = 'resolved', updated_at = ?1 WHERE id = ?2",
+            params![now, id],
         )?;
-        Ok(count as u32)
+        Ok(updated > 0)
     })
 }
 
-pub fn delete_inbox_message(id: &str) -> Result<()> {
+/// Set a resolved comment back to open.
+/// Returns false if no comment has this id
+pub fn reopen_comment(id: &str) -> Result<bool> {
+    let now = Utc::now().to_rfc3339();
     with_db(|conn| {
-        conn.execute("DELETE FROM inbox_messages WHERE id = ?1", params![id])?;
-        Ok(())
+        let updated = conn.execute(
+            "UPDATE diff_comments SET status = 'open', updated_at = ?1 WHERE id = ?2",
+            params![now, id],
+        )?;
+        Ok(updated > 0)
     })
 }
 
-pub fn clear_inbox() -> Result<()> {
+pub fn delete_comment(id: &str) -> Result<()> {
     with_db(|conn| {
-        conn.execute("DELETE FROM inbox_messages", [])?;
+        conn.execute("DELETE FROM diff_comments WHERE id = ?1", params![id])?;
         Ok(())
     })
 }
 
-// Diff Comment CRUD
-pub fn create_comment(
-    session_id: &str,
-    file_path: &str,
-    line_number: Option<i32>,
-    line_type: Option<&str>,
-    author: &str,
-    content: &str,
-    parent_id: Option<&str>,
-) -> Result<DiffComment> {
+// ========== RUN MESSAGES ==========
+
+/// Append a streamed message to a session's transcript; `seq` continues from
+/// the session's last stored message, so it spans runs.
+/// Returns the seq the message was stored with
+pub fn append_run_message(session_id: &str, role: &str, content_json: &str) -> Result<i64> {
     let id = uuid::Uuid::new_v4().to_string();
-    let now = Utc::now();
 
     with_db(|conn| {
-        conn.execute(
-            "INSERT INTO diff_comments (id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'open', ?8, ?9, ?9)",
-            params![id, session_id, file_path, line_number, line_type, author, content, parent_id, now.to_rfc3339()],
-        )?;
+        conn.query_row(
+            "INSERT INTO run_messages (id, session_id, seq, role, content_json, created_at)
+             SELECT ?1, ?2, COALESCE(MAX(seq), -1) + 1, ?3, ?4, ?5
+             FROM run_messages WHERE session_id = ?2
+             RETURNING seq",
+            params![id, session_id, role, content_json, Utc::now().to_rfc3339()],
+            |row| row.get(0),
+        )
+    })
+}
 
-        Ok(DiffComment {
-            id,
-            session_id: session_id.to_string(),
-            file_path: file_path.to_string(),
-            line_number,
-            line_type: line_type.map(String::from),
-            author: author.to_string(),
-            content: content.to_string(),
-            status: "open".to_string(),
-            parent_id: parent_id.map(String::from),
-            created_at: now,
-            updated_at: now,
-            convex_id: None,
-            sync_status: "pending".to_string(),
-            deleted_at: None,
-        })
+/// Completed turns in a session's transcript, i.e. its persisted results
+pub fn count_run_results(session_id: &str) -> Result<u64> {
+    with_db(|conn| {
+        conn.query_row(
+            "SELECT COUNT(*) FROM run_messages WHERE session_id = ?1 AND role = 'result'",
+            params![session_id],
+            |row| row.get(0),
+        )
     })
 }
 
-pub fn get_comments_for_session(session_id: &str) -> Result<Vec<DiffComment>> {
+pub fn get_run_messages(session_id: &str) -> Result<Vec<RunMessage>> {
     with_db(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at
-             FROM diff_comments
+            "SELECT id, session_id, seq, role, content_json, created_at
+             FROM run_messages
              WHERE session_id = ?1
-             ORDER BY created_at ASC"
+             ORDER BY seq ASC",
         )?;
-        let comments = stmt
+        let messages = stmt
             .query_map(params![session_id], |row| {
-                let created_at_str: String = row.get(9)?;
-                let updated_at_str: String = row.get(10)?;
-                Ok(DiffComment {
+                let created_at_str: String = row.get(5)?;
+                Ok(RunMessage {
                     id: row.get(0)?,
                     session_id: row.get(1)?,
-                    file_path: row.get(2)?,
-                    line_number: row.get(3)?,
-                    line_type: row.get(4)?,
-                    author: row.get(5)?,
-                    content: row.get(6)?,
-                    status: row.get(7)?,
-                    parent_id: row.get(8)?,
+                    seq: row.get(2)?,
+                    role: row.get(3)?,
+                    content_json: row.get(4)?,
                     created_at: DateTime::parse_from_rfc3339(&created_at_str)
                         .map(|dt| dt.with_timezone(&Utc))
                         .unwrap_or_else(|_| Utc::now()),
-                    updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    convex_id: None,
-                    sync_status: "pending".to_string(),
-                    deleted_at: None,
                 })
             })?
             .collect::<Result<Vec<_>>>()?;
-        Ok(comments)
+        Ok(messages)
     })
 }
 
-pub fn get_open_comments_for_session(session_id: &str) -> Result<Vec<DiffComment>> {
+// ========== RUN QUEUE ==========
+
+pub fn enqueue_run(
+    session_id: &str,
+    prompt: &str,
+    cwd: &str,
+    options_json: &str,
+) -> Result<PendingRun> {
+    let run = PendingRun {
+        id: uuid::Uuid::new_v4().to_string(),
+        session_id: session_id.to_string(),
+        prompt: prompt.to_string(),
+        cwd: cwd.to_string(),
+        options_json: options_json.to_string(),
+        queued_at: Utc::now(),
+    };
+
+    with_db(|conn| {
+        conn.execute(
+            "INSERT INTO pending_runs (id, session_id, prompt, cwd, options_json, queued_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                run.id,
+                run.session_id,
+                run.prompt,
+                run.cwd,
+                run.options_json,
+                run.queued_at.to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    })?;
+
+    Ok(run)
+}
+
+/// Queued runs, oldest first
+pub fn get_pending_runs() -> Result<Vec<PendingRun>> {
     with_db(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT id, session_id, file_path, line_number, line_type, author, content, status, parent_id, created_at, updated_at
-             FROM diff_comments
-             WHERE session_id = ?1 AND status = 'open' AND parent_id IS NULL
-             ORDER BY created_at ASC"
+            "SELECT id, session_id, prompt, cwd, options_json, queued_at
+             FROM pending_runs
+             ORDER BY queued_at ASC",
         )?;
-        let comments = stmt
-            .query_map(params![session_id], |row| {
-                let created_at_str: String = row.get(9)?;
-                let updated_at_str: String = row.get(10)?;
-                Ok(DiffComment {
+        let runs = stmt
+            .query_map([], |row| {
+                let queued_at_str: String = row.get(5)?;
+                Ok(PendingRun {
                     id: row.get(0)?,
                     session_id: row.get(1)?,
-                    file_path: row.get(2)?,
-                    line_number: row.get(3)?,
-                    line_type: row.get(4)?,
-                    author: row.get(5)?,
-                    content: row.get(6)?,
-                    status: row.get(7)?,
-                    parent_id: row.get(8)?,
-                    created_at: DateTime::parse_from_rfc3339(&created_at_str)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                    updated_at: DateTime::parse_from_rfc3339(&updated_at_str)
+                    prompt: row.get(2)?,
+                    cwd: row.get(3)?,
+                    options_json: row.get(4)?,
+                    queued_at: DateTime::parse_from_rfc3339(&queued_at_str)
                         .map(|dt| dt.with_timezone(&Utc))
                         .unwrap_or_else(|_| Utc::now()),
-                    convex_id: None,
-                    sync_status: "pending".to_string(),
-                    deleted_at: None,
                 })
             })?
             .collect::<Result<Vec<_>>>()?;
-        Ok(comments)
+        Ok(runs)
     })
 }
 
-pub fn reply_to_comment(parent_id: &str, author: &str, content: &str) -> Result<DiffComment> {
-    // Get parent comment to copy session_id, file_path, line_number
-    let parent = with_db(|conn| {
-        let mut stmt = conn.prepare(
-            "SELECT session_id, file_path, line_number, line_type FROM diff_comments WHERE id = ?1",
-        )?;
-        let mut rows = stmt.query(params![parent_id])?;
-        if let Some(row) = rows.next()? {
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, Option<i32>>(2)?,
-                row.get::<_, Option<String>>(3)?,
-            ))
-        } else {
-            Err(rusqlite::Error::QueryReturnedNoRows)
-        }
-    })?;
-
-    create_comment(
-        &parent.0,
-        &parent.1,
-        parent.2,
-        parent.3.as_deref(),
-        author,
-        content,
-        Some(parent_id),
-    )
+/// Returns false if no queued run has this id (e.g. it already started)
+pub fn delete_pending_run(id: &str) -> Result<bool> {
+    with_db(|conn| {
+        let deleted = conn.execute("DELETE FROM pending_runs WHERE id = ?1", params![id])?;
+        Ok(deleted > 0)
+    })
 }
 
-pub fn resolve_comment(id: &str) -> Result<()> {
-    let now = Utc::now().to_rfc3339();
+// ========== SESSION COSTS ==========
+
+/// Record the cost of a finished run. Kept when the session is deleted so
+/// usage totals stay accurate.
+pub fn record_session_cost(session_id: &str, cost_usd: f64, duration_ms: i64) -> Result<()> {
+    let id = uuid::Uuid::new_v4().to_string();
+
     with_db(|conn| {
         conn.execute(
-            "UPDATE diff_comments SET status = 'resolved', updated_at = ?1 WHERE id = ?2",
-            params![now, id],
+            "INSERT INTO session_costs (id, session_id, cost_usd, duration_ms, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                id,
+                session_id,
+                cost_usd,
+                duration_ms,
+                Utc::now().to_rfc3339()
+            ],
         )?;
         Ok(())
     })
 }
 
-pub fn delete_comment(id: &str) -> Result<()> {
+/// Costs recorded in `[from, to)`, per session and in total. An empty range
+/// yields zero totals.
+pub fn get_cost_summary(from: DateTime<Utc>, to: DateTime<Utc>) -> Result<CostSummary> {
     with_db(|conn| {
-        conn.execute("DELETE FROM diff_comments WHERE id = ?1", params![id])?;
-        Ok(())
+        let mut stmt = conn.prepare(
+            "SELECT c.session_id, s.name, SUM(c.cost_usd), SUM(c.duration_ms), COUNT(*)
+             FROM session_costs c
+             LEFT JOIN sessions s ON s.id = c.session_id
+             WHERE julianday(c.recorded_at) >= julianday(?1)
+               AND julianday(c.recorded_at) < julianday(?2)
+             GROUP BY c.session_id
+             ORDER BY SUM(c.cost_usd) DESC",
+        )?;
+        let sessions = stmt
+            .query_map(params![from.to_rfc3339(), to.to_rfc3339()], |row| {
+                Ok(SessionCost {
+                    session_id: row.get(0)?,
+                    session_name: row.get(1)?,
+                    cost_usd: row.get(2)?,
+                    duration_ms: row.get(3)?,
+                    runs: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(CostSummary {
+            total_cost_usd: sessions.iter().map(|s| s.cost_usd).sum(),
+            total_duration_ms: sessions.iter().map(|s| s.duration_ms).sum(),
+            sessions,
+        })
+    })
+}
+
+// ========== IMPORT ==========
+
+/// Merge workspaces, sessions, inbox messages and comments from another
+/// sessions.db. The source is attached read-only; rows whose id already
+/// exists here are skipped, never overwritten.
+pub fn import_from(path: &Path) -> Result<ImportStats> {
+    // SQLite URI: escape the characters that would end the path component
+    let uri = format!(
+        "file:{}?mode=ro",
+        path.to_string_lossy()
+            .replace('%', "%25")
+            .replace('?', "%3f")
+            .replace('#', "%23")
+    );
+
+    with_db(|conn| {
+        conn.execute("ATTACH DATABASE ?1 AS import_src", params![uri])?;
+        let result = import_attached(conn);
+        if let Err(e) = conn.execute("DETACH DATABASE import_src", []) {
+            warn!("[DB] Failed to detach imported database: {}", e);
+        }
+        result
+    })
+}
+
+fn import_attached(conn: &Connection) -> Result<ImportStats> {
+    let tx = conn.unchecked_transaction()?;
+    let stats = ImportStats {
+        workspaces: import_table(&tx, "workspaces")?,
+        sessions: import_table(&tx, "sessions")?,
+        inbox_messages: import_table(&tx, "inbox_messages")?,
+        comments: import_table(&tx, "diff_comments")?,
+    };
+    tx.commit()?;
+    Ok(stats)
+}
+
+/// Copy rows with new ids from `import_src.<table>`, using only the columns
+/// both schemas share so older exports still import
+fn import_table(conn: &Connection, table: &str) -> Result<TableImportCounts> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM import_src.sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get(0),
+    )?;
+    if exists == 0 {
+        return Ok(TableImportCounts::default());
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT name FROM pragma_table_info(?1, 'import_src')
+         WHERE name IN (SELECT name FROM pragma_table_info(?1, 'main'))",
+    )?;
+    let columns = stmt
+        .query_map(params![table], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>>>()?;
+    if !columns.iter().any(|c| c == "id") {
+        return Ok(TableImportCounts::default());
+    }
+    let columns = columns.join(", ");
+
+    let total: u32 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM import_src.{}", table),
+        [],
+        |row| row.get(0),
+    )?;
+    let imported = conn.execute(
+        &format!(
+            "INSERT INTO main.{table} ({columns})
+             SELECT {columns} FROM import_src.{table}
+             WHERE id NOT IN (SELECT id FROM main.{table})"
+        ),
+        [],
+    )? as u32;
+
+    Ok(TableImportCounts {
+        imported,
+        skipped: total - imported,
     })
 }
 
@@ -866,7 +2541,7 @@ pub fn increment_sync_attempts(id: &str, error: &str) -> Result<()> {
 pub fn get_unsynced_sessions() -> Result<Vec<Session>> {
     with_db(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT id, name, cwd, workspace_id, worktree_name, status, base_commit, created_at, updated_at, convex_id, sync_status, deleted_at
+            "SELECT id, name, cwd, workspace_id, worktree_name, status, base_commit, created_at, updated_at, convex_id, sync_status, deleted_at, last_error
              FROM sessions
              WHERE sync_status = 'pending' AND deleted_at IS NULL
              ORDER BY created_at",
@@ -884,6 +2559,8 @@ pub fn get_unsynced_sessions() -> Result<Vec<Session>> {
                     worktree_name: row.get(4)?,
                     status: row.get(5)?,
                     base_commit: row.get(6)?,
+                    tags: Vec::new(),
+                    last_error: row.get(12)?,
                     created_at: DateTime::parse_from_rfc3339(&created_at_str)
                         .map(|dt| dt.with_timezone(&Utc))
                         .unwrap_or_else(|_| Utc::now()),
@@ -1,7 +1,13 @@
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::path::Path;
-use std::process::{Command, ExitStatus};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::{Component, Path};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileDiff {
@@ -11,6 +17,20 @@ pub struct FileDiff {
     pub insertions: u32,
     pub deletions: u32,
     pub hunks: Vec<DiffHunk>,
+    #[serde(default)]
+    pub mode_change: Option<(String, String)>, // (old, new) file mode, e.g. ("100644", "100755")
+    #[serde(default)]
+    pub is_symlink: bool, // Content is the link target (a single line in the hunk)
+    #[serde(default)]
+    pub language: Option<String>, // Syntax highlighting hint; None if unknown
+    #[serde(default)]
+    pub has_conflict_markers: bool, // Contains <<<<<<< / ======= / >>>>>>> lines
+    #[serde(default)]
+    pub content_hash: String, // SHA-256 of the raw diff; empty when hunks aren't loaded
+    #[serde(default)]
+    pub generated: bool, // Lockfile, bundle, snapshot, or linguist-generated
+    #[serde(default)]
+    pub large: bool, // More than LARGE_FILE_LINES lines changed; set in summaries and full diffs
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +49,23 @@ pub struct DiffLine {
     pub old_line: Option<u32>,
     pub new_line: Option<u32>,
     pub content: String,
+    #[serde(default)]
+    pub segments: Vec<DiffSegment>, // Word-level spans; empty unless word diff was requested
+}
+
+/// A run of text within a changed line, for highlighting the exact words that changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffSegment {
+    pub kind: String, // "context", "add", "delete"
+    pub text: String,
+}
+
+/// Result of rebasing a worktree onto its base
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebaseOutcome {
+    pub success: bool,
+    pub conflicts: Vec<String>, // Files with conflicts; the rebase is left in progress
+    pub output: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,10 +76,316 @@ pub struct DiffSummary {
     pub total_files: u32,
 }
 
+/// A file diff laid out for a side-by-side viewer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitDiff {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: String,
+    pub hunks: Vec<SplitHunk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitHunk {
+    pub header: String,
+    pub rows: Vec<SplitRow>,
+}
+
+/// One row of a split view. Context lines appear on both sides; a missing
+/// side is a blank where the other side added or deleted a line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitRow {
+    pub left: Option<DiffLine>,  // Old side: context or delete
+    pub right: Option<DiffLine>, // New side: context or add
+}
+
+/// A single hunk of a file's diff, for loading large diffs on demand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiffHunk {
+    pub hunk: DiffHunk,
+    pub hunk_index: usize,
+    pub total_hunks: usize,
+}
+
+/// Complete old and new contents of a file, for a merge view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileVersions {
+    pub old_content: Option<String>, // None if the file is new (or binary)
+    pub new_content: Option<String>, // None if the file was deleted (or binary)
+    pub is_binary: bool,
+}
+
+/// Whether a file is worth reviewing, from `classify_files`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileClassification {
+    pub path: String,
+    pub generated: bool,
+    pub large: bool, // More than LARGE_FILE_LINES lines changed, as in `FileDiff`
+}
+
+/// Who last changed a line, as of some ref
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlameLine {
+    pub line_number: u32,
+    pub commit_sha: String,
+    pub author: String,
+    pub date: String, // RFC3339 author time
+    pub summary: String,
+}
+
+/// Line count above which a file (or its diff) is flagged `large`
+const LARGE_FILE_LINES: u32 = 1000;
+
+/// Generated files by name, wherever they are (lockfiles)
+const GENERATED_FILE_NAMES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "pnpm-lock.yaml",
+    "go.sum",
+];
+
+/// Generated files by suffix (`*.lock`, `*.min.js`, ...)
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".lock", ".min.js", ".min.css", ".map", ".snap", ".pb.go", "_pb2.py",
+];
+
+/// Directories whose contents are generated (`dist/**`, ...), at any depth
+const GENERATED_DIRS: &[&str] = &["dist", "vendor", "node_modules", "__snapshots__"];
+
+/// Largest file `blame_file` will process
+const MAX_BLAME_BYTES: u64 = 1024 * 1024;
+
+/// How much of a file is scanned for conflict markers
+const MAX_CONFLICT_SCAN_BYTES: u64 = 1024 * 1024;
+
+/// Bytes git inspects for a NUL when deciding a file is binary
+const BINARY_CHECK_BYTES: usize = 8000;
+
+/// Git's file mode for symbolic links
+const SYMLINK_MODE: &str = "120000";
+
+/// Mode given to paths that don't exist on one side of a diff
+const MISSING_MODE: &str = "000000";
+
+/// Subcommands `run_readonly` will run; everything else is rejected
+const READONLY_SUBCOMMANDS: &[&str] = &[
+    "log",
+    "show",
+    "diff",
+    "status",
+    "rev-parse",
+    "branch",
+    "remote",
+];
+
+/// Flags that make `git branch` create, delete, rename or reconfigure branches
+const BRANCH_WRITE_FLAGS: &[&str] = &[
+    "-d",
+    "-D",
+    "--delete",
+    "-m",
+    "-M",
+    "--move",
+    "-c",
+    "-C",
+    "--copy",
+    "-f",
+    "--force",
+    "-u",
+    "--set-upstream-to",
+    "--unset-upstream",
+    "--edit-description",
+    "-t",
+    "--track",
+    "--no-track",
+    "--create-reflog",
+];
+
+/// Reject file paths that could escape the worktree: absolute paths,
+/// `..` components, and (on Windows) drive prefixes.
+pub fn validate_relative_path(path: &str) -> Result<(), String> {
+    if path.trim().is_empty() {
+        return Err("File path must not be empty".to_string());
+    }
+    if path.contains('\0') {
+        return Err(format!("Invalid file path {:?}: contains NUL byte", path));
+    }
+
+    let escapes = Path::new(path).components().any(|component| {
+        matches!(
+            component,
+            Component::RootDir | Component::Prefix(_) | Component::ParentDir
+        )
+    });
+    // Windows forms aren't recognised by `Path` on other platforms, but the
+    // path may still reach a Windows checkout
+    let bytes = path.as_bytes();
+    let windows_absolute = path.starts_with('\\')
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':');
+    let windows_parent = path.split('\\').any(|part| part == "..");
+    if escapes || windows_absolute || windows_parent {
+        return Err(format!(
+            "Invalid file path {:?}: must be relative to the worktree without '..'",
+            path
+        ));
+    }
+
+    Ok(())
+}
+
 fn diff_status_ok(status: ExitStatus) -> bool {
     matches!(status.code(), Some(0) | Some(1))
 }
 
+/// Cancellable operations in progress, keyed by the caller's operation id
+static OPERATIONS: Lazy<Mutex<HashMap<String, Operation>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// How often a cancellable git process is checked for exit
+const OPERATION_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+struct Operation {
+    child: Option<Child>, // The git process running for it right now
+    cancelled: bool,
+}
+
+fn lock_operations() -> MutexGuard<'static, HashMap<String, Operation>> {
+    OPERATIONS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A registered operation id; unregistered when dropped. Without an id,
+/// git runs normally and can't be cancelled.
+struct OperationGuard {
+    op_id: Option<String>,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if let Some(op_id) = &self.op_id {
+            lock_operations().remove(op_id);
+        }
+    }
+}
+
+fn begin_operation(op_id: Option<&str>) -> Result<OperationGuard, String> {
+    let Some(op_id) = op_id else {
+        return Ok(OperationGuard { op_id: None });
+    };
+    let mut operations = lock_operations();
+    if operations.contains_key(op_id) {
+        return Err(format!("Git operation {} is already running", op_id));
+    }
+    operations.insert(op_id.to_string(), Operation::default());
+    Ok(OperationGuard {
+        op_id: Some(op_id.to_string()),
+    })
+}
+
+fn cancelled_error(op_id: &str) -> String {
+    format!("Git operation {} was cancelled", op_id)
+}
+
+/// Like `Command::output`, but the process can be killed by `cancel_operation`
+/// while it runs. `name` labels spawn errors, e.g. "git fetch".
+fn run_git(command: &mut Command, op: &OperationGuard, name: &str) -> Result<Output, String> {
+    let Some(op_id) = op.op_id.as_deref() else {
+        return command
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", name, e));
+    };
+
+    let (stdout, stderr) = {
+        // Held across the spawn, so a cancel can't land between the check and
+        // the child being recorded
+        let mut operations = lock_operations();
+        let operation = operations.entry(op_id.to_string()).or_default();
+        if operation.cancelled {
+            return Err(cancelled_error(op_id));
+        }
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run {}: {}", name, e))?;
+        let pipes = (child.stdout.take(), child.stderr.take());
+        operation.child = Some(child);
+        pipes
+    };
+
+    // Drain both pipes while waiting, so a chatty process can't block on a full pipe
+    let stdout_reader = read_pipe(stdout);
+    let stderr_reader = read_pipe(stderr);
+
+    let status = loop {
+        {
+            let mut operations = lock_operations();
+            // Gone means cancel_operation killed and took it
+            let Some(operation) = operations.get_mut(op_id) else {
+                break None;
+            };
+            let Some(child) = operation.child.as_mut() else {
+                break None;
+            };
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    operation.child = None;
+                    break Some(status);
+                }
+                Ok(None) => {}
+                Err(e) => return Err(format!("Failed to wait for {}: {}", name, e)),
+            }
+        }
+        thread::sleep(OPERATION_POLL_INTERVAL);
+    };
+
+    // On cancel the readers are left to finish on their own: a killed git's
+    // helpers (e.g. git-remote-https) can hold the pipes open a little longer
+    let Some(status) = status else {
+        return Err(cancelled_error(op_id));
+    };
+    Ok(Output {
+        status,
+        stdout: stdout_reader.join().unwrap_or_default(),
+        stderr: stderr_reader.join().unwrap_or_default(),
+    })
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+/// Fail early if the operation was cancelled between git runs
+fn check_cancelled(op: &OperationGuard) -> Result<(), String> {
+    match op.op_id.as_deref() {
+        Some(op_id) if lock_operations().get(op_id).is_some_and(|o| o.cancelled) => {
+            Err(cancelled_error(op_id))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Kill the git process of a running operation and make the rest of it fail.
+/// Returns false if no operation has this id (e.g. it already finished).
+pub fn cancel_operation(op_id: &str) -> bool {
+    let mut operations = lock_operations();
+    let Some(operation) = operations.get_mut(op_id) else {
+        return false;
+    };
+    operation.cancelled = true;
+    if let Some(mut child) = operation.child.take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    true
+}
+
 fn get_untracked_files(worktree_path: &Path) -> Result<Vec<String>, String> {
     let output = Command::new("git")
         .current_dir(worktree_path)
@@ -98,7 +441,12 @@ fn get_untracked_numstat(worktree_path: &Path, file_path: &str) -> Result<(u32,
         return Err(format!("git diff --no-index failed: {}", stderr));
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_numstat(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Insertions and deletions from the first line of `--numstat` output.
+/// Binary files report `-` for both and count as `(0, 0)`.
+fn parse_numstat(stdout: &str) -> (u32, u32) {
     let line = stdout.lines().find(|l| !l.trim().is_empty());
     if let Some(line) = line {
         let parts: Vec<&str> = line.split('\t').collect();
@@ -110,10 +458,10 @@ fn get_untracked_numstat(worktree_path: &Path, file_path: &str) -> Result<(u32,
             .get(1)
             .and_then(|v| v.parse::<u32>().ok())
             .unwrap_or(0);
-        return Ok((insertions, deletions));
+        return (insertions, deletions);
     }
 
-    Ok((0, 0))
+    (0, 0)
 }
 
 /// Get a summary of changes between the worktree and a base branch
@@ -133,6 +481,7 @@ pub fn get_diff_summary(worktree_path: &str, base_branch: &str) -> Result<DiffSu
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut file_modes = get_file_modes(path, base_branch)?;
     let mut files = Vec::new();
     let mut file_set = HashSet::new();
     let mut total_insertions = 0u32;
@@ -147,6 +496,8 @@ pub fn get_diff_summary(worktree_path: &str, base_branch: &str) -> Result<DiffSu
         if parts.len() >= 3 {
             let insertions = parts[0].parse::<u32>().unwrap_or(0);
             let deletions = parts[1].parse::<u32>().unwrap_or(0);
+            // numstat reports "-" for binary files
+            let is_binary = parts[0] == "-";
             let file_path = parts[2].to_string();
 
             // Determine file status
@@ -155,6 +506,11 @@ pub fn get_diff_summary(worktree_path: &str, base_branch: &str) -> Result<DiffSu
             total_insertions += insertions;
             total_deletions += deletions;
 
+            let (mode_change, is_symlink) = classify_modes(file_modes.remove(&file_path));
+            let language = detect_language(&file_path);
+            let has_conflict_markers =
+                !is_symlink && !is_binary && file_has_conflict_markers(path, &file_path);
+
             files.push(FileDiff {
                 path: file_path,
                 old_path: None,
@@ -162,6 +518,13 @@ pub fn get_diff_summary(worktree_path: &str, base_branch: &str) -> Result<DiffSu
                 insertions,
                 deletions,
                 hunks: Vec::new(), // Hunks loaded separately
+                mode_change,
+                is_symlink,
+                language,
+                has_conflict_markers,
+                content_hash: String::new(),
+                generated: false, // Set by mark_review_exclusions
+                large: false,
             });
             file_set.insert(files.last().unwrap().path.clone());
         }
@@ -174,16 +537,28 @@ pub fn get_diff_summary(worktree_path: &str, base_branch: &str) -> Result<DiffSu
         let (insertions, deletions) = get_untracked_numstat(path, &file_path)?;
         total_insertions += insertions;
         total_deletions += deletions;
+        let is_symlink = std::fs::symlink_metadata(path.join(&file_path))
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false);
         files.push(FileDiff {
+            language: detect_language(&file_path),
+            has_conflict_markers: !is_symlink && file_has_conflict_markers(path, &file_path),
             path: file_path,
             old_path: None,
             status: "added".to_string(),
             insertions,
             deletions,
             hunks: Vec::new(),
+            mode_change: None,
+            is_symlink,
+            content_hash: String::new(),
+            generated: false, // Set by mark_review_exclusions
+            large: false,
         });
     }
 
+    mark_review_exclusions(path, &mut files)?;
+
     Ok(DiffSummary {
         total_files: files.len() as u32,
         files,
@@ -192,6 +567,235 @@ pub fn get_diff_summary(worktree_path: &str, base_branch: &str) -> Result<DiffSu
     })
 }
 
+/// (old, new) file modes of every changed file against the base, keyed by
+/// path. Used to spot mode-only changes (chmod +x), which have no hunks and
+/// show as 0/0 in numstat, and symlinks.
+fn get_file_modes(
+    worktree_path: &Path,
+    base_branch: &str,
+) -> Result<HashMap<String, (String, String)>, String> {
+    let output = Command::new("git")
+        .current_dir(worktree_path)
+        .args(["diff", "--raw", "--ignore-submodules", base_branch])
+        .output()
+        .map_err(|e| format!("Failed to run git diff --raw: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff --raw failed: {}", stderr));
+    }
+
+    // Lines look like ":100644 100755 abc1234 0000000 M\tpath/to/file"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (info, paths) = line.strip_prefix(':')?.split_once('\t')?;
+            let mut modes = info.split_whitespace();
+            let old_mode = modes.next()?.to_string();
+            let new_mode = modes.next()?.to_string();
+            // Renames and copies list "old\tnew"; the new path is last
+            let file_path = paths.rsplit('\t').next()?.to_string();
+            Some((file_path, (old_mode, new_mode)))
+        })
+        .collect())
+}
+
+/// Split a file's (old, new) modes into a real mode change (not an add or
+/// delete) and whether either side is a symlink
+fn classify_modes(modes: Option<(String, String)>) -> (Option<(String, String)>, bool) {
+    let is_symlink = modes
+        .as_ref()
+        .is_some_and(|(old, new)| old == SYMLINK_MODE || new == SYMLINK_MODE);
+    let mode_change =
+        modes.filter(|(old, new)| old != new && old != MISSING_MODE && new != MISSING_MODE);
+    (mode_change, is_symlink)
+}
+
+/// Flag generated and large files in a summary, so the UI can collapse them
+fn mark_review_exclusions(worktree_path: &Path, files: &mut [FileDiff]) -> Result<(), String> {
+    let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    let attributed = get_linguist_generated(worktree_path, &paths)?;
+    for file in files.iter_mut() {
+        file.generated = attributed.contains(&file.path) || is_generated_path(&file.path);
+        file.large = file.insertions + file.deletions > LARGE_FILE_LINES;
+    }
+    Ok(())
+}
+
+/// Classify files as generated (by `.gitattributes` or name) and large (by
+/// numstat against `base_branch`, default HEAD, the same measure summaries use)
+pub fn classify_files(
+    worktree_path: &str,
+    files: &[String],
+    base_branch: Option<&str>,
+) -> Result<Vec<FileClassification>, String> {
+    for file_path in files {
+        validate_relative_path(file_path)?;
+    }
+    let base = resolve_ref(worktree_path, "base_branch", base_branch.unwrap_or("HEAD"))?;
+    let path = Path::new(worktree_path);
+    let paths: Vec<&str> = files.iter().map(String::as_str).collect();
+    let attributed = get_linguist_generated(path, &paths)?;
+    let changed = get_changed_line_counts(path, &base)?;
+    let untracked: HashSet<String> = get_untracked_files(path)?.into_iter().collect();
+
+    files
+        .iter()
+        .map(|file_path| {
+            // An untracked file's numstat is its line count, so count directly
+            // rather than running a diff per file
+            let large = match changed.get(file_path) {
+                Some(lines) => *lines > LARGE_FILE_LINES,
+                None if untracked.contains(file_path) => {
+                    has_more_lines_than(&path.join(file_path), LARGE_FILE_LINES)?
+                }
+                None => false,
+            };
+            Ok(FileClassification {
+                path: file_path.clone(),
+                generated: attributed.contains(file_path) || is_generated_path(file_path),
+                large,
+            })
+        })
+        .collect()
+}
+
+/// Lines changed (insertions + deletions) per tracked file against `base`
+fn get_changed_line_counts(path: &Path, base: &str) -> Result<HashMap<String, u32>, String> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args([
+            "diff",
+            "--numstat",
+            "-z",
+            "--no-renames",
+            "--ignore-submodules",
+            base,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to run git diff --numstat: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff --numstat failed: {}", stderr));
+    }
+
+    // "insertions\tdeletions\tpath" records; binary files report "-"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter_map(|record| {
+            let mut parts = record.splitn(3, '\t');
+            let insertions = parts.next()?.parse::<u32>().unwrap_or(0);
+            let deletions = parts.next()?.parse::<u32>().unwrap_or(0);
+            Some((parts.next()?.to_string(), insertions + deletions))
+        })
+        .collect())
+}
+
+/// Whether a file has more than `limit` lines, reading only until it's past
+/// the limit. Binary files (a NUL in the first `BINARY_CHECK_BYTES`, as git
+/// decides) count as having no lines, matching numstat.
+fn has_more_lines_than(path: &Path, limit: u32) -> Result<bool, String> {
+    let mut file =
+        std::fs::File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut lines: u32 = 0;
+    let mut last_byte = b'\n';
+    let mut first_chunk = true;
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &buffer[..read];
+        if first_chunk && chunk[..read.min(BINARY_CHECK_BYTES)].contains(&0) {
+            return Ok(false);
+        }
+        first_chunk = false;
+        lines += chunk.iter().filter(|&&b| b == b'\n').count() as u32;
+        if lines > limit {
+            return Ok(true);
+        }
+        last_byte = chunk[read - 1];
+    }
+    // A last line without a trailing newline still counts
+    if last_byte != b'\n' {
+        lines += 1;
+    }
+    Ok(lines > limit)
+}
+
+/// Paths marked `linguist-generated` in `.gitattributes`
+fn get_linguist_generated(worktree_path: &Path, paths: &[&str]) -> Result<HashSet<String>, String> {
+    if paths.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    // Paths go over stdin, so a large changeset can't exceed the argument limit
+    let mut child = Command::new("git")
+        .current_dir(worktree_path)
+        .args(["check-attr", "--stdin", "-z", "linguist-generated"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run git check-attr: {}", e))?;
+
+    let mut input = Vec::new();
+    for path in paths {
+        input.extend_from_slice(path.as_bytes());
+        input.push(0);
+    }
+    // Written from a thread: git answers as it reads, and a full stdout pipe
+    // would otherwise block both sides
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or("Failed to open git check-attr stdin")?;
+    let writer = thread::spawn(move || stdin.write_all(&input));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run git check-attr: {}", e))?;
+    writer
+        .join()
+        .map_err(|_| "git check-attr stdin writer panicked".to_string())?
+        .map_err(|e| format!("Failed to write to git check-attr: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git check-attr failed: {}", stderr));
+    }
+
+    // NUL-separated (path, attribute, value) triples
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = stdout.split('\0').collect();
+    Ok(fields
+        .chunks(3)
+        .filter(|entry| entry.len() == 3 && matches!(entry[2], "set" | "true"))
+        .map(|entry| entry[0].to_string())
+        .collect())
+}
+
+/// Lockfiles, minified bundles, snapshots and the like, by path alone
+fn is_generated_path(file_path: &str) -> bool {
+    let path = Path::new(file_path);
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(file_path);
+    let in_generated_dir = path.parent().is_some_and(|dir| {
+        dir.components()
+            .any(|c| GENERATED_DIRS.iter().any(|d| c.as_os_str() == *d))
+    });
+    GENERATED_FILE_NAMES.contains(&name)
+        || GENERATED_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+        || in_generated_dir
+}
+
 /// Get file status (added, modified, deleted, renamed)
 fn get_file_status(
     worktree_path: &Path,
@@ -227,41 +831,193 @@ fn get_file_status(
     })
 }
 
-/// Get detailed diff for a specific file with hunks
-pub fn get_file_diff(
+/// Resolve a user-supplied ref to a SHA, naming which argument failed
+fn resolve_ref(worktree_path: &str, label: &str, ref_name: &str) -> Result<String, String> {
+    if ref_name.is_empty() || ref_name.starts_with('-') {
+        return Err(format!("Invalid {} {:?}", label, ref_name));
+    }
+    get_commit_sha(worktree_path, ref_name)
+        .map_err(|e| format!("Invalid {} {:?}: {}", label, ref_name, e.trim()))
+}
+
+/// Summary of changes between two commits/refs (`git diff <from>..<to>`),
+/// independent of the worktree's state
+pub fn get_diff_between(
     worktree_path: &str,
-    file_path: &str,
-    base_branch: &str,
-) -> Result<FileDiff, String> {
-    let path = Path::new(worktree_path);
+    from_ref: &str,
+    to_ref: &str,
+) -> Result<DiffSummary, String> {
+    let from = resolve_ref(worktree_path, "from_ref", from_ref)?;
+    let to = resolve_ref(worktree_path, "to_ref", to_ref)?;
+    summarize_diff(Path::new(worktree_path), &format!("{}..{}", from, to))
+}
 
-    if is_untracked_file(path, file_path)? {
-        let output = Command::new("git")
-            .current_dir(path)
-            .args(["diff", "-U3", "--no-index", "/dev/null", file_path])
-            .output()
-            .map_err(|e| format!("Failed to get file diff: {}", e))?;
+/// Summary of the changes staged in the index (`git diff --cached`)
+pub fn get_staged_diff_summary(worktree_path: &str) -> Result<DiffSummary, String> {
+    summarize_diff(Path::new(worktree_path), "--cached")
+}
 
-        if !diff_status_ok(output.status) {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("git diff --no-index failed: {}", stderr));
-        }
+/// Summary for a diff that doesn't read worktree files: a commit range, or
+/// `--cached` for the index
+fn summarize_diff(path: &Path, spec: &str) -> Result<DiffSummary, String> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["diff", "--numstat", "-z", "--ignore-submodules", spec])
+        .output()
+        .map_err(|e| format!("Failed to run git diff: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff failed: {}", stderr));
+    }
+
+    let statuses = get_range_statuses(path, spec)?;
+    let mut file_modes = get_file_modes(path, spec)?;
+    let mut files = Vec::new();
+    let mut total_insertions = 0u32;
+    let mut total_deletions = 0u32;
+
+    // -z keeps paths unquoted and gives a rename's old and new path as
+    // separate fields, instead of "old => new"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut fields = stdout.split('\0');
+    while let Some(field) = fields.next() {
+        let mut parts = field.splitn(3, '\t');
+        let (Some(insertions), Some(deletions), Some(file_path)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let insertions = insertions.parse::<u32>().unwrap_or(0);
+        let deletions = deletions.parse::<u32>().unwrap_or(0);
+        // Renames and copies leave the path empty; the paths follow
+        let (old_path, file_path) = if file_path.is_empty() {
+            let (Some(old_path), Some(new_path)) = (fields.next(), fields.next()) else {
+                break;
+            };
+            (Some(old_path.to_string()), new_path.to_string())
+        } else {
+            (None, file_path.to_string())
+        };
+
+        let (mode_change, is_symlink) = classify_modes(file_modes.remove(&file_path));
+
+        total_insertions += insertions;
+        total_deletions += deletions;
 
-        let diff_content = String::from_utf8_lossy(&output.stdout);
-        return parse_unified_diff(&diff_content, file_path);
+        files.push(FileDiff {
+            status: statuses
+                .get(&file_path)
+                .cloned()
+                .unwrap_or_else(|| "modified".to_string()),
+            language: detect_language(&file_path),
+            path: file_path,
+            old_path,
+            insertions,
+            deletions,
+            hunks: Vec::new(), // Hunks loaded separately
+            mode_change,
+            is_symlink,
+            has_conflict_markers: false, // Not worktree content; not checked
+            content_hash: String::new(),
+            generated: false, // Set by mark_review_exclusions
+            large: false,
+        });
     }
 
-    // Get the unified diff for this file
+    mark_review_exclusions(path, &mut files)?;
+
+    Ok(DiffSummary {
+        total_files: files.len() as u32,
+        files,
+        total_insertions,
+        total_deletions,
+    })
+}
+
+/// File statuses for a commit range or `--cached`, keyed by (new) path
+fn get_range_statuses(
+    worktree_path: &Path,
+    range: &str,
+) -> Result<HashMap<String, String>, String> {
     let output = Command::new("git")
-        .current_dir(path)
-        .args([
-            "diff",
-            "-U3",
-            "--ignore-submodules",
-            base_branch,
-            "--",
-            file_path,
-        ])
+        .current_dir(worktree_path)
+        .args(["diff", "--name-status", "-z", "--ignore-submodules", range])
+        .output()
+        .map_err(|e| format!("Failed to get file statuses: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff --name-status failed: {}", stderr));
+    }
+
+    // NUL-separated: a status code, then one path, or old and new path for
+    // renames and copies
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut fields = stdout.split('\0').filter(|field| !field.is_empty());
+    let mut statuses = HashMap::new();
+    while let Some(code) = fields.next() {
+        let (status, path_count) = match code.chars().next() {
+            Some('A') => ("added", 1),
+            Some('D') => ("deleted", 1),
+            Some('R') => ("renamed", 2),
+            Some('C') => ("copied", 2),
+            _ => ("modified", 1),
+        };
+        let Some(file_path) = fields.by_ref().take(path_count).last() else {
+            break;
+        };
+        statuses.insert(file_path.to_string(), status.to_string());
+    }
+    Ok(statuses)
+}
+
+/// Hunks for one file between two commits/refs. Pass a renamed file's
+/// `old_path` (from the summary) so it diffs as a rename, not an add.
+pub fn get_file_diff_between(
+    worktree_path: &str,
+    from_ref: &str,
+    to_ref: &str,
+    file_path: &str,
+    old_path: Option<&str>,
+) -> Result<FileDiff, String> {
+    validate_relative_path(file_path)?;
+    let from = resolve_ref(worktree_path, "from_ref", from_ref)?;
+    let to = resolve_ref(worktree_path, "to_ref", to_ref)?;
+    diff_file(
+        worktree_path,
+        &format!("{}..{}", from, to),
+        file_path,
+        old_path,
+    )
+}
+
+/// Hunks for one file's staged changes (`git diff --cached`). `old_path` as
+/// for `get_file_diff_between`.
+pub fn get_staged_file_diff(
+    worktree_path: &str,
+    file_path: &str,
+    old_path: Option<&str>,
+) -> Result<FileDiff, String> {
+    validate_relative_path(file_path)?;
+    diff_file(worktree_path, "--cached", file_path, old_path)
+}
+
+/// Hunks for one file in a commit range or `--cached`
+fn diff_file(
+    worktree_path: &str,
+    spec: &str,
+    file_path: &str,
+    old_path: Option<&str>,
+) -> Result<FileDiff, String> {
+    if let Some(old_path) = old_path {
+        validate_relative_path(old_path)?;
+    }
+    // Rename detection only pairs paths that are both in the pathspec
+    let output = Command::new("git")
+        .current_dir(worktree_path)
+        .args(["diff", "-U3", "--ignore-submodules", spec, "--", file_path])
+        .args(old_path)
         .output()
         .map_err(|e| format!("Failed to get file diff: {}", e))?;
 
@@ -270,11 +1026,697 @@ pub fn get_file_diff(
         return Err(format!("git diff failed: {}", stderr));
     }
 
-    let diff_content = String::from_utf8_lossy(&output.stdout);
-    parse_unified_diff(&diff_content, file_path)
+    parse_unified_diff(&String::from_utf8_lossy(&output.stdout), file_path)
 }
 
-/// Parse a unified diff format into structured data
+/// A file's full content at the base (`git show <base>:<path>`) and in the
+/// worktree. Binary files (on either side) return neither.
+pub fn get_file_versions(
+    worktree_path: &str,
+    file_path: &str,
+    base_branch: &str,
+) -> Result<FileVersions, String> {
+    validate_relative_path(file_path)?;
+    let base = resolve_ref(worktree_path, "base_branch", base_branch)?;
+    let path = Path::new(worktree_path);
+    let spec = format!("{}:{}", base, file_path);
+
+    let exists_at_base = Command::new("git")
+        .current_dir(path)
+        .args(["cat-file", "-e", &spec])
+        .output()
+        .map_err(|e| format!("Failed to run git cat-file: {}", e))?
+        .status
+        .success();
+
+    let old_bytes = if exists_at_base {
+        let output = Command::new("git")
+            .current_dir(path)
+            .args(["show", &spec])
+            .output()
+            .map_err(|e| format!("Failed to run git show: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("git show failed: {}", stderr));
+        }
+        Some(output.stdout)
+    } else {
+        None
+    };
+
+    // Symlinks are compared by target, as git stores them
+    let full_path = path.join(file_path);
+    let new_bytes = match std::fs::symlink_metadata(&full_path) {
+        Ok(meta) if meta.file_type().is_symlink() => std::fs::read_link(&full_path)
+            .map(|target| Some(target.to_string_lossy().into_owned().into_bytes())),
+        Ok(_) => std::fs::read(&full_path).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+    .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+
+    let is_binary = [&old_bytes, &new_bytes]
+        .into_iter()
+        .flatten()
+        .any(|bytes| is_binary_content(bytes));
+    if is_binary {
+        return Ok(FileVersions {
+            old_content: None,
+            new_content: None,
+            is_binary,
+        });
+    }
+
+    // Not binary, so valid UTF-8
+    let to_string = |bytes: Vec<u8>| String::from_utf8(bytes).unwrap_or_default();
+    Ok(FileVersions {
+        old_content: old_bytes.map(to_string),
+        new_content: new_bytes.map(to_string),
+        is_binary,
+    })
+}
+
+/// Last commit to touch each line of a file at `ref_name`
+/// (`git blame --line-porcelain`). Binary files and files over
+/// `MAX_BLAME_BYTES` are rejected. Cancellable via `op_id`.
+pub fn blame_file(
+    worktree_path: &str,
+    file_path: &str,
+    ref_name: &str,
+    op_id: Option<&str>,
+) -> Result<Vec<BlameLine>, String> {
+    validate_relative_path(file_path)?;
+    let op = begin_operation(op_id)?;
+    let sha = resolve_ref(worktree_path, "ref_name", ref_name)?;
+    let path = Path::new(worktree_path);
+    let spec = format!("{}:{}", sha, file_path);
+
+    let output = run_git(
+        Command::new("git")
+            .current_dir(path)
+            .args(["cat-file", "-s", &spec]),
+        &op,
+        "git cat-file",
+    )?;
+    if !output.status.success() {
+        return Err(format!("{} does not exist at {}", file_path, ref_name));
+    }
+    let size: u64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|e| format!("Unexpected git cat-file output: {}", e))?;
+    if size > MAX_BLAME_BYTES {
+        return Err(format!(
+            "{} is too large to blame ({} bytes, limit {})",
+            file_path, size, MAX_BLAME_BYTES
+        ));
+    }
+
+    let output = run_git(
+        Command::new("git")
+            .current_dir(path)
+            .args(["cat-file", "blob", &spec]),
+        &op,
+        "git cat-file",
+    )?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git cat-file failed: {}", stderr));
+    }
+    if is_binary_content(&output.stdout) {
+        return Err(format!(
+            "{} is a binary file and can't be blamed",
+            file_path
+        ));
+    }
+
+    let output = run_git(
+        Command::new("git").current_dir(path).args([
+            "blame",
+            "--line-porcelain",
+            &sha,
+            "--",
+            file_path,
+        ]),
+        &op,
+        "git blame",
+    )?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git blame failed: {}", stderr));
+    }
+
+    Ok(parse_line_porcelain(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Parse `--line-porcelain` output: a `<sha> <orig> <final> [<count>]`
+/// header, `key value` lines, then the line's content prefixed with a tab
+fn parse_line_porcelain(output: &str) -> Vec<BlameLine> {
+    let mut lines = Vec::new();
+    let mut current: Option<BlameLine> = None;
+
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            lines.extend(current.take());
+            continue;
+        }
+        let Some(entry) = current.as_mut() else {
+            let mut fields = line.split(' ');
+            let commit_sha = fields.next().unwrap_or_default().to_string();
+            let line_number = fields.nth(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+            current = Some(BlameLine {
+                line_number,
+                commit_sha,
+                author: String::new(),
+                date: String::new(),
+                summary: String::new(),
+            });
+            continue;
+        };
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "author" => entry.author = value.to_string(),
+            "author-time" => {
+                entry.date = value
+                    .parse()
+                    .ok()
+                    .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+                    .map(|dt| dt.to_rfc3339())
+                    .unwrap_or_default();
+            }
+            "summary" => entry.summary = value.to_string(),
+            _ => {}
+        }
+    }
+
+    lines
+}
+
+/// Git's heuristic (a NUL near the start), plus anything that isn't UTF-8
+fn is_binary_content(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_CHECK_BYTES)].contains(&0) || std::str::from_utf8(bytes).is_err()
+}
+
+/// Get `(insertions, deletions)` for one file without building its hunks,
+/// so the UI can warn before rendering a huge diff
+pub fn get_file_diff_stats(
+    worktree_path: &str,
+    file_path: &str,
+    base_branch: &str,
+) -> Result<(u32, u32), String> {
+    validate_relative_path(file_path)?;
+    let path = Path::new(worktree_path);
+
+    if is_untracked_file(path, file_path)? {
+        return get_untracked_numstat(path, file_path);
+    }
+
+    let output = Command::new("git")
+        .current_dir(path)
+        .args([
+            "diff",
+            "--numstat",
+            "--ignore-submodules",
+            base_branch,
+            "--",
+            file_path,
+        ])
+        .output()
+        .map_err(|e| format!("Failed to run git diff: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff failed: {}", stderr));
+    }
+
+    Ok(parse_numstat(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Diff algorithms accepted by `--diff-algorithm`
+const DIFF_ALGORITHMS: &[&str] = &["myers", "minimal", "patience", "histogram"];
+
+/// The `--diff-algorithm` flag for an optional algorithm name; no flags
+/// (git's default) for None
+fn diff_algorithm_args(diff_algorithm: Option<&str>) -> Result<Vec<String>, String> {
+    match diff_algorithm {
+        None => Ok(Vec::new()),
+        Some(algorithm) if DIFF_ALGORITHMS.contains(&algorithm) => {
+            Ok(vec![format!("--diff-algorithm={}", algorithm)])
+        }
+        Some(algorithm) => Err(format!(
+            "Invalid diff algorithm '{}': must be one of {}",
+            algorithm,
+            DIFF_ALGORITHMS.join(", ")
+        )),
+    }
+}
+
+/// Get detailed diff for a specific file with hunks
+/// With `word_diff`, changed lines also get word-level `segments`.
+pub fn get_file_diff(
+    worktree_path: &str,
+    file_path: &str,
+    base_branch: &str,
+    word_diff: bool,
+    diff_algorithm: Option<&str>,
+) -> Result<FileDiff, String> {
+    validate_relative_path(file_path)?;
+    let algorithm_args = diff_algorithm_args(diff_algorithm)?;
+    let path = Path::new(worktree_path);
+
+    let (diff_content, untracked) =
+        get_file_diff_text(path, file_path, base_branch, &algorithm_args)?;
+    let mut file_diff = parse_unified_diff(&diff_content, file_path)?;
+
+    if word_diff && !untracked {
+        let output = Command::new("git")
+            .current_dir(path)
+            .args(["diff", "-U3", "--word-diff=porcelain"])
+            .args(&algorithm_args)
+            .args(["--ignore-submodules", base_branch, "--", file_path])
+            .output()
+            .map_err(|e| format!("Failed to get word diff: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("git diff --word-diff failed: {}", stderr));
+        }
+
+        apply_word_diff(&mut file_diff, &String::from_utf8_lossy(&output.stdout));
+    }
+
+    Ok(file_diff)
+}
+
+/// `content_hash` of the file's current diff. Matches `get_file_diff` for the
+/// same arguments, so the UI can skip re-fetching unchanged diffs. The diff is
+/// still generated; only parsing it (and the word diff) is skipped.
+pub fn get_file_diff_hash(
+    worktree_path: &str,
+    file_path: &str,
+    base_branch: &str,
+    diff_algorithm: Option<&str>,
+) -> Result<String, String> {
+    validate_relative_path(file_path)?;
+    let algorithm_args = diff_algorithm_args(diff_algorithm)?;
+    let (diff_content, _) = get_file_diff_text(
+        Path::new(worktree_path),
+        file_path,
+        base_branch,
+        &algorithm_args,
+    )?;
+    Ok(content_hash(&diff_content))
+}
+
+/// Raw unified diff of one file against the base, and whether the file is
+/// untracked (untracked files diff against /dev/null)
+fn get_file_diff_text(
+    path: &Path,
+    file_path: &str,
+    base_branch: &str,
+    algorithm_args: &[String],
+) -> Result<(String, bool), String> {
+    if is_untracked_file(path, file_path)? {
+        let output = Command::new("git")
+            .current_dir(path)
+            .args(["diff", "-U3"])
+            .args(algorithm_args)
+            .args(["--no-index", "/dev/null", file_path])
+            .output()
+            .map_err(|e| format!("Failed to get file diff: {}", e))?;
+
+        if !diff_status_ok(output.status) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("git diff --no-index failed: {}", stderr));
+        }
+
+        return Ok((String::from_utf8_lossy(&output.stdout).into_owned(), true));
+    }
+
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["diff", "-U3"])
+        .args(algorithm_args)
+        .args(["--ignore-submodules", base_branch, "--", file_path])
+        .output()
+        .map_err(|e| format!("Failed to get file diff: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff failed: {}", stderr));
+    }
+
+    Ok((String::from_utf8_lossy(&output.stdout).into_owned(), false))
+}
+
+/// Hex SHA-256 of a diff's text
+fn content_hash(diff: &str) -> String {
+    Sha256::digest(diff.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Pair a unified diff into left/right rows: each run of deletes is aligned
+/// with the run of adds that follows it. Line numbers are kept as parsed.
+pub fn to_split_view(file_diff: &FileDiff) -> SplitDiff {
+    let hunks = file_diff
+        .hunks
+        .iter()
+        .map(|hunk| {
+            let mut rows = Vec::new();
+            let mut deletes: Vec<&DiffLine> = Vec::new();
+            let mut adds: Vec<&DiffLine> = Vec::new();
+
+            for line in &hunk.lines {
+                match line.line_type.as_str() {
+                    // A delete after adds starts a new change block
+                    "delete" if !adds.is_empty() => {
+                        flush_split_rows(&mut rows, &mut deletes, &mut adds);
+                        deletes.push(line);
+                    }
+                    "delete" => deletes.push(line),
+                    "add" => adds.push(line),
+                    _ => {
+                        flush_split_rows(&mut rows, &mut deletes, &mut adds);
+                        rows.push(SplitRow {
+                            left: Some(line.clone()),
+                            right: Some(line.clone()),
+                        });
+                    }
+                }
+            }
+            flush_split_rows(&mut rows, &mut deletes, &mut adds);
+
+            SplitHunk {
+                header: hunk.header.clone(),
+                rows,
+            }
+        })
+        .collect();
+
+    SplitDiff {
+        path: file_diff.path.clone(),
+        old_path: file_diff.old_path.clone(),
+        status: file_diff.status.clone(),
+        hunks,
+    }
+}
+
+/// Emit pending deletes and adds side by side, padding the shorter run
+fn flush_split_rows(
+    rows: &mut Vec<SplitRow>,
+    deletes: &mut Vec<&DiffLine>,
+    adds: &mut Vec<&DiffLine>,
+) {
+    for i in 0..deletes.len().max(adds.len()) {
+        rows.push(SplitRow {
+            left: deletes.get(i).map(|line| (*line).clone()),
+            right: adds.get(i).map(|line| (*line).clone()),
+        });
+    }
+    deletes.clear();
+    adds.clear();
+}
+
+/// Only the `hunk_index`th (0-based) hunk of a file's diff, with the total
+/// hunk count so callers can page through the rest
+pub fn get_file_diff_hunk(
+    worktree_path: &str,
+    file_path: &str,
+    base_branch: &str,
+    hunk_index: usize,
+    diff_algorithm: Option<&str>,
+) -> Result<FileDiffHunk, String> {
+    let file_diff = get_file_diff(worktree_path, file_path, base_branch, false, diff_algorithm)?;
+    let total_hunks = file_diff.hunks.len();
+    let hunk = file_diff.hunks.into_iter().nth(hunk_index).ok_or_else(|| {
+        format!(
+            "Hunk {} out of range: {} has {} hunk(s)",
+            hunk_index, file_path, total_hunks
+        )
+    })?;
+
+    Ok(FileDiffHunk {
+        hunk,
+        hunk_index,
+        total_hunks,
+    })
+}
+
+/// One line of `--word-diff=porcelain` output that contains changes
+struct WordDiffLine {
+    segments: Vec<DiffSegment>,
+}
+
+impl WordDiffLine {
+    fn has(&self, kind: &str) -> bool {
+        self.segments.iter().any(|s| s.kind == kind)
+    }
+
+    /// Segments as seen from one side ("add" for new, "delete" for old)
+    fn side(&self, kind: &str) -> Vec<DiffSegment> {
+        self.segments
+            .iter()
+            .filter(|s| s.kind == "context" || s.kind == kind)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Attach word-level segments from porcelain word-diff output to the matching
+/// add/delete lines of a line diff. Word diff ignores whitespace-only changes,
+/// so its lines can't be numbered reliably; instead each side is matched in
+/// order by reconstructed text. Lines that don't match keep empty segments.
+fn apply_word_diff(file_diff: &mut FileDiff, porcelain: &str) {
+    let mut changed = Vec::new();
+    let mut current: Vec<DiffSegment> = Vec::new();
+    let mut in_hunk = false;
+
+    for line in porcelain.lines() {
+        if line.starts_with("@@") {
+            in_hunk = true;
+            current.clear();
+            continue;
+        }
+        if !in_hunk {
+            continue;
+        }
+        let kind = match line.chars().next() {
+            Some('~') => {
+                let segments = std::mem::take(&mut current);
+                if segments.iter().any(|s| s.kind != "context") {
+                    changed.push(WordDiffLine { segments });
+                }
+                continue;
+            }
+            Some(' ') => "context",
+            Some('+') => "add",
+            Some('-') => "delete",
+            Some('\\') => continue, // "\ No newline at end of file"
+            _ => {
+                // Next file header
+                in_hunk = false;
+                continue;
+            }
+        };
+        current.push(DiffSegment {
+            kind: kind.to_string(),
+            text: line[1..].to_string(),
+        });
+    }
+
+    let mut old_cursor = 0;
+    let mut new_cursor = 0;
+    for diff_line in file_diff.hunks.iter_mut().flat_map(|h| h.lines.iter_mut()) {
+        let (kind, cursor) = match diff_line.line_type.as_str() {
+            "delete" => ("delete", &mut old_cursor),
+            "add" => ("add", &mut new_cursor),
+            _ => continue,
+        };
+        let found = changed.iter().enumerate().skip(*cursor).find(|(_, w)| {
+            w.has(kind)
+                && w.side(kind)
+                    .iter()
+                    .map(|s| s.text.as_str())
+                    .collect::<String>()
+                    == diff_line.content
+        });
+        if let Some((i, w)) = found {
+            diff_line.segments = w.side(kind);
+            *cursor = i + 1;
+        }
+    }
+}
+
+/// Get detailed diffs for every changed file (including untracked ones) from a
+/// single `git diff` run, instead of one process per file.
+/// Fails if more than `max_files` files changed, checked before any hunks are
+/// generated, so huge branches don't stall the UI. Cancellable via `op_id`.
+pub fn get_full_diff(
+    worktree_path: &str,
+    base_branch: &str,
+    max_files: usize,
+    diff_algorithm: Option<&str>,
+    op_id: Option<&str>,
+) -> Result<Vec<FileDiff>, String> {
+    let algorithm_args = diff_algorithm_args(diff_algorithm)?;
+    let op = begin_operation(op_id)?;
+    let path = Path::new(worktree_path);
+
+    // Count the files cheaply first, so an oversized diff is rejected before
+    // any hunks are generated
+    let output = run_git(
+        Command::new("git").current_dir(path).args([
+            "diff",
+            "--name-only",
+            "-z",
+            "--ignore-submodules",
+            base_branch,
+        ]),
+        &op,
+        "git diff --name-only",
+    )?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff --name-only failed: {}", stderr));
+    }
+
+    let names = String::from_utf8_lossy(&output.stdout);
+    let tracked: HashSet<&str> = names.split('\0').filter(|name| !name.is_empty()).collect();
+    let untracked: Vec<String> = get_untracked_files(path)?
+        .into_iter()
+        .filter(|file_path| !tracked.contains(file_path.as_str()))
+        .collect();
+
+    let total = tracked.len() + untracked.len();
+    if total > max_files {
+        return Err(format!(
+            "Diff touches {} files, more than the limit of {}",
+            total, max_files
+        ));
+    }
+
+    let output = run_git(
+        Command::new("git")
+            .current_dir(path)
+            .args(["diff", "-U3"])
+            .args(&algorithm_args)
+            .args(["--ignore-submodules", base_branch]),
+        &op,
+        "git diff",
+    )?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff failed: {}", stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut files = Vec::with_capacity(total);
+    for section in split_diff_sections(&stdout) {
+        let file_path = diff_section_path(&section)
+            .ok_or_else(|| "Could not determine file path in diff section".to_string())?;
+        files.push(parse_unified_diff(&section, &file_path)?);
+    }
+
+    for file_path in untracked {
+        check_cancelled(&op)?;
+        files.push(get_file_diff(
+            worktree_path,
+            &file_path,
+            base_branch,
+            false,
+            diff_algorithm,
+        )?);
+    }
+
+    mark_review_exclusions(path, &mut files)?;
+    Ok(files)
+}
+
+/// Get the session's changes as a patch applicable with `git apply`, including
+/// untracked files. `--binary` makes binary files apply too, instead of the
+/// "Binary files differ" stub.
+pub fn get_patch(worktree_path: &str, base_branch: &str) -> Result<String, String> {
+    let path = Path::new(worktree_path);
+
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["diff", "--binary", "--ignore-submodules", base_branch])
+        .output()
+        .map_err(|e| format!("Failed to run git diff: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff failed: {}", stderr));
+    }
+
+    let mut patch = String::from_utf8_lossy(&output.stdout).to_string();
+
+    for file_path in get_untracked_files(path)? {
+        let output = Command::new("git")
+            .current_dir(path)
+            .args(["diff", "--binary", "--no-index", "/dev/null", &file_path])
+            .output()
+            .map_err(|e| format!("Failed to diff untracked file: {}", e))?;
+
+        if !diff_status_ok(output.status) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("git diff --no-index failed: {}", stderr));
+        }
+
+        patch.push_str(&String::from_utf8_lossy(&output.stdout));
+    }
+
+    Ok(patch)
+}
+
+/// Split multi-file `git diff` output into one section per file.
+/// Content lines always carry a ` `/`+`/`-` prefix, so a line starting with
+/// `diff --git ` can only be a file header.
+fn split_diff_sections(diff: &str) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            sections.push(String::new());
+        }
+        if let Some(section) = sections.last_mut() {
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+    sections
+}
+
+/// Find the (new) file path of a single-file diff section
+fn diff_section_path(section: &str) -> Option<String> {
+    let mut old_path = None;
+    for line in section.lines() {
+        if line.starts_with("@@") {
+            break;
+        }
+        if let Some(path) = line.strip_prefix("+++ b/") {
+            return Some(path.to_string());
+        }
+        if let Some(path) = line.strip_prefix("rename to ") {
+            return Some(path.to_string());
+        }
+        if let Some(path) = line.strip_prefix("--- a/") {
+            old_path = Some(path.to_string());
+        }
+    }
+    // Deleted files have "+++ /dev/null"; mode-only and binary changes have no ---/+++ lines
+    old_path.or_else(|| {
+        let header = section.lines().next()?;
+        header.rfind(" b/").map(|idx| header[idx + 3..].to_string())
+    })
+}
+
+/// Parse a unified diff format into structured data
 fn parse_unified_diff(diff: &str, file_path: &str) -> Result<FileDiff, String> {
     let mut hunks = Vec::new();
     let mut current_hunk: Option<DiffHunk> = None;
@@ -283,13 +1725,32 @@ fn parse_unified_diff(diff: &str, file_path: &str) -> Result<FileDiff, String> {
     let mut old_line = 0u32;
     let mut new_line = 0u32;
     let mut status = "modified".to_string();
+    let mut old_path = None;
+    let mut old_mode = None;
+    let mut new_mode = None;
+    let mut is_symlink = false;
 
     for line in diff.lines() {
-        // Check for new file indicator
-        if line.starts_with("new file mode") {
+        // Symlinks carry mode 120000 on a file mode or index line
+        if current_hunk.is_none()
+            && line.ends_with(SYMLINK_MODE)
+            && (line.contains(" mode ") || line.starts_with("index "))
+        {
+            is_symlink = true;
+        }
+
+        // Header lines: mode changes, new/deleted/renamed file indicators
+        if current_hunk.is_none() && line.starts_with("old mode ") {
+            old_mode = line.split_whitespace().nth(2).map(String::from);
+        } else if current_hunk.is_none() && line.starts_with("new mode ") {
+            new_mode = line.split_whitespace().nth(2).map(String::from);
+        } else if line.starts_with("new file mode") {
             status = "added".to_string();
         } else if line.starts_with("deleted file mode") {
             status = "deleted".to_string();
+        } else if let Some(from) = line.strip_prefix("rename from ") {
+            status = "renamed".to_string();
+            old_path = Some(from.to_string());
         } else if line.starts_with("@@") {
             // Save previous hunk if exists
             if let Some(hunk) = current_hunk.take() {
@@ -317,6 +1778,7 @@ fn parse_unified_diff(diff: &str, file_path: &str) -> Result<FileDiff, String> {
                     old_line: None,
                     new_line: Some(new_line),
                     content: line[1..].to_string(),
+                    segments: Vec::new(),
                 };
                 new_line += 1;
                 (Some(diff_line), true)
@@ -327,6 +1789,7 @@ fn parse_unified_diff(diff: &str, file_path: &str) -> Result<FileDiff, String> {
                     old_line: Some(old_line),
                     new_line: None,
                     content: line[1..].to_string(),
+                    segments: Vec::new(),
                 };
                 old_line += 1;
                 (Some(diff_line), true)
@@ -337,6 +1800,7 @@ fn parse_unified_diff(diff: &str, file_path: &str) -> Result<FileDiff, String> {
                     old_line: Some(old_line),
                     new_line: Some(new_line),
                     content: content.to_string(),
+                    segments: Vec::new(),
                 };
                 old_line += 1;
                 new_line += 1;
@@ -358,16 +1822,127 @@ fn parse_unified_diff(diff: &str, file_path: &str) -> Result<FileDiff, String> {
         hunks.push(hunk);
     }
 
+    let has_conflict_markers = contains_conflict_markers(
+        hunks
+            .iter()
+            .flat_map(|h| &h.lines)
+            .filter(|l| l.line_type != "delete")
+            .map(|l| l.content.as_str()),
+    );
+
     Ok(FileDiff {
         path: file_path.to_string(),
-        old_path: None,
+        old_path,
         status,
         insertions,
         deletions,
         hunks,
+        mode_change: old_mode.zip(new_mode),
+        is_symlink,
+        language: detect_language(file_path),
+        has_conflict_markers,
+        content_hash: content_hash(diff),
+        generated: false, // Set by mark_review_exclusions; single-file diffs stay unclassified
+        large: false,
     })
 }
 
+/// Whether the worktree copy of a file contains conflict markers within its
+/// first `MAX_CONFLICT_SCAN_BYTES`. Binary files never do.
+fn file_has_conflict_markers(worktree_path: &Path, file_path: &str) -> bool {
+    let mut bytes = Vec::new();
+    let read = std::fs::File::open(worktree_path.join(file_path))
+        .and_then(|file| file.take(MAX_CONFLICT_SCAN_BYTES).read_to_end(&mut bytes));
+    if read.is_err() {
+        return false; // Deleted or unreadable
+    }
+    if bytes[..bytes.len().min(BINARY_CHECK_BYTES)].contains(&0) {
+        return false;
+    }
+    contains_conflict_markers(String::from_utf8_lossy(&bytes).lines())
+}
+
+/// Whether the lines contain a full conflict block: `<<<<<<<`, then
+/// `=======`, then `>>>>>>>`. A lone `=======` (e.g. a heading underline)
+/// doesn't count.
+fn contains_conflict_markers<'a>(lines: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut stage = 0;
+    for line in lines {
+        stage = match stage {
+            0 if is_conflict_marker(line, "<<<<<<<") => 1,
+            1 if is_conflict_marker(line, "=======") => 2,
+            2 if is_conflict_marker(line, ">>>>>>>") => return true,
+            s => s,
+        };
+    }
+    false
+}
+
+/// A line that is exactly `marker`, or (for `<` and `>`) `marker` followed by
+/// a space and a label (`<<<<<<< HEAD`)
+fn is_conflict_marker(line: &str, marker: &str) -> bool {
+    match line.trim_end().strip_prefix(marker) {
+        Some(rest) => rest.is_empty() || (marker != "=======" && rest.starts_with(' ')),
+        None => false,
+    }
+}
+
+/// Syntax highlighting language for a path, from well-known file names
+/// (which often have no extension) and then the extension
+fn detect_language(file_path: &str) -> Option<String> {
+    let path = Path::new(file_path);
+    let file_name = path.file_name()?.to_str()?;
+
+    let by_name = match file_name {
+        "Dockerfile" | "Containerfile" => Some("dockerfile"),
+        "Makefile" | "makefile" | "GNUmakefile" => Some("makefile"),
+        "CMakeLists.txt" => Some("cmake"),
+        "Gemfile" | "Rakefile" | "Podfile" | "Vagrantfile" => Some("ruby"),
+        "Jenkinsfile" => Some("groovy"),
+        ".bashrc" | ".bash_profile" | ".zshrc" | ".profile" => Some("shell"),
+        _ if file_name.starts_with("Dockerfile.") => Some("dockerfile"),
+        _ => None,
+    };
+    if let Some(language) = by_name {
+        return Some(language.to_string());
+    }
+
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let language = match extension.as_str() {
+        "rs" => "rust",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "py" | "pyi" => "python",
+        "go" => "go",
+        "rb" => "ruby",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "cs" => "csharp",
+        "php" => "php",
+        "sh" | "bash" | "zsh" => "shell",
+        "json" => "json",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "md" | "markdown" => "markdown",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "sql" => "sql",
+        "xml" => "xml",
+        "lua" => "lua",
+        "dockerfile" => "dockerfile",
+        "mk" => "makefile",
+        "ini" | "cfg" => "ini",
+        _ => return None,
+    };
+    Some(language.to_string())
+}
+
 /// Parse hunk header like "@@ -1,5 +1,7 @@"
 fn parse_hunk_header(header: &str) -> Option<(u32, u32, u32, u32)> {
     let header = header.trim_start_matches("@@ ");
@@ -397,6 +1972,270 @@ fn parse_line_range(range: &str) -> Option<(u32, u32)> {
     }
 }
 
+/// Locate a previously commented line in a regenerated diff.
+/// Candidates must match both line type and content; among those, the one whose
+/// surrounding lines best match `before`/`after` wins, then the one closest to `hint`.
+/// Returns the line number the frontend keys comments by (old line for deletes).
+pub fn locate_line(
+    diff: &FileDiff,
+    line_type: &str,
+    content: &str,
+    before: &[String],
+    after: &[String],
+    hint: u32,
+) -> Option<u32> {
+    let lines: Vec<&DiffLine> = diff.hunks.iter().flat_map(|h| h.lines.iter()).collect();
+
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.line_type == line_type && line.content == content)
+        .filter_map(|(i, line)| {
+            let number = if line.line_type == "delete" {
+                line.old_line
+            } else {
+                line.new_line
+            }?;
+            let before_score = before
+                .iter()
+                .rev()
+                .enumerate()
+                .filter(|(k, text)| i > *k && lines[i - k - 1].content == **text)
+                .count();
+            let after_score = after
+                .iter()
+                .enumerate()
+                .filter(|(k, text)| lines.get(i + k + 1).is_some_and(|l| l.content == **text))
+                .count();
+            Some((before_score + after_score, number))
+        })
+        .max_by_key(|(score, number)| (*score, std::cmp::Reverse(number.abs_diff(hint))))
+        .map(|(_, number)| number)
+}
+
+/// Check whether a path is inside a git work tree
+pub fn is_git_repo(path: &str) -> bool {
+    Command::new("git")
+        .current_dir(path)
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .output()
+        .map(|output| {
+            output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true"
+        })
+        .unwrap_or(false)
+}
+
+/// Get the top-level directory of the work tree containing `path`
+pub fn get_repo_root(path: &str) -> Result<String, String> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .map_err(|e| format!("Failed to run git rev-parse: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Not a git repository: {}", stderr.trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Check that a `git` argument list only reads repository state.
+/// `args[0]` must be a whitelisted subcommand, and the subcommands that can
+/// also write (`branch`, `remote`) are limited to their listing forms.
+fn validate_readonly_args(args: &[String]) -> Result<(), String> {
+    let (subcommand, rest) = args
+        .split_first()
+        .ok_or_else(|| "No git subcommand given".to_string())?;
+
+    if !READONLY_SUBCOMMANDS.contains(&subcommand.as_str()) {
+        return Err(format!(
+            "git {} is not allowed; permitted subcommands: {}",
+            subcommand,
+            READONLY_SUBCOMMANDS.join(", ")
+        ));
+    }
+
+    // --output writes the result to a file; --ext-diff runs a configured program
+    if let Some(arg) = rest
+        .iter()
+        .find(|a| *a == "--output" || a.starts_with("--output=") || *a == "--ext-diff")
+    {
+        return Err(format!("{} is not allowed", arg));
+    }
+
+    match subcommand.as_str() {
+        "branch" => {
+            let flag_name = |a: &String| a.split('=').next().unwrap_or_default().to_string();
+            if let Some(arg) = rest
+                .iter()
+                .find(|a| BRANCH_WRITE_FLAGS.contains(&flag_name(a).as_str()))
+            {
+                return Err(format!("git branch {} is not allowed", arg));
+            }
+            // Without --list, a bare name creates a branch
+            let lists = rest.iter().any(|a| a == "--list" || a == "-l");
+            if !lists && rest.iter().any(|a| !a.starts_with('-')) {
+                return Err(
+                    "git branch with a name is only allowed with --list (use --opt=value for option values)"
+                        .to_string(),
+                );
+            }
+        }
+        "remote" => {
+            if let Some(action) = rest.iter().find(|a| !a.starts_with('-')) {
+                if action != "show" && action != "get-url" {
+                    return Err(format!("git remote {} is not allowed", action));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Run a read-only git command in the worktree and return its stdout.
+/// A controlled escape hatch for ad-hoc inspection (`log --graph`,
+/// `show --stat`, ...), not a shell: see `validate_readonly_args`.
+pub fn run_readonly(worktree_path: &str, args: Vec<String>) -> Result<String, String> {
+    validate_readonly_args(&args)?;
+
+    let output = Command::new("git")
+        .current_dir(worktree_path)
+        // Keep `git status` from refreshing the index behind our back
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .args(&args)
+        .output()
+        .map_err(|e| format!("Failed to run git {}: {}", args[0], e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git {} failed: {}", args[0], stderr.trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Short names of local branches, or of remote-tracking branches with `remote`
+fn branch_names(repo_path: &str, remote: bool) -> Result<Vec<String>, String> {
+    let mut args = vec!["branch", "--format=%(refname:short)"];
+    if remote {
+        args.push("-r");
+    }
+
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .args(&args)
+        .output()
+        .map_err(|e| format!("Failed to list branches: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git branch failed: {}", stderr.trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Conventional default branch names, checked in order when origin/HEAD is unset
+const DEFAULT_BRANCH_CANDIDATES: &[&str] = &["main", "master", "trunk"];
+
+/// The branch origin/HEAD points at (e.g. "main"), if the remote set one
+fn origin_head_branch(repo_path: &str) -> Option<String> {
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .args(["symbolic-ref", "--short", "refs/remotes/origin/HEAD"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .strip_prefix("origin/")
+        .map(String::from)
+}
+
+/// Whether `name` exists as a local branch or on origin
+fn branch_exists(repo_path: &str, name: &str) -> bool {
+    [
+        format!("refs/heads/{}", name),
+        format!("refs/remotes/origin/{}", name),
+    ]
+    .iter()
+    .any(|ref_name| {
+        Command::new("git")
+            .current_dir(repo_path)
+            .args(["rev-parse", "--verify", "--quiet", ref_name])
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    })
+}
+
+/// Work out a repository's default branch: the one origin/HEAD points at,
+/// else the first of main/master/trunk that exists
+pub fn detect_default_branch(repo_path: &str) -> Result<String, String> {
+    if let Some(branch) = origin_head_branch(repo_path) {
+        return Ok(branch);
+    }
+
+    DEFAULT_BRANCH_CANDIDATES
+        .iter()
+        .find(|name| branch_exists(repo_path, name))
+        .map(|name| name.to_string())
+        .ok_or_else(|| {
+            format!(
+                "Could not detect a default branch in {}: origin/HEAD is not set and none of {} exist",
+                repo_path,
+                DEFAULT_BRANCH_CANDIDATES.join(", ")
+            )
+        })
+}
+
+/// List branch names for choosing a workspace's origin branch.
+/// With `include_remote`, branches on origin are listed without the
+/// `origin/` prefix and merged with local ones of the same name; other
+/// remotes keep their full name. The default branch comes first.
+pub fn list_branches(repo_path: &str, include_remote: bool) -> Result<Vec<String>, String> {
+    let mut branches = branch_names(repo_path, false)?;
+
+    if include_remote {
+        let mut seen: HashSet<String> = branches.iter().cloned().collect();
+        for name in branch_names(repo_path, true)? {
+            // Skip the origin/HEAD alias (shown as "origin" by older git)
+            if !name.contains('/') || name.ends_with("/HEAD") {
+                continue;
+            }
+            let name = name
+                .strip_prefix("origin/")
+                .map(String::from)
+                .unwrap_or(name);
+            if seen.insert(name.clone()) {
+                branches.push(name);
+            }
+        }
+    }
+
+    if let Ok(default) = detect_default_branch(repo_path) {
+        if let Some(idx) = branches.iter().position(|b| *b == default) {
+            let branch = branches.remove(idx);
+            branches.insert(0, branch);
+        }
+    }
+
+    Ok(branches)
+}
+
 /// Get the current branch name
 pub fn get_current_branch(worktree_path: &str) -> Result<String, String> {
     let path = Path::new(worktree_path);
@@ -432,15 +2271,121 @@ pub fn get_commit_sha(worktree_path: &str, ref_name: &str) -> Result<String, Str
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-/// Fetch from remote origin
-pub fn fetch_origin(worktree_path: &str) -> Result<(), String> {
+/// SHAs of commits on HEAD that are not on `base_branch` (`git rev-list <base>..HEAD`),
+/// newest first
+pub fn get_new_commits(worktree_path: &str, base_branch: &str) -> Result<Vec<String>, String> {
+    let base = resolve_ref(worktree_path, "base_branch", base_branch)?;
+    let range = format!("{}..HEAD", base);
     let path = Path::new(worktree_path);
 
     let output = Command::new("git")
         .current_dir(path)
-        .args(["fetch", "origin"])
+        .args(["rev-list", &range])
         .output()
-        .map_err(|e| format!("Failed to run git fetch: {}", e))?;
+        .map_err(|e| format!("Failed to run git rev-list: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git rev-list failed: {}", stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+/// Rebase the worktree's branch onto `base_branch`.
+/// On conflicts the rebase stays in progress and the conflicting files are
+/// returned, so the caller can resolve them or call `abort_rebase`.
+pub fn rebase_onto_base(worktree_path: &str, base_branch: &str) -> Result<RebaseOutcome, String> {
+    let path = Path::new(worktree_path);
+
+    let output = Command::new("git")
+        .current_dir(path)
+        .env("GIT_EDITOR", "true")
+        .args(["rebase", base_branch])
+        .output()
+        .map_err(|e| format!("Failed to run git rebase: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if output.status.success() {
+        return Ok(RebaseOutcome {
+            success: true,
+            conflicts: Vec::new(),
+            output: stdout,
+        });
+    }
+
+    let conflicts = get_conflicted_files(path)?;
+    if conflicts.is_empty() {
+        // Refused to start (dirty worktree, unknown ref, ...); nothing to clean up
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git rebase failed: {}", stderr.trim()));
+    }
+
+    Ok(RebaseOutcome {
+        success: false,
+        conflicts,
+        output: stdout,
+    })
+}
+
+/// Abort an in-progress rebase, restoring the branch to its previous state
+pub fn abort_rebase(worktree_path: &str) -> Result<(), String> {
+    let output = Command::new("git")
+        .current_dir(worktree_path)
+        .args(["rebase", "--abort"])
+        .output()
+        .map_err(|e| format!("Failed to run git rebase --abort: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git rebase --abort failed: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+/// Files with unresolved merge conflicts, e.g. left by a merge or rebase
+pub fn get_conflicts(worktree_path: &str) -> Result<Vec<String>, String> {
+    get_conflicted_files(Path::new(worktree_path))
+}
+
+/// Files with unresolved merge conflicts
+fn get_conflicted_files(worktree_path: &Path) -> Result<Vec<String>, String> {
+    let output = Command::new("git")
+        .current_dir(worktree_path)
+        .args(["diff", "--name-only", "--diff-filter=U"])
+        .output()
+        .map_err(|e| format!("Failed to list conflicted files: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("git diff failed: {}", stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Fetch from remote origin. Cancellable via `op_id`.
+pub fn fetch_origin(worktree_path: &str, op_id: Option<&str>) -> Result<(), String> {
+    let op = begin_operation(op_id)?;
+    let path = Path::new(worktree_path);
+
+    let output = run_git(
+        Command::new("git")
+            .current_dir(path)
+            .args(["fetch", "origin"]),
+        &op,
+        "git fetch",
+    )?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -449,3 +2394,47 @@ pub fn fetch_origin(worktree_path: &str) -> Result<(), String> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_relative_path_rejects_escapes() {
+        for path in [
+            "../../etc/passwd",
+            "a/../../b",
+            "/etc/passwd",
+            "C:\\Windows\\System32",
+            "C:/Windows/System32",
+            "\\\\server\\share\\file",
+            "a\\..\\..\\b",
+            "",
+            "   ",
+            "a\0b",
+        ] {
+            assert!(
+                validate_relative_path(path).is_err(),
+                "{:?} should be rejected",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn validate_relative_path_accepts_nested_paths() {
+        for path in [
+            "README.md",
+            "src/lib.rs",
+            "a/b/c/d.txt",
+            "./src/main.rs",
+            "a..b/c",
+        ] {
+            assert!(
+                validate_relative_path(path).is_ok(),
+                "{:?} should be accepted",
+                path
+            );
+        }
+    }
+}
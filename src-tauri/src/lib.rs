@@ -2,6 +2,7 @@ mod claude_headless;
 mod claude_sessions;
 mod db;
 mod git;
+mod logging;
 mod permissions;
 mod server;
 
@@ -9,6 +10,7 @@ use chrono::Utc;
 use permissions::{PermissionBehavior, PermissionResponse};
 use serde::{Deserialize, Serialize};
 use tauri::Emitter;
+use tracing::{error, info, warn};
 
 // Types for IPC
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +31,8 @@ pub struct SessionData {
     pub worktree_name: Option<String>,
     pub status: String,
     pub base_commit: Option<String>,
+    pub tags: Vec<String>,
+    pub last_error: Option<String>, // Set while status is "error"
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +44,26 @@ pub struct InboxMessageData {
     pub created_at: String,
     pub read_at: Option<String>,
     pub first_read_at: Option<String>,
+    pub acknowledged: bool,
+    pub acknowledged_at: Option<String>,
+    pub ack_reply: Option<String>,
+    pub starred: bool,
+}
+
+fn inbox_message_to_data(m: db::InboxMessage) -> InboxMessageData {
+    InboxMessageData {
+        id: m.id,
+        session_id: m.session_id,
+        session_name: m.session_name,
+        message: m.message,
+        created_at: m.created_at.to_rfc3339(),
+        read_at: m.read_at.map(|dt| dt.to_rfc3339()),
+        first_read_at: m.first_read_at.map(|dt| dt.to_rfc3339()),
+        acknowledged: m.acknowledged_at.is_some(),
+        acknowledged_at: m.acknowledged_at.map(|dt| dt.to_rfc3339()),
+        ack_reply: m.ack_reply,
+        starred: m.starred,
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,10 +72,12 @@ pub struct DiffCommentData {
     pub session_id: String,
     pub file_path: String,
     pub line_number: Option<i32>,
+    pub end_line_number: Option<i32>,
     pub line_type: Option<String>,
     pub author: String,
     pub content: String,
     pub status: String,
+    pub severity: String, // "blocker", "suggestion", "question" or "praise"
     pub parent_id: Option<String>,
     pub created_at: String,
     pub updated_at: String,
@@ -63,16 +89,45 @@ fn comment_to_data(c: db::DiffComment) -> DiffCommentData {
         session_id: c.session_id,
         file_path: c.file_path,
         line_number: c.line_number,
+        end_line_number: c.end_line_number,
         line_type: c.line_type,
         author: c.author,
         content: c.content,
         status: c.status,
+        severity: c.severity,
         parent_id: c.parent_id,
         created_at: c.created_at.to_rfc3339(),
         updated_at: c.updated_at.to_rfc3339(),
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentCountsData {
+    pub open: u32,
+    pub resolved: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceStats {
+    pub session_count: u32,
+    pub open_comment_count: u32, // Root comments only
+    pub unread_message_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PruneOrphansData {
+    pub messages: u32,
+    pub comments: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReanchoredCommentData {
+    #[serde(flatten)]
+    pub comment: DiffCommentData,
+    /// True when the commented line no longer exists in the current diff
+    pub outdated: bool,
+}
+
 // Tauri commands for workspaces
 #[tauri::command]
 fn get_workspaces() -> Result<Vec<WorkspaceData>, String> {
@@ -92,6 +147,21 @@ fn get_workspaces() -> Result<Vec<WorkspaceData>, String> {
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+fn get_workspace(id: String) -> Result<Option<WorkspaceData>, String> {
+    db::get_workspace(&id)
+        .map(|workspace| {
+            workspace.map(|w| WorkspaceData {
+                id: w.id,
+                name: w.name,
+                folder: w.folder,
+                script_path: w.script_path,
+                origin_branch: w.origin_branch,
+            })
+        })
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn create_workspace(
     name: String,
@@ -99,7 +169,13 @@ fn create_workspace(
     script_path: Option<String>,
     origin_branch: Option<String>,
 ) -> Result<WorkspaceData, String> {
-    let origin_branch = origin_branch.unwrap_or_else(|| "main".to_string());
+    let origin_branch = match origin_branch {
+        Some(branch) => branch,
+        None => git::detect_default_branch(&folder).unwrap_or_else(|e| {
+            info!("[Git] {}; using \"main\"", e);
+            "main".to_string()
+        }),
+    };
     let workspace = db::Workspace {
         id: uuid::Uuid::new_v4().to_string(),
         name: name.clone(),
@@ -126,202 +202,1260 @@ fn delete_workspace(id: String) -> Result<(), String> {
     db::delete_workspace(&id).map_err(|e| e.to_string())
 }
 
+/// Save the panel layout for a workspace. The JSON is opaque to the backend.
+#[tauri::command]
+fn save_workspace_ui_state(workspace_id: String, state_json: String) -> Result<(), String> {
+    serde_json::from_str::<serde_json::Value>(&state_json)
+        .map_err(|e| format!("Invalid UI state JSON: {}", e))?;
+    db::save_workspace_ui_state(&workspace_id, &state_json).map_err(|e| e.to_string())
+}
+
+/// The saved panel layout for a workspace, or None to use the defaults
+#[tauri::command]
+fn get_workspace_ui_state(workspace_id: String) -> Result<Option<String>, String> {
+    db::get_workspace_ui_state(&workspace_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_workspace_stats(workspace_id: String) -> Result<WorkspaceStats, String> {
+    db::get_workspace_stats(&workspace_id)
+        .map(
+            |(session_count, open_comment_count, unread_message_count)| WorkspaceStats {
+                session_count,
+                open_comment_count,
+                unread_message_count,
+            },
+        )
+        .map_err(|e| e.to_string())
+}
+
 // Tauri commands for sessions
 #[tauri::command]
 fn get_sessions() -> Result<Vec<SessionData>, String> {
     db::get_all_sessions()
-        .map(|sessions| {
-            sessions
-                .into_iter()
-                .map(|s| SessionData {
-                    id: s.id,
-                    name: s.name,
-                    cwd: s.cwd,
-                    workspace_id: s.workspace_id,
-                    worktree_name: s.worktree_name,
-                    status: s.status,
-                    base_commit: s.base_commit,
-                })
-                .collect()
-        })
+        .map(|sessions| sessions.into_iter().map(session_to_data).collect())
         .map_err(|e| e.to_string())
 }
 
-#[tauri::command]
-fn create_session(
+fn session_to_data(s: db::Session) -> SessionData {
+    SessionData {
+        id: s.id,
+        name: s.name,
+        cwd: s.cwd,
+        workspace_id: s.workspace_id,
+        worktree_name: s.worktree_name,
+        status: s.status,
+        base_commit: s.base_commit,
+        tags: s.tags,
+        last_error: s.last_error,
+    }
+}
+
+/// A fresh, busy session record (not yet stored)
+fn new_session(
     name: String,
     cwd: String,
     workspace_id: Option<String>,
     worktree_name: Option<String>,
     base_commit: Option<String>,
-) -> Result<SessionData, String> {
-    let session = db::Session {
+) -> db::Session {
+    db::Session {
         id: uuid::Uuid::new_v4().to_string(),
-        name: name.clone(),
-        cwd: cwd.clone(),
-        workspace_id: workspace_id.clone(),
-        worktree_name: worktree_name.clone(),
-        status: "busy".to_string(),
-        base_commit: base_commit.clone(),
+        name,
+        cwd,
+        workspace_id,
+        worktree_name,
+        status: db::SessionStatus::Busy.to_string(),
+        base_commit,
+        tags: Vec::new(),
+        last_error: None,
         created_at: Utc::now(),
         updated_at: Utc::now(),
         convex_id: None,
         sync_status: "pending".to_string(),
         deleted_at: None,
+    }
+}
+
+#[tauri::command]
+fn create_session(
+    name: String,
+    cwd: String,
+    workspace_id: Option<String>,
+    worktree_name: Option<String>,
+    base_commit: Option<String>,
+    base_ref: Option<String>,
+) -> Result<SessionData, String> {
+    // Resolve base_ref to a concrete SHA now so the stored base can't drift
+    let base_commit = match base_ref {
+        Some(ref_name) => Some(git::get_commit_sha(&cwd, &ref_name)?),
+        None => base_commit,
     };
+
+    let session = new_session(name, cwd, workspace_id, worktree_name, base_commit);
     db::create_session(&session).map_err(|e| e.to_string())?;
-    Ok(SessionData {
-        id: session.id,
-        name,
-        cwd,
-        workspace_id,
-        worktree_name,
-        status: session.status,
-        base_commit,
+    if let Err(e) = db::record_recent_folder(&session.cwd) {
+        warn!("[DB] Failed to record recent folder: {}", e);
+    }
+    Ok(session_to_data(session))
+}
+
+/// One session to create in `create_sessions_batch`
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionSpec {
+    pub name: String,
+    pub worktree_name: Option<String>,
+    pub base_ref: Option<String>,
+}
+
+/// Create several sessions in a workspace at once, in order. All base refs
+/// are resolved first and the sessions are inserted in one transaction, so
+/// either every session is created or none is.
+#[tauri::command]
+fn create_sessions_batch(
+    workspace_id: String,
+    specs: Vec<SessionSpec>,
+) -> Result<Vec<SessionData>, String> {
+    let workspace = db::get_workspace(&workspace_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Workspace {} not found", workspace_id))?;
+
+    let sessions = specs
+        .into_iter()
+        .map(|spec| {
+            // Resolve base_ref to a concrete SHA now so the stored base can't drift
+            let base_commit = match spec.base_ref {
+                Some(ref_name) => Some(
+                    git::get_commit_sha(&workspace.folder, &ref_name)
+                        .map_err(|e| format!("Session {:?}: {}", spec.name, e))?,
+                ),
+                None => None,
+            };
+            Ok(new_session(
+                spec.name,
+                workspace.folder.clone(),
+                Some(workspace_id.clone()),
+                spec.worktree_name,
+                base_commit,
+            ))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    db::create_sessions(&sessions).map_err(|e| e.to_string())?;
+    if let Err(e) = db::record_recent_folder(&workspace.folder) {
+        warn!("[DB] Failed to record recent folder: {}", e);
+    }
+    info!(
+        "[DB] Created {} sessions in workspace {}",
+        sessions.len(),
+        workspace_id
+    );
+    Ok(sessions.into_iter().map(session_to_data).collect())
+}
+
+/// Default number of folders returned by `get_recent_folders`
+const RECENT_FOLDERS_DEFAULT_LIMIT: u32 = 10;
+
+/// Ids of sessions whose cwd is gone, or whose worktree is no longer a git
+/// work tree, so the UI can offer to archive or delete them
+#[tauri::command]
+fn find_stale_sessions() -> Result<Vec<String>, String> {
+    let sessions = db::get_all_sessions().map_err(|e| e.to_string())?;
+    let stale: Vec<String> = sessions
+        .into_iter()
+        .filter(|s| {
+            !std::path::Path::new(&s.cwd).is_dir()
+                || (s.worktree_name.is_some() && !git::is_git_repo(&s.cwd))
+        })
+        .map(|s| s.id)
+        .collect();
+    info!("[App] Found {} stale session(s)", stale.len());
+    Ok(stale)
+}
+
+/// Folders used for recent sessions, most recent first
+#[tauri::command]
+fn get_recent_folders(limit: Option<u32>) -> Result<Vec<String>, String> {
+    db::get_recent_folders(limit.unwrap_or(RECENT_FOLDERS_DEFAULT_LIMIT)).map_err(|e| e.to_string())
+}
+
+/// Save a prompt template, replacing any with the same name. The body is
+/// stored as-is; `{{cwd}}` and `{{branch}}` are substituted by the frontend.
+#[tauri::command]
+fn save_template(name: String, body: String) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Template name must not be empty".to_string());
+    }
+    db::save_template(name, &body).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_templates() -> Result<Vec<db::SessionTemplate>, String> {
+    db::get_templates().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_template(name: String) -> Result<(), String> {
+    if db::delete_template(&name).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("Template {} not found", name))
+    }
+}
+
+/// Characters per token assumed by `estimate_tokens`; typical for English and code
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Rough token count for a prompt (chars / 4), as a guard against oversized prompts
+#[tauri::command]
+fn estimate_tokens(text: String) -> Result<u32, String> {
+    let tokens = text.chars().count().div_ceil(CHARS_PER_TOKEN);
+    Ok(u32::try_from(tokens).unwrap_or(u32::MAX))
+}
+
+/// Directory holding sessions.db and other app data
+#[tauri::command]
+fn get_app_data_dir() -> Result<String, String> {
+    db::get_app_data_dir()
+        .to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| "App data directory path is not valid UTF-8".to_string())
+}
+
+/// Open the app data directory in the OS file manager
+#[tauri::command]
+fn reveal_app_data_dir(app: tauri::AppHandle) -> Result<(), String> {
+    use tauri_plugin_shell::ShellExt;
+
+    let dir = get_app_data_dir()?;
+    info!("[App] Revealing app data directory: {}", dir);
+    // The shell plugin's open is deprecated in favour of the opener plugin,
+    // which this app doesn't ship yet
+    #[allow(deprecated)]
+    app.shell()
+        .open(dir.clone(), None)
+        .map_err(|e| format!("Failed to open {}: {}", dir, e))
+}
+
+/// Today's backend log file
+#[tauri::command]
+fn get_log_path() -> Result<String, String> {
+    let path = logging::get_log_path().ok_or_else(|| "File logging is disabled".to_string())?;
+    path.to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| "Log path is not valid UTF-8".to_string())
+}
+
+/// Change the backend log level ("error", "warn", "info", "debug", "trace"
+/// or "off"). Saved, so it also applies on the next launch.
+#[tauri::command]
+fn set_log_level(app: tauri::AppHandle, level: String) -> Result<(), String> {
+    let level = level.trim().to_lowercase();
+    logging::set_level(&level)?;
+    set_string_setting(&app, logging::LOG_LEVEL_KEY, &level)?;
+    info!("[App] Log level set to {}", level);
+    Ok(())
+}
+
+/// Merge another sessions.db into this one, skipping rows that already exist
+#[tauri::command]
+fn import_database(path: String) -> Result<db::ImportStats, String> {
+    let source = std::path::Path::new(&path);
+    if !source.is_file() {
+        return Err(format!("Database file not found: {}", path));
+    }
+    let stats = db::import_from(source).map_err(|e| format!("Failed to import {}: {}", path, e))?;
+    info!("[DB] Imported from {}: {:?}", path, stats);
+    Ok(stats)
+}
+
+/// Tags are trimmed; empty tags and tags spanning lines are rejected
+fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err("Tag must not be empty".to_string());
+    }
+    if tag.contains('\n') {
+        return Err("Tag must be a single line".to_string());
+    }
+    Ok(tag.to_string())
+}
+
+#[tauri::command]
+fn add_session_tag(session_id: String, tag: String) -> Result<(), String> {
+    db::add_session_tag(&session_id, &normalize_tag(&tag)?).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_session_tag(session_id: String, tag: String) -> Result<(), String> {
+    db::remove_session_tag(&session_id, tag.trim()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_session_tags(session_id: String) -> Result<Vec<String>, String> {
+    db::get_session_tags(&session_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_sessions_by_tag(tag: String) -> Result<Vec<SessionData>, String> {
+    db::get_sessions_by_tag(tag.trim())
+        .map(|sessions| sessions.into_iter().map(session_to_data).collect())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_session(id: String) -> Result<(), String> {
+    db::delete_session(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn rename_session(id: String, name: String) -> Result<(), String> {
+    db::rename_session(&id, &name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn update_session_cwd(id: String, cwd: String) -> Result<(), String> {
+    db::update_session_cwd(&id, &cwd).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_session_status(id: String) -> Result<String, String> {
+    db::get_session(&id)
+        .map_err(|e| e.to_string())?
+        .map(|s| s.status)
+        .ok_or_else(|| "Session not found".to_string())
+}
+
+#[tauri::command]
+fn set_session_status(id: String, status: String) -> Result<(), String> {
+    let status: db::SessionStatus = status.parse()?;
+    db::update_session_status(&id, status).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StalledSessionData {
+    pub id: String,
+    pub name: String,
+    pub last_heartbeat_at: String,
+}
+
+/// Busy sessions whose agent hasn't sent a heartbeat in `threshold_secs`
+#[tauri::command]
+fn get_stalled_sessions(threshold_secs: u64) -> Result<Vec<StalledSessionData>, String> {
+    let cutoff = i64::try_from(threshold_secs)
+        .ok()
+        .and_then(chrono::Duration::try_seconds)
+        .and_then(|threshold| Utc::now().checked_sub_signed(threshold))
+        .ok_or_else(|| format!("threshold_secs {} is out of range", threshold_secs))?;
+    db::get_stalled_sessions(cutoff)
+        .map(|sessions| {
+            sessions
+                .into_iter()
+                .map(|s| StalledSessionData {
+                    id: s.id,
+                    name: s.name,
+                    last_heartbeat_at: s.last_heartbeat_at.to_rfc3339(),
+                })
+                .collect()
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// Mark a session as failed, keeping the reason for display and retry
+#[tauri::command]
+fn set_session_error(id: String, message: String) -> Result<(), String> {
+    db::set_session_error(&id, &message).map_err(|e| e.to_string())
+}
+
+/// A stored headless-run message; `content` is the stream-json message as sent
+/// in the `claude-message` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunMessageData {
+    pub id: String,
+    pub session_id: String,
+    pub seq: i64,
+    pub role: String,
+    pub content: serde_json::Value,
+    pub created_at: String,
+}
+
+/// Transcript of every headless run in a session, oldest first
+#[tauri::command]
+fn get_run_messages(session_id: String) -> Result<Vec<RunMessageData>, String> {
+    let messages = db::get_run_messages(&session_id).map_err(|e| e.to_string())?;
+    messages
+        .into_iter()
+        .map(|m| {
+            let content = serde_json::from_str(&m.content_json)
+                .map_err(|e| format!("Corrupt run message {}: {}", m.id, e))?;
+            Ok(RunMessageData {
+                id: m.id,
+                session_id: m.session_id,
+                seq: m.seq,
+                role: m.role,
+                content,
+                created_at: m.created_at.to_rfc3339(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxMessagePageData {
+    pub messages: Vec<InboxMessageData>,
+    pub total: u32, // Messages matching the filters, across all pages
+}
+
+// Tauri commands for inbox messages
+/// Inbox messages, newest first. With no arguments, returns every message.
+#[tauri::command]
+fn get_inbox_messages(
+    limit: Option<u32>,
+    offset: Option<u32>,
+    unread_only: Option<bool>,
+    session_id: Option<String>,
+) -> Result<InboxMessagePageData, String> {
+    let (messages, total) = db::get_inbox_messages_paged(
+        limit,
+        offset.unwrap_or(0),
+        unread_only.unwrap_or(false),
+        session_id.as_deref(),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(InboxMessagePageData {
+        messages: messages.into_iter().map(inbox_message_to_data).collect(),
+        total,
+    })
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// All inbox messages serialized as "json" or "csv", for the frontend to save
+#[tauri::command]
+fn export_inbox(format: String) -> Result<String, String> {
+    let messages = get_inbox_messages(None, None, None, None)?.messages;
+    match format.as_str() {
+        "json" => serde_json::to_string_pretty(&messages).map_err(|e| e.to_string()),
+        "csv" => {
+            let mut csv = String::from(
+                "id,session_id,session_name,message,created_at,read_at,first_read_at,acknowledged_at,ack_reply,starred\n",
+            );
+            for m in &messages {
+                let fields = [
+                    m.id.as_str(),
+                    m.session_id.as_str(),
+                    m.session_name.as_str(),
+                    m.message.as_str(),
+                    m.created_at.as_str(),
+                    m.read_at.as_deref().unwrap_or(""),
+                    m.first_read_at.as_deref().unwrap_or(""),
+                    m.acknowledged_at.as_deref().unwrap_or(""),
+                    m.ack_reply.as_deref().unwrap_or(""),
+                    if m.starred { "true" } else { "false" },
+                ];
+                let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+            Ok(csv)
+        }
+        _ => Err(format!(
+            "Invalid export format '{}': must be json or csv",
+            format
+        )),
+    }
+}
+
+#[tauri::command]
+fn get_starred_messages() -> Result<Vec<InboxMessageData>, String> {
+    db::get_starred_messages()
+        .map(|messages| messages.into_iter().map(inbox_message_to_data).collect())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_inbox_message_starred(id: String, starred: bool) -> Result<(), String> {
+    if db::set_message_starred(&id, starred).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("Inbox message {} not found", id))
+    }
+}
+
+#[tauri::command]
+fn mark_inbox_message_read(id: String) -> Result<(), String> {
+    db::mark_message_read(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn mark_inbox_message_unread(id: String) -> Result<(), String> {
+    db::mark_message_unread(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn mark_session_messages_read(session_id: String) -> Result<u32, String> {
+    db::mark_session_messages_read(&session_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn mark_session_messages_unread(session_id: String) -> Result<u32, String> {
+    db::mark_session_messages_unread(&session_id).map_err(|e| e.to_string())
+}
+
+/// Oldest unread message in a session, for "jump to first unread"
+#[tauri::command]
+fn get_first_unread_message(session_id: String) -> Result<Option<InboxMessageData>, String> {
+    db::get_first_unread_message(&session_id)
+        .map(|message| message.map(inbox_message_to_data))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn acknowledge_inbox_message(id: String, reply: Option<String>) -> Result<(), String> {
+    match db::acknowledge_message(&id, None, reply.as_deref()) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("Inbox message not found".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+fn delete_inbox_message(id: String) -> Result<(), String> {
+    db::delete_inbox_message(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn clear_inbox() -> Result<(), String> {
+    db::clear_inbox().map_err(|e| e.to_string())
+}
+
+/// Remove inbox messages and comments left behind by deleted sessions
+#[tauri::command]
+fn prune_orphans() -> Result<PruneOrphansData, String> {
+    let (messages, comments) = db::prune_orphans().map_err(|e| e.to_string())?;
+    info!(
+        "[DB] Pruned {} orphaned messages and {} orphaned comments",
+        messages, comments
+    );
+    Ok(PruneOrphansData { messages, comments })
+}
+
+/// Resulting contents of the worktree config files, as pretty-printed JSON.
+/// `None` means the file is left untouched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorktreeConfigPreview {
+    pub mcp_json: Option<String>,
+    pub settings_local_json: Option<String>,
+}
+
+/// Compute what `configure_worktree` would write, without touching disk.
+/// Shared by the real and dry-run paths so they can't diverge.
+fn compute_worktree_config(_worktree_path: &str) -> Result<WorktreeConfigPreview, String> {
+    // MCP configuration removed - custom tools (notify_ready, get_pending_comments, etc.)
+    // are now provided directly to the SDK via createSdkMcpServer() in agent-service.
+    // No need to write .mcp.json or .claude/settings.local.json anymore.
+    Ok(WorktreeConfigPreview {
+        mcp_json: None,
+        settings_local_json: None,
+    })
+}
+
+/// Write a worktree config file. If an existing file can't be parsed as JSON
+/// (e.g. hand-edited with a trailing comma), it is copied to `<file>.bak`
+/// first so user data is never silently discarded.
+fn write_config_file(path: &std::path::Path, contents: &str) -> Result<(), String> {
+    if let Ok(existing) = std::fs::read_to_string(path) {
+        if let Err(e) = serde_json::from_str::<serde_json::Value>(&existing) {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(".bak");
+            std::fs::copy(path, &backup).map_err(|copy_err| {
+                format!(
+                    "{} is invalid JSON (line {}, column {}) and could not be backed up: {}",
+                    path.display(),
+                    e.line(),
+                    e.column(),
+                    copy_err
+                )
+            })?;
+            info!(
+                "[Config] {} was invalid JSON (line {}, column {}), backed up to {:?}",
+                path.display(),
+                e.line(),
+                e.column(),
+                backup
+            );
+        }
+    }
+
+    std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Configure a worktree directory for Claude Code
+/// Note: MCP configuration is no longer needed - custom tools are now provided
+/// directly via the SDK in agent-service. This function is kept for any future
+/// worktree-specific configuration needs.
+///
+/// With `dry_run`, returns the computed file contents without writing them.
+#[tauri::command]
+fn configure_worktree(
+    worktree_path: String,
+    _session_id: String,
+    dry_run: Option<bool>,
+) -> Result<WorktreeConfigPreview, String> {
+    let preview = compute_worktree_config(&worktree_path)?;
+
+    if dry_run.unwrap_or(false) {
+        return Ok(preview);
+    }
+
+    let root = std::path::Path::new(&worktree_path);
+    if let Some(ref contents) = preview.mcp_json {
+        write_config_file(&root.join(".mcp.json"), contents)?;
+    }
+    if let Some(ref contents) = preview.settings_local_json {
+        let claude_dir = root.join(".claude");
+        std::fs::create_dir_all(&claude_dir)
+            .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+        write_config_file(&claude_dir.join("settings.local.json"), contents)?;
+    }
+
+    info!(
+        "[Config] Worktree configured at: {} (no MCP files needed)",
+        worktree_path
+    );
+    Ok(preview)
+}
+
+/// Name of our server entry in `.mcp.json`; its tools are `mcp__claude-sessions__*`
+const MCP_SERVER_NAME: &str = "claude-sessions";
+
+/// What `verify_worktree_config` found in a worktree. A file counts as
+/// present only if it exists and parses as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorktreeConfigStatus {
+    /// Whether `configure_worktree` writes these files at all; false while
+    /// the tools are provided through the agent SDK
+    pub config_required: bool,
+    pub mcp_json_present: bool,
+    pub server_entry_present: bool,
+    /// The script our server entry runs exists on disk
+    pub bridge_script_exists: bool,
+    pub settings_local_present: bool,
+    /// `permissions.allow` covers our MCP tools
+    pub permissions_present: bool,
+}
+
+fn read_json_file(path: &std::path::Path) -> Option<serde_json::Value> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Check a worktree's `.mcp.json` and `.claude/settings.local.json` for our
+/// server entry, its bridge script and tool permissions
+#[tauri::command]
+fn verify_worktree_config(worktree_path: String) -> Result<WorktreeConfigStatus, String> {
+    let root = std::path::Path::new(&worktree_path);
+    if !root.is_dir() {
+        return Err(format!("Worktree not found: {}", worktree_path));
+    }
+
+    let preview = compute_worktree_config(&worktree_path)?;
+    let mcp_json = read_json_file(&root.join(".mcp.json"));
+    let server = mcp_json
+        .as_ref()
+        .and_then(|config| config.get("mcpServers")?.get(MCP_SERVER_NAME));
+
+    // The bridge is the first script-like path among the command and its args
+    let bridge_script_exists = server.is_some_and(|server| {
+        let command = server.get("command").and_then(|c| c.as_str());
+        let args = server
+            .get("args")
+            .and_then(|a| a.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|a| a.as_str());
+        command
+            .into_iter()
+            .chain(args)
+            .find(|arg| {
+                [".js", ".cjs", ".mjs", ".ts"]
+                    .iter()
+                    .any(|ext| arg.ends_with(ext))
+            })
+            .is_some_and(|script| root.join(script).is_file())
+    });
+
+    let settings = read_json_file(&root.join(".claude").join("settings.local.json"));
+    let tool_prefix = format!("mcp__{}", MCP_SERVER_NAME);
+    let permissions_present = settings
+        .as_ref()
+        .and_then(|s| s.get("permissions")?.get("allow")?.as_array())
+        .is_some_and(|allow| {
+            allow
+                .iter()
+                .filter_map(|rule| rule.as_str())
+                .any(|rule| rule.starts_with(&tool_prefix))
+        });
+
+    Ok(WorktreeConfigStatus {
+        config_required: preview.mcp_json.is_some() || preview.settings_local_json.is_some(),
+        mcp_json_present: mcp_json.is_some(),
+        server_entry_present: server.is_some(),
+        bridge_script_exists,
+        settings_local_present: settings.is_some(),
+        permissions_present,
+    })
+}
+
+// Git diff commands
+#[tauri::command]
+fn get_diff_summary(
+    worktree_path: String,
+    base_branch: String,
+) -> Result<git::DiffSummary, String> {
+    git::get_diff_summary(&worktree_path, &base_branch)
+}
+
+/// Line counts for a file's diff; check before `get_file_diff` on large files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiffStats {
+    pub insertions: u32,
+    pub deletions: u32,
+}
+
+#[tauri::command]
+fn get_diff_between(
+    worktree_path: String,
+    from_ref: String,
+    to_ref: String,
+) -> Result<git::DiffSummary, String> {
+    git::get_diff_between(&worktree_path, &from_ref, &to_ref)
+}
+
+#[tauri::command]
+fn get_file_diff_between(
+    worktree_path: String,
+    from_ref: String,
+    to_ref: String,
+    file_path: String,
+    old_path: Option<String>,
+) -> Result<git::FileDiff, String> {
+    git::get_file_diff_between(
+        &worktree_path,
+        &from_ref,
+        &to_ref,
+        &file_path,
+        old_path.as_deref(),
+    )
+}
+
+/// What's staged in the index, as opposed to the unstaged or versus-base diffs
+#[tauri::command]
+fn get_staged_diff_summary(worktree_path: String) -> Result<git::DiffSummary, String> {
+    git::get_staged_diff_summary(&worktree_path)
+}
+
+#[tauri::command]
+fn get_staged_file_diff(
+    worktree_path: String,
+    file_path: String,
+    old_path: Option<String>,
+) -> Result<git::FileDiff, String> {
+    git::get_staged_file_diff(&worktree_path, &file_path, old_path.as_deref())
+}
+
+/// Full base and worktree contents of a file, for an editable merge view
+#[tauri::command]
+fn get_file_versions(
+    worktree_path: String,
+    file_path: String,
+    base_branch: String,
+) -> Result<git::FileVersions, String> {
+    git::get_file_versions(&worktree_path, &file_path, &base_branch)
+}
+
+/// Flag files not worth reviewing: generated (lockfiles, bundles, snapshots,
+/// `linguist-generated`) or large (lines changed against `base_branch`, default HEAD)
+#[tauri::command]
+fn classify_files(
+    worktree_path: String,
+    files: Vec<String>,
+    base_branch: Option<String>,
+) -> Result<Vec<git::FileClassification>, String> {
+    git::classify_files(&worktree_path, &files, base_branch.as_deref())
+}
+
+/// Last commit to touch each line of a file at a ref, for annotating context lines
+#[tauri::command]
+async fn blame_file(
+    worktree_path: String,
+    file_path: String,
+    ref_name: String,
+    op_id: Option<String>,
+) -> Result<Vec<git::BlameLine>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::blame_file(&worktree_path, &file_path, &ref_name, op_id.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn get_file_diff_stats(
+    worktree_path: String,
+    file_path: String,
+    base_branch: String,
+) -> Result<FileDiffStats, String> {
+    let (insertions, deletions) =
+        git::get_file_diff_stats(&worktree_path, &file_path, &base_branch)?;
+    Ok(FileDiffStats {
+        insertions,
+        deletions,
+    })
+}
+
+#[tauri::command]
+fn get_file_diff(
+    worktree_path: String,
+    file_path: String,
+    base_branch: String,
+    word_diff: Option<bool>,
+    diff_algorithm: Option<String>,
+) -> Result<git::FileDiff, String> {
+    git::get_file_diff(
+        &worktree_path,
+        &file_path,
+        &base_branch,
+        word_diff.unwrap_or(false),
+        diff_algorithm.as_deref(),
+    )
+}
+
+/// Hash of a file's current diff, to check whether a cached `get_file_diff` is stale
+#[tauri::command]
+fn get_file_diff_hash(
+    worktree_path: String,
+    file_path: String,
+    base_branch: String,
+    diff_algorithm: Option<String>,
+) -> Result<String, String> {
+    git::get_file_diff_hash(
+        &worktree_path,
+        &file_path,
+        &base_branch,
+        diff_algorithm.as_deref(),
+    )
+}
+
+#[tauri::command]
+fn get_file_diff_hunk(
+    worktree_path: String,
+    file_path: String,
+    base_branch: String,
+    hunk_index: usize,
+    diff_algorithm: Option<String>,
+) -> Result<git::FileDiffHunk, String> {
+    git::get_file_diff_hunk(
+        &worktree_path,
+        &file_path,
+        &base_branch,
+        hunk_index,
+        diff_algorithm.as_deref(),
+    )
+}
+
+/// A file's diff as left/right rows for the side-by-side viewer
+#[tauri::command]
+fn get_file_diff_split(
+    worktree_path: String,
+    file_path: String,
+    base_branch: String,
+    word_diff: Option<bool>,
+    diff_algorithm: Option<String>,
+) -> Result<git::SplitDiff, String> {
+    let diff = git::get_file_diff(
+        &worktree_path,
+        &file_path,
+        &base_branch,
+        word_diff.unwrap_or(false),
+        diff_algorithm.as_deref(),
+    )?;
+    Ok(git::to_split_view(&diff))
+}
+
+/// Default cap on files returned by `get_full_diff`
+const FULL_DIFF_MAX_FILES: usize = 200;
+
+#[tauri::command]
+async fn get_full_diff(
+    worktree_path: String,
+    base_branch: String,
+    max_files: Option<usize>,
+    diff_algorithm: Option<String>,
+    op_id: Option<String>,
+) -> Result<Vec<git::FileDiff>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::get_full_diff(
+            &worktree_path,
+            &base_branch,
+            max_files.unwrap_or(FULL_DIFF_MAX_FILES),
+            diff_algorithm.as_deref(),
+            op_id.as_deref(),
+        )
     })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
-fn delete_session(id: String) -> Result<(), String> {
-    db::delete_session(&id).map_err(|e| e.to_string())
+fn is_git_repo(path: String) -> bool {
+    git::is_git_repo(&path)
 }
 
 #[tauri::command]
-fn rename_session(id: String, name: String) -> Result<(), String> {
-    db::rename_session(&id, &name).map_err(|e| e.to_string())
+fn get_repo_root(path: String) -> Result<String, String> {
+    git::get_repo_root(&path)
 }
 
 #[tauri::command]
-fn update_session_cwd(id: String, cwd: String) -> Result<(), String> {
-    db::update_session_cwd(&id, &cwd).map_err(|e| e.to_string())
+fn run_git_readonly(worktree_path: String, args: Vec<String>) -> Result<String, String> {
+    git::run_readonly(&worktree_path, args)
 }
 
 #[tauri::command]
-fn get_session_status(id: String) -> Result<String, String> {
-    db::get_session(&id)
-        .map_err(|e| e.to_string())?
-        .map(|s| s.status)
-        .ok_or_else(|| "Session not found".to_string())
+fn detect_default_branch(repo_path: String) -> Result<String, String> {
+    git::detect_default_branch(&repo_path)
 }
 
 #[tauri::command]
-fn set_session_status(id: String, status: String) -> Result<(), String> {
-    db::update_session_status(&id, &status).map_err(|e| e.to_string())
+fn list_branches(repo_path: String, include_remote: Option<bool>) -> Result<Vec<String>, String> {
+    git::list_branches(&repo_path, include_remote.unwrap_or(false))
 }
 
-// Tauri commands for inbox messages
 #[tauri::command]
-fn get_inbox_messages() -> Result<Vec<InboxMessageData>, String> {
-    db::get_all_inbox_messages()
-        .map(|messages| {
-            messages
+fn get_current_branch(worktree_path: String) -> Result<String, String> {
+    git::get_current_branch(&worktree_path)
+}
+
+#[tauri::command]
+fn get_commit_sha(worktree_path: String, ref_name: String) -> Result<String, String> {
+    git::get_commit_sha(&worktree_path, &ref_name)
+}
+
+#[tauri::command]
+fn get_new_commits(worktree_path: String, base_branch: String) -> Result<Vec<String>, String> {
+    git::get_new_commits(&worktree_path, &base_branch)
+}
+
+#[tauri::command]
+fn update_session_base_commit(id: String, base_commit: String) -> Result<(), String> {
+    db::update_session_base_commit(&id, &base_commit).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaseCommitEntryData {
+    pub base_commit: String,
+    pub set_at: String,
+}
+
+/// Total Claude cost across all sessions between two RFC3339 timestamps
+/// (`from` inclusive, `to` exclusive)
+#[tauri::command]
+fn get_cost_summary(from: String, to: String) -> Result<db::CostSummary, String> {
+    let parse = |name: &str, value: &str| {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| format!("Invalid {} timestamp '{}': {}", name, value, e))
+    };
+    let from = parse("from", &from)?;
+    let to = parse("to", &to)?;
+    if from > to {
+        return Err("'from' must not be after 'to'".to_string());
+    }
+    db::get_cost_summary(from, to).map_err(|e| e.to_string())
+}
+
+/// Every base commit the session has been diffed against, oldest first
+#[tauri::command]
+fn get_base_commit_history(session_id: String) -> Result<Vec<BaseCommitEntryData>, String> {
+    db::get_base_commit_history(&session_id)
+        .map(|entries| {
+            entries
                 .into_iter()
-                .map(|m| InboxMessageData {
-                    id: m.id,
-                    session_id: m.session_id,
-                    session_name: m.session_name,
-                    message: m.message,
-                    created_at: m.created_at.to_rfc3339(),
-                    read_at: m.read_at.map(|dt| dt.to_rfc3339()),
-                    first_read_at: m.first_read_at.map(|dt| dt.to_rfc3339()),
+                .map(|e| BaseCommitEntryData {
+                    base_commit: e.base_commit,
+                    set_at: e.set_at.to_rfc3339(),
                 })
                 .collect()
         })
         .map_err(|e| e.to_string())
 }
 
+/// Make a previous base commit from the session's history active again.
+/// The restore is itself recorded as a new history entry.
 #[tauri::command]
-fn mark_inbox_message_read(id: String) -> Result<(), String> {
-    db::mark_message_read(&id).map_err(|e| e.to_string())
-}
-
-#[tauri::command]
-fn mark_inbox_message_unread(id: String) -> Result<(), String> {
-    db::mark_message_unread(&id).map_err(|e| e.to_string())
+fn restore_base_commit(session_id: String, base_commit: String) -> Result<(), String> {
+    if !db::has_base_commit_in_history(&session_id, &base_commit).map_err(|e| e.to_string())? {
+        return Err(format!(
+            "{} is not in the base commit history of session {}",
+            base_commit, session_id
+        ));
+    }
+    db::update_session_base_commit(&session_id, &base_commit).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn mark_session_messages_read(session_id: String) -> Result<u32, String> {
-    db::mark_session_messages_read(&session_id).map_err(|e| e.to_string())
+fn update_session_claude_id(id: String, claude_session_id: String) -> Result<(), String> {
+    db::update_session_claude_id(&id, &claude_session_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn delete_inbox_message(id: String) -> Result<(), String> {
-    db::delete_inbox_message(&id).map_err(|e| e.to_string())
+fn get_session_claude_id(id: String) -> Result<Option<String>, String> {
+    db::get_session_claude_id(&id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn clear_inbox() -> Result<(), String> {
-    db::clear_inbox().map_err(|e| e.to_string())
+async fn fetch_origin(worktree_path: String, op_id: Option<String>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        git::fetch_origin(&worktree_path, op_id.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
-/// Configure a worktree directory for Claude Code
-/// Note: MCP configuration is no longer needed - custom tools are now provided
-/// directly via the SDK in agent-service. This function is kept for any future
-/// worktree-specific configuration needs.
+/// Kill a slow git call started with this `op_id` (`fetch_origin`,
+/// `blame_file`, `get_full_diff`); that call then fails with a "cancelled"
+/// error. Those commands run off the main thread so this can be handled
+/// while they're in progress.
 #[tauri::command]
-fn configure_worktree(worktree_path: String, _session_id: String) -> Result<(), String> {
-    // MCP configuration removed - custom tools (notify_ready, get_pending_comments, etc.)
-    // are now provided directly to the SDK via createSdkMcpServer() in agent-service.
-    // No need to write .mcp.json or .claude/settings.local.json anymore.
-
-    println!(
-        "[Config] Worktree configured at: {} (no MCP files needed)",
-        worktree_path
-    );
+fn cancel_git_operation(op_id: String) -> Result<(), String> {
+    if !git::cancel_operation(&op_id) {
+        return Err(format!("No git operation {} is running", op_id));
+    }
     Ok(())
 }
 
-// Git diff commands
 #[tauri::command]
-fn get_diff_summary(
+fn rebase_onto_base(
     worktree_path: String,
     base_branch: String,
-) -> Result<git::DiffSummary, String> {
-    git::get_diff_summary(&worktree_path, &base_branch)
+) -> Result<git::RebaseOutcome, String> {
+    git::rebase_onto_base(&worktree_path, &base_branch)
 }
 
 #[tauri::command]
-fn get_file_diff(
-    worktree_path: String,
-    file_path: String,
-    base_branch: String,
-) -> Result<git::FileDiff, String> {
-    git::get_file_diff(&worktree_path, &file_path, &base_branch)
+fn abort_rebase(worktree_path: String) -> Result<(), String> {
+    git::abort_rebase(&worktree_path)
 }
 
 #[tauri::command]
-fn get_current_branch(worktree_path: String) -> Result<String, String> {
-    git::get_current_branch(&worktree_path)
+fn get_conflicts(worktree_path: String) -> Result<Vec<String>, String> {
+    git::get_conflicts(&worktree_path)
 }
 
+/// Get a session's changes as a `.patch`, diffed against its base commit
+/// (or the workspace's origin branch if no base commit was recorded)
 #[tauri::command]
-fn get_commit_sha(worktree_path: String, ref_name: String) -> Result<String, String> {
-    git::get_commit_sha(&worktree_path, &ref_name)
+fn get_session_patch(session_id: String) -> Result<String, String> {
+    let (session, base) = session_with_diff_base(&session_id)?;
+    git::get_patch(&session.cwd, &base)
 }
 
-#[tauri::command]
-fn update_session_base_commit(id: String, base_commit: String) -> Result<(), String> {
-    db::update_session_base_commit(&id, &base_commit).map_err(|e| e.to_string())
+/// Load a session along with the ref its changes are diffed against: its
+/// base commit, or the workspace's origin branch if none was recorded
+fn session_with_diff_base(session_id: &str) -> Result<(db::Session, String), String> {
+    let session = db::get_session(session_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Session {} not found", session_id))?;
+
+    let base = match &session.base_commit {
+        Some(commit) => commit.clone(),
+        None => {
+            let workspace = match &session.workspace_id {
+                Some(id) => db::get_workspace(id).map_err(|e| e.to_string())?,
+                None => None,
+            };
+            format!(
+                "origin/{}",
+                workspace.map_or_else(|| "main".to_string(), |w| w.origin_branch)
+            )
+        }
+    };
+
+    Ok((session, base))
 }
 
-#[tauri::command]
-fn update_session_claude_id(id: String, claude_session_id: String) -> Result<(), String> {
-    db::update_session_claude_id(&id, &claude_session_id).map_err(|e| e.to_string())
+/// A file changed by only one of two compared sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparedFileDelta {
+    pub path: String,
+    pub net_lines: i64, // Insertions minus deletions
+}
+
+/// A file changed by both compared sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparedCommonFile {
+    pub path: String,
+    pub a_net_lines: i64,
+    pub b_net_lines: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionComparison {
+    pub common_files: Vec<ComparedCommonFile>,
+    pub a_only: Vec<ComparedFileDelta>,
+    pub b_only: Vec<ComparedFileDelta>,
 }
 
+/// Compare which files two sessions changed, each against its own base,
+/// e.g. to review two agents' attempts at the same task side by side
 #[tauri::command]
-fn get_session_claude_id(id: String) -> Result<Option<String>, String> {
-    db::get_session_claude_id(&id).map_err(|e| e.to_string())
+fn compare_sessions(session_a: String, session_b: String) -> Result<SessionComparison, String> {
+    let net_lines_by_path =
+        |session_id: &str| -> Result<std::collections::BTreeMap<String, i64>, String> {
+            let (session, base) = session_with_diff_base(session_id)?;
+            let summary = git::get_diff_summary(&session.cwd, &base)?;
+            Ok(summary
+                .files
+                .into_iter()
+                .map(|file| {
+                    let net = file.insertions as i64 - file.deletions as i64;
+                    (file.path, net)
+                })
+                .collect())
+        };
+
+    let a_files = net_lines_by_path(&session_a)?;
+    let mut b_files = net_lines_by_path(&session_b)?;
+
+    let mut comparison = SessionComparison {
+        common_files: Vec::new(),
+        a_only: Vec::new(),
+        b_only: Vec::new(),
+    };
+    for (path, a_net_lines) in a_files {
+        match b_files.remove(&path) {
+            Some(b_net_lines) => comparison.common_files.push(ComparedCommonFile {
+                path,
+                a_net_lines,
+                b_net_lines,
+            }),
+            None => comparison.a_only.push(ComparedFileDelta {
+                path,
+                net_lines: a_net_lines,
+            }),
+        }
+    }
+    comparison.b_only = b_files
+        .into_iter()
+        .map(|(path, net_lines)| ComparedFileDelta { path, net_lines })
+        .collect();
+
+    Ok(comparison)
+}
+
+/// Store file holding user settings
+const SETTINGS_STORE: &str = "settings.json";
+
+/// Read a non-empty string setting from the settings store
+fn get_string_setting(app: &tauri::AppHandle, key: &str) -> Option<String> {
+    use tauri_plugin_store::StoreExt;
+
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(key))
+        .and_then(|value| value.as_str().map(String::from))
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// Read a non-negative integer setting from the settings store
+fn get_u64_setting(app: &tauri::AppHandle, key: &str) -> Option<u64> {
+    use tauri_plugin_store::StoreExt;
+
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(key))
+        .and_then(|value| value.as_u64())
+}
+
+fn set_string_setting(app: &tauri::AppHandle, key: &str, value: &str) -> Result<(), String> {
+    use tauri_plugin_store::StoreExt;
+
+    let store = app.store(SETTINGS_STORE).map_err(|e| e.to_string())?;
+    store.set(key, value);
+    store.save().map_err(|e| e.to_string())
+}
+
+/// Settings key holding the user's editor command template
+const EDITOR_COMMAND_KEY: &str = "editor_command";
+const DEFAULT_EDITOR_COMMAND: &str = "code --goto {file}:{line}";
+
+/// Build the argv for an editor template. `{file}` and `{line}` are substituted
+/// per argument (so paths with spaces stay intact); a template without `{file}`
+/// (e.g. a bare `$EDITOR` like "vim") gets the file appended.
+fn editor_args(template: &str, file: &str, line: u32) -> Vec<String> {
+    let mut args: Vec<String> = template
+        .split_whitespace()
+        .map(|arg| {
+            arg.replace("{file}", file)
+                .replace("{line}", &line.to_string())
+        })
+        .collect();
+    if !template.contains("{file}") {
+        args.push(file.to_string());
+    }
+    args
 }
 
+/// Open a file from a session's worktree in the user's editor, at a line if given
 #[tauri::command]
-fn fetch_origin(worktree_path: String) -> Result<(), String> {
-    git::fetch_origin(&worktree_path)
+fn open_in_editor(
+    app: tauri::AppHandle,
+    worktree_path: String,
+    file_path: String,
+    line_number: Option<u32>,
+) -> Result<(), String> {
+    git::validate_relative_path(&file_path)?;
+    let template = get_string_setting(&app, EDITOR_COMMAND_KEY)
+        .unwrap_or_else(|| DEFAULT_EDITOR_COMMAND.to_string());
+
+    let file = std::path::Path::new(&worktree_path).join(&file_path);
+    let args = editor_args(&template, &file.to_string_lossy(), line_number.unwrap_or(1));
+    let (program, rest) = args
+        .split_first()
+        .ok_or_else(|| "Editor command is empty".to_string())?;
+
+    info!("[Editor] Opening {:?} with {}", file, program);
+
+    std::process::Command::new(program)
+        .args(rest)
+        .current_dir(&worktree_path)
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                format!(
+                    "Editor '{}' not found on PATH. Set '{}' in {} (e.g. \"{}\")",
+                    program, EDITOR_COMMAND_KEY, SETTINGS_STORE, DEFAULT_EDITOR_COMMAND
+                )
+            } else {
+                format!("Failed to launch editor '{}': {}", program, e)
+            }
+        })?;
+
+    Ok(())
 }
 
 // Comment commands
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn create_comment(
     session_id: String,
     file_path: String,
@@ -330,20 +1464,169 @@ fn create_comment(
     author: String,
     content: String,
     parent_id: Option<String>,
+    anchor: Option<db::CommentAnchor>,
+    end_line_number: Option<i32>,
+    severity: Option<String>,
 ) -> Result<DiffCommentData, String> {
-    db::create_comment(
+    git::validate_relative_path(&file_path)?;
+    let severity: db::CommentSeverity = match severity {
+        Some(severity) => severity.parse()?,
+        None => db::CommentSeverity::default(),
+    };
+    if let (Some(start), Some(end)) = (line_number, end_line_number) {
+        if end < start {
+            return Err(format!(
+                "end_line_number ({}) must not be before line_number ({})",
+                end, start
+            ));
+        }
+    }
+
+    let comment = db::create_comment(
         &session_id,
         &file_path,
         line_number,
+        end_line_number,
         line_type.as_deref(),
         &author,
         &content,
         parent_id.as_deref(),
+        severity,
+        anchor.as_ref(),
+        db::CommentAuthorKind::User,
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(comment_to_data(comment))
+}
+
+/// Settings key for the display name used on comments the user writes
+const COMMENT_AUTHOR_KEY: &str = "comment_author";
+const DEFAULT_COMMENT_AUTHOR: &str = "user";
+
+#[tauri::command]
+fn get_comment_author(app: tauri::AppHandle) -> String {
+    get_string_setting(&app, COMMENT_AUTHOR_KEY)
+        .unwrap_or_else(|| DEFAULT_COMMENT_AUTHOR.to_string())
+}
+
+#[tauri::command]
+fn set_comment_author(app: tauri::AppHandle, name: String) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Comment author must not be empty".to_string());
+    }
+    set_string_setting(&app, COMMENT_AUTHOR_KEY, name)
+}
+
+/// Create a comment authored by the user, using their configured display name
+#[tauri::command]
+fn create_user_comment(
+    app: tauri::AppHandle,
+    session_id: String,
+    file_path: String,
+    line_number: Option<i32>,
+    line_type: Option<String>,
+    content: String,
+    severity: Option<String>,
+) -> Result<DiffCommentData, String> {
+    git::validate_relative_path(&file_path)?;
+    let severity: db::CommentSeverity = match severity {
+        Some(severity) => severity.parse()?,
+        None => db::CommentSeverity::default(),
+    };
+    let author = get_comment_author(app);
+    db::create_comment(
+        &session_id,
+        &file_path,
+        line_number,
+        None,
+        line_type.as_deref(),
+        &author,
+        &content,
+        None,
+        severity,
+        None,
+        db::CommentAuthorKind::User,
     )
     .map(comment_to_data)
     .map_err(|e| e.to_string())
 }
 
+/// Re-locate a file's comments against a freshly generated diff.
+/// Comments whose anchored line moved are updated in place; ones whose line
+/// can no longer be found are returned with `outdated: true`. Pass the
+/// `diff_algorithm` the user is viewing, so line numbers match.
+#[tauri::command]
+fn reanchor_comments(
+    session_id: String,
+    worktree_path: String,
+    file_path: String,
+    base_branch: String,
+    diff_algorithm: Option<String>,
+) -> Result<Vec<ReanchoredCommentData>, String> {
+    let diff = git::get_file_diff(
+        &worktree_path,
+        &file_path,
+        &base_branch,
+        false,
+        diff_algorithm.as_deref(),
+    )?;
+    let comments = db::get_comments_for_file_with_anchors(&session_id, &file_path)
+        .map_err(|e| e.to_string())?;
+
+    // Resolve root comments first; replies follow their root
+    let mut root_results: std::collections::HashMap<String, (Option<i32>, bool)> =
+        std::collections::HashMap::new();
+    for anchored in comments.iter().filter(|c| c.comment.parent_id.is_none()) {
+        let c = &anchored.comment;
+        let (line_number, outdated) = match (&anchored.anchor, &c.line_type, c.line_number) {
+            (Some(anchor), Some(line_type), Some(old_line)) => {
+                match git::locate_line(
+                    &diff,
+                    line_type,
+                    &anchor.content,
+                    &anchor.before,
+                    &anchor.after,
+                    old_line.max(0) as u32,
+                ) {
+                    Some(new_line) => {
+                        let new_line = new_line as i32;
+                        if new_line != old_line {
+                            db::update_comment_line(&c.id, new_line).map_err(|e| e.to_string())?;
+                        }
+                        (Some(new_line), false)
+                    }
+                    None => (c.line_number, true),
+                }
+            }
+            // File-level or un-anchored comments can't be re-located
+            _ => (c.line_number, false),
+        };
+        root_results.insert(c.id.clone(), (line_number, outdated));
+    }
+
+    Ok(comments
+        .into_iter()
+        .map(|anchored| {
+            let mut comment = anchored.comment;
+            let root_id = comment
+                .parent_id
+                .clone()
+                .unwrap_or_else(|| comment.id.clone());
+            let (line_number, outdated) = root_results
+                .get(&root_id)
+                .copied()
+                .unwrap_or((comment.line_number, false));
+            comment.line_number = line_number;
+            ReanchoredCommentData {
+                comment: comment_to_data(comment),
+                outdated,
+            }
+        })
+        .collect())
+}
+
 #[tauri::command]
 fn get_comments_for_session(session_id: String) -> Result<Vec<DiffCommentData>, String> {
     db::get_comments_for_session(&session_id)
@@ -358,20 +1641,147 @@ fn get_open_comments_for_session(session_id: String) -> Result<Vec<DiffCommentDa
         .map_err(|e| e.to_string())
 }
 
+/// A session's comments from one side of the review: `author` is "user" for
+/// ones written in the app or "agent" for ones posted through the session
+/// server. Comments older than that tracking are matched by the current
+/// display name, so renaming it can misfile them.
+#[tauri::command]
+fn get_comments_by_author(
+    app: tauri::AppHandle,
+    session_id: String,
+    author: String,
+) -> Result<Vec<DiffCommentData>, String> {
+    let agent = match author.trim() {
+        "user" => false,
+        "agent" => true,
+        other => {
+            return Err(format!(
+                "Invalid comment author '{}': must be user or agent",
+                other
+            ))
+        }
+    };
+    let user_author = get_comment_author(app);
+    db::get_comments_for_session_by_author(&session_id, &user_author, agent)
+        .map(|comments| comments.into_iter().map(comment_to_data).collect())
+        .map_err(|e| e.to_string())
+}
+
+/// Count open/resolved root comments (replies are not counted)
+#[tauri::command]
+fn get_comment_counts(session_id: String) -> Result<CommentCountsData, String> {
+    db::get_comment_counts(&session_id)
+        .map(|(open, resolved)| CommentCountsData { open, resolved })
+        .map_err(|e| e.to_string())
+}
+
+/// Open root comments marked as blockers; approving should wait until this is 0
+#[tauri::command]
+fn get_blocking_comment_count(session_id: String) -> Result<u32, String> {
+    db::get_blocking_comment_count(&session_id).map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileOpenCommentsData {
+    pub file_path: String,
+    pub open_count: u32,
+}
+
+/// Files that still have open root comments, sorted by path
+#[tauri::command]
+fn get_files_with_open_comments(session_id: String) -> Result<Vec<FileOpenCommentsData>, String> {
+    db::get_files_with_open_comments(&session_id)
+        .map(|files| {
+            files
+                .into_iter()
+                .map(|(file_path, open_count)| FileOpenCommentsData {
+                    file_path,
+                    open_count,
+                })
+                .collect()
+        })
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn reply_to_comment(
     parent_id: String,
     author: String,
     content: String,
 ) -> Result<DiffCommentData, String> {
-    db::reply_to_comment(&parent_id, &author, &content)
+    db::reply_to_comment(&parent_id, &author, &content, db::CommentAuthorKind::User)
         .map(comment_to_data)
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 fn resolve_comment(id: String) -> Result<(), String> {
-    db::resolve_comment(&id).map_err(|e| e.to_string())
+    db::resolve_comment(&id)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn reopen_comment(id: String) -> Result<(), String> {
+    if db::reopen_comment(&id).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("Comment {} not found", id))
+    }
+}
+
+/// Manually move a comment (with its replies) to a line, e.g. when re-anchoring
+/// couldn't find it. The target must be a line of that type in the current
+/// diff (old numbering for deletes); `anchor` describes it for re-anchoring.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn relocate_comment(
+    id: String,
+    file_path: String,
+    line_number: i32,
+    line_type: String,
+    anchor: db::CommentAnchor,
+    worktree_path: String,
+    base_branch: String,
+    diff_algorithm: Option<String>,
+) -> Result<DiffCommentData, String> {
+    git::validate_relative_path(&file_path)?;
+    if !["add", "delete", "context"].contains(&line_type.as_str()) {
+        return Err(format!(
+            "Invalid line type '{}': must be add, delete or context",
+            line_type
+        ));
+    }
+    if line_number < 1 {
+        return Err(format!("Invalid line number {}", line_number));
+    }
+
+    let diff = git::get_file_diff(
+        &worktree_path,
+        &file_path,
+        &base_branch,
+        false,
+        diff_algorithm.as_deref(),
+    )?;
+    let exists = diff.hunks.iter().flat_map(|h| &h.lines).any(|line| {
+        let number = if line.line_type == "delete" {
+            line.old_line
+        } else {
+            line.new_line
+        };
+        line.line_type == line_type && number == Some(line_number as u32)
+    });
+    if !exists {
+        return Err(format!(
+            "{} has no {} line {} in the current diff",
+            file_path, line_type, line_number
+        ));
+    }
+
+    db::relocate_comment(&id, &file_path, line_number, &line_type, &anchor)
+        .map_err(|e| e.to_string())?
+        .map(comment_to_data)
+        .ok_or_else(|| format!("Comment {} not found or is a reply", id))
 }
 
 #[tauri::command]
@@ -438,20 +1848,7 @@ fn increment_sync_attempts(id: String, error: String) -> Result<(), String> {
 #[tauri::command]
 fn get_unsynced_sessions() -> Result<Vec<SessionData>, String> {
     db::get_unsynced_sessions()
-        .map(|sessions| {
-            sessions
-                .into_iter()
-                .map(|s| SessionData {
-                    id: s.id,
-                    name: s.name,
-                    cwd: s.cwd,
-                    workspace_id: s.workspace_id,
-                    worktree_name: s.worktree_name,
-                    status: s.status,
-                    base_commit: s.base_commit,
-                })
-                .collect()
-        })
+        .map(|sessions| sessions.into_iter().map(session_to_data).collect())
         .map_err(|e| e.to_string())
 }
 
@@ -509,11 +1906,11 @@ async fn start_oauth_flow() -> Result<u16, String> {
     // Use default config - the plugin injects JavaScript that fetches the full URL back
     // We cannot override the response as it breaks the callback mechanism
     tauri_plugin_oauth::start(move |url| {
-        println!("[OAuth] Received callback URL: {}", url);
+        info!("[OAuth] Received callback URL: {}", url);
         // Store the URL for polling
         if let Ok(mut stored_url) = OAUTH_CALLBACK_URL.lock() {
             *stored_url = Some(url);
-            println!("[OAuth] Stored callback URL for polling");
+            info!("[OAuth] Stored callback URL for polling");
         }
     })
     .map_err(|e| e.to_string())
@@ -568,9 +1965,12 @@ fn respond_to_permission(
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Dropped on exit to flush the log file
+    let mut log_guard = logging::init();
+
     // Initialize database
     if let Err(e) = db::init_db() {
-        eprintln!("[App] Failed to initialize database: {}", e);
+        error!("[App] Failed to initialize database: {}", e);
     }
 
     let mut builder = tauri::Builder::default()
@@ -582,35 +1982,104 @@ pub fn run() {
         .plugin(tauri_plugin_deep_link::init())
         .invoke_handler(tauri::generate_handler![
             get_workspaces,
+            get_workspace,
             create_workspace,
             delete_workspace,
+            save_workspace_ui_state,
+            get_workspace_ui_state,
+            get_workspace_stats,
             get_sessions,
             create_session,
             delete_session,
+            add_session_tag,
+            remove_session_tag,
+            get_session_tags,
+            get_sessions_by_tag,
             rename_session,
             update_session_cwd,
             get_session_status,
             set_session_status,
+            set_session_error,
+            get_stalled_sessions,
+            create_sessions_batch,
+            get_recent_folders,
+            save_template,
+            get_templates,
+            delete_template,
+            find_stale_sessions,
+            get_run_messages,
+            estimate_tokens,
+            get_app_data_dir,
+            get_log_path,
+            set_log_level,
+            reveal_app_data_dir,
+            import_database,
+            get_cost_summary,
             configure_worktree,
+            verify_worktree_config,
             get_inbox_messages,
             mark_inbox_message_read,
+            get_starred_messages,
+            export_inbox,
+            set_inbox_message_starred,
             mark_inbox_message_unread,
             mark_session_messages_read,
+            mark_session_messages_unread,
+            get_first_unread_message,
+            acknowledge_inbox_message,
             delete_inbox_message,
             clear_inbox,
+            prune_orphans,
             get_diff_summary,
             get_file_diff,
+            get_file_diff_hash,
+            get_file_diff_hunk,
+            get_file_diff_split,
+            get_file_diff_stats,
+            get_file_versions,
+            blame_file,
+            classify_files,
+            get_diff_between,
+            get_file_diff_between,
+            get_staged_diff_summary,
+            get_staged_file_diff,
+            get_full_diff,
+            get_session_patch,
+            compare_sessions,
+            is_git_repo,
+            get_repo_root,
+            run_git_readonly,
+            list_branches,
+            detect_default_branch,
             get_current_branch,
             get_commit_sha,
+            get_new_commits,
             update_session_base_commit,
+            get_base_commit_history,
+            restore_base_commit,
             update_session_claude_id,
             get_session_claude_id,
             fetch_origin,
+            cancel_git_operation,
+            rebase_onto_base,
+            abort_rebase,
+            get_conflicts,
+            open_in_editor,
             create_comment,
+            get_comment_author,
+            set_comment_author,
+            create_user_comment,
             get_comments_for_session,
             get_open_comments_for_session,
+            reanchor_comments,
+            get_comment_counts,
+            get_comments_by_author,
+            get_blocking_comment_count,
+            get_files_with_open_comments,
             reply_to_comment,
             resolve_comment,
+            reopen_comment,
+            relocate_comment,
             delete_comment,
             // Sync queue commands
             add_to_sync_queue,
@@ -632,21 +2101,52 @@ pub fn run() {
             claude_headless::start_claude_headless,
             claude_headless::send_claude_input,
             claude_headless::stop_claude_session,
+            claude_headless::stop_all_claude_sessions,
+            claude_headless::interrupt_claude_session,
+            claude_headless::request_session_stop,
             claude_headless::is_claude_running,
             claude_headless::get_running_claude_sessions,
+            claude_headless::get_running_claude_session_details,
+            claude_headless::check_claude_cli,
+            claude_headless::get_live_session_transcript,
+            claude_headless::get_running_session_stats,
+            claude_headless::queue_claude_run,
+            claude_headless::get_queued_runs,
+            claude_headless::cancel_queued_run,
+            claude_headless::resume_queued_runs,
             // Agent SDK sidecar command (new)
             claude_headless::start_claude_agent,
             // Session persistence commands
             claude_sessions::load_claude_session_messages,
+            claude_sessions::tail_claude_session_messages,
             claude_sessions::list_claude_sessions,
+            claude_sessions::list_claude_projects,
+            claude_sessions::get_claude_project_dir,
+            claude_sessions::get_last_assistant_message,
+            claude_sessions::get_session_first_prompt,
+            claude_sessions::watch_claude_session,
+            claude_sessions::unwatch_claude_session,
+            claude_sessions::delete_claude_session_file,
         ])
         .setup(|app| {
+            // Apply the saved log level unless RUST_LOG chose one
+            if !logging::level_from_env() {
+                if let Some(level) = get_string_setting(app.handle(), logging::LOG_LEVEL_KEY) {
+                    if let Err(e) = logging::set_level(&level) {
+                        warn!("[App] Ignoring saved log level: {}", e);
+                    }
+                }
+            }
+
             // Spawn HTTP server for MCP bridge in background
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 server::start_server_with_app(app_handle).await;
             });
 
+            // Runs left queued from a previous launch wait for the user to resume them
+            claude_headless::hold_restored_runs(app.handle());
+
             // Set up deep link handler for OAuth callbacks
             #[cfg(desktop)]
             {
@@ -654,7 +2154,7 @@ pub fn run() {
 
                 // Register the deep link scheme (needed for dev mode)
                 if let Err(e) = app.deep_link().register_all() {
-                    eprintln!("[DeepLink] Failed to register: {}", e);
+                    warn!("[DeepLink] Failed to register: {}", e);
                 }
 
                 // Handle deep links opened while app is running
@@ -662,10 +2162,10 @@ pub fn run() {
                 app.deep_link().on_open_url(move |event| {
                     let urls = event.urls();
                     for url in urls {
-                        println!("[DeepLink] Received: {}", url);
+                        info!("[DeepLink] Received: {}", url);
                         // Emit event to frontend for OAuth callback handling
                         if let Err(e) = app_handle.emit("deep-link", url.to_string()) {
-                            eprintln!("[DeepLink] Failed to emit event: {}", e);
+                            warn!("[DeepLink] Failed to emit event: {}", e);
                         }
                     }
                 });
@@ -674,9 +2174,9 @@ pub fn run() {
                 if let Ok(Some(urls)) = app.deep_link().get_current() {
                     let app_handle = app.handle().clone();
                     for url in urls {
-                        println!("[DeepLink] Started with: {}", url);
+                        info!("[DeepLink] Started with: {}", url);
                         if let Err(e) = app_handle.emit("deep-link", url.to_string()) {
-                            eprintln!("[DeepLink] Failed to emit startup event: {}", e);
+                            warn!("[DeepLink] Failed to emit startup event: {}", e);
                         }
                     }
                 }
@@ -692,6 +2192,60 @@ pub fn run() {
     }
 
     builder
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(move |_app, event| {
+            // Don't leave orphaned claude processes behind on quit
+            if let tauri::RunEvent::Exit = event {
+                let stopped = claude_headless::stop_all_processes();
+                if !stopped.is_empty() {
+                    info!("[App] Stopped {} Claude session(s) on exit", stopped.len());
+                }
+                // The process exits without unwinding, so flush the log here
+                drop(log_guard.take());
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir
+    fn temp_dir() -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("claude-sessions-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn write_config_file_backs_up_invalid_json() {
+        let dir = temp_dir();
+        let path = dir.join(".mcp.json");
+        let original = "{\n  // hand-edited\n  \"mcpServers\": {},\n}\n";
+        std::fs::write(&path, original).unwrap();
+
+        write_config_file(&path, "{}").unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.join(".mcp.json.bak")).unwrap(),
+            original
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_config_file_skips_backup_for_valid_json() {
+        let dir = temp_dir();
+        let path = dir.join(".mcp.json");
+        std::fs::write(&path, "{\"mcpServers\": {}}").unwrap();
+
+        write_config_file(&path, "{}").unwrap();
+
+        assert!(!dir.join(".mcp.json.bak").exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,104 @@
+//! Backend logging
+//!
+//! Log lines go to stdout and to a daily file under `<app data>/logs`
+//! (the last `MAX_LOG_FILES` days are kept). The level starts from
+//! `RUST_LOG` (a plain level such as "debug") or the saved `log_level`
+//! setting, and can be changed at runtime with `set_log_level`.
+
+use once_cell::sync::OnceCell;
+use std::path::PathBuf;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Registry};
+
+/// Settings key holding the level chosen with `set_log_level`
+pub const LOG_LEVEL_KEY: &str = "log_level";
+
+const LOG_FILE_PREFIX: &str = "claude-sessions";
+const LOG_FILE_SUFFIX: &str = "log";
+const MAX_LOG_FILES: usize = 7;
+
+/// Swaps the active level filter
+static LEVEL_HANDLE: OnceCell<reload::Handle<LevelFilter, Registry>> = OnceCell::new();
+
+/// Directory being logged to. Unset if the log file couldn't be opened.
+static FILE_LOG_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+pub fn get_log_dir() -> PathBuf {
+    crate::db::get_app_data_dir().join("logs")
+}
+
+/// Parse "off", "error", "warn", "info", "debug" or "trace"
+pub fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level
+        .trim()
+        .parse::<LevelFilter>()
+        .map_err(|_| format!("Invalid log level: {}", level))
+}
+
+/// Whether `RUST_LOG` picked the level, in which case the saved setting is ignored
+pub fn level_from_env() -> bool {
+    std::env::var("RUST_LOG").is_ok_and(|value| parse_level(&value).is_ok())
+}
+
+/// Install the global subscriber. Call once, before anything logs.
+/// The returned guard flushes the log file when dropped, so hold it until exit.
+pub fn init() -> Option<WorkerGuard> {
+    let level = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|value| parse_level(&value).ok())
+        .unwrap_or(LevelFilter::INFO);
+    let (filter, handle) = reload::Layer::new(level);
+
+    let log_dir = get_log_dir();
+    let (file_layer, guard) = match RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(&log_dir)
+    {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = FILE_LOG_DIR.set(log_dir);
+            (
+                Some(fmt::layer().with_writer(writer).with_ansi(false)),
+                Some(guard),
+            )
+        }
+        Err(e) => {
+            eprintln!("[Logging] File log disabled: {}", e);
+            (None, None)
+        }
+    };
+
+    let installed = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(file_layer)
+        .try_init();
+    if installed.is_ok() {
+        let _ = LEVEL_HANDLE.set(handle);
+    }
+    guard
+}
+
+/// Change the level of every log output
+pub fn set_level(level: &str) -> Result<(), String> {
+    let level = parse_level(level)?;
+    let handle = LEVEL_HANDLE
+        .get()
+        .ok_or_else(|| "Logging is not initialized".to_string())?;
+    handle.reload(level).map_err(|e| e.to_string())
+}
+
+/// Today's log file, or `None` if file logging is disabled. Files roll over
+/// at midnight UTC.
+pub fn get_log_path() -> Option<PathBuf> {
+    let log_dir = FILE_LOG_DIR.get()?;
+    let date = chrono::Utc::now().format("%Y-%m-%d");
+    Some(log_dir.join(format!("{}.{}.{}", LOG_FILE_PREFIX, date, LOG_FILE_SUFFIX)))
+}
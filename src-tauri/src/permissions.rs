@@ -1,7 +1,7 @@
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{Mutex, PoisonError};
 use tokio::sync::oneshot;
 
 /// A pending permission request waiting for user response
@@ -38,6 +38,13 @@ pub struct PermissionResponse {
     pub always_allow: Option<bool>,
 }
 
+/// Event payload when a pending request is withdrawn before the user answered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionCancelled {
+    pub request_id: String,
+    pub session_id: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum PermissionBehavior {
@@ -45,7 +52,8 @@ pub enum PermissionBehavior {
     Deny,
 }
 
-/// Global map of pending permission requests
+/// Global map of pending permission requests.
+/// Locks on these maps ignore poisoning: a panicking holder leaves them valid.
 pub static PENDING_PERMISSIONS: Lazy<Mutex<HashMap<String, PendingPermission>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
@@ -56,7 +64,9 @@ pub static ALWAYS_ALLOWED: Lazy<Mutex<HashMap<(String, String), bool>>> =
 
 /// Check if a tool is always-allowed for a session
 pub fn is_always_allowed(session_id: &str, tool_name: &str) -> bool {
-    let allowed = ALWAYS_ALLOWED.lock().unwrap();
+    let allowed = ALWAYS_ALLOWED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
     // Check exact match first
     if allowed.contains_key(&(session_id.to_string(), tool_name.to_string())) {
         return true;
@@ -67,25 +77,39 @@ pub fn is_always_allowed(session_id: &str, tool_name: &str) -> bool {
 
 /// Mark a tool as always-allowed for a session
 pub fn set_always_allowed(session_id: &str, tool_name: &str) {
-    let mut allowed = ALWAYS_ALLOWED.lock().unwrap();
+    let mut allowed = ALWAYS_ALLOWED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
     allowed.insert((session_id.to_string(), tool_name.to_string()), true);
 }
 
 /// Add a pending permission request
 pub fn add_pending(request_id: String, pending: PendingPermission) {
-    let mut pending_map = PENDING_PERMISSIONS.lock().unwrap();
+    let mut pending_map = PENDING_PERMISSIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
     pending_map.insert(request_id, pending);
 }
 
 /// Remove and return a pending permission request
 pub fn take_pending(request_id: &str) -> Option<PendingPermission> {
-    let mut pending_map = PENDING_PERMISSIONS.lock().unwrap();
+    let mut pending_map = PENDING_PERMISSIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
     pending_map.remove(request_id)
 }
 
+/// Withdraw a pending request. Dropping its sender wakes any awaiter with
+/// a `RecvError`. Returns the request if it was still pending.
+pub fn cancel_pending(request_id: &str) -> Option<PermissionRequest> {
+    take_pending(request_id).map(|pending| pending.request)
+}
+
 /// Get a list of all pending request IDs for a session
 pub fn get_pending_for_session(session_id: &str) -> Vec<String> {
-    let pending_map = PENDING_PERMISSIONS.lock().unwrap();
+    let pending_map = PENDING_PERMISSIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
     pending_map
         .iter()
         .filter(|(_, p)| p.request.session_id == session_id)
@@ -1,17 +1,20 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::Json,
     routing::{get, post},
     Router,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tauri::Emitter;
 use tokio::sync::oneshot;
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
+use tracing::{info, warn};
 
 use crate::db;
 use crate::permissions::{
@@ -20,11 +23,71 @@ use crate::permissions::{
 
 const SERVER_PORT: u16 = 19420;
 
+/// Page size for list endpoints when `?limit=` is absent, and the largest allowed
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// Messages a session may send per `MESSAGE_RATE_WINDOW` unless the
+/// "message_rate_limit" setting overrides it
+const DEFAULT_MESSAGE_RATE_LIMIT: u32 = 10;
+const MESSAGE_RATE_WINDOW: Duration = Duration::from_secs(10);
+const MESSAGE_RATE_LIMIT_KEY: &str = "message_rate_limit";
+
 #[derive(Debug, Serialize)]
 struct ApiResponse<T> {
     success: bool,
     data: Option<T>,
     error: Option<String>,
+    error_code: Option<ErrorCode>,
+}
+
+/// Machine-readable failure reason, so clients can branch without parsing `error`
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum ErrorCode {
+    SessionNotFound,
+    MessageNotFound,
+    CommentNotFound,
+    InvalidStatus,
+    DbError,
+    EventEmitFailed,
+    PermissionCancelled,
+    PermissionTimeout,
+    RateLimited,
+}
+
+/// Look up a session, mapping "missing" to 404 and lookup failures to 500
+fn require_session<T>(id: &str) -> Result<db::Session, (StatusCode, Json<ApiResponse<T>>)> {
+    match db::get_session(id) {
+        Ok(Some(session)) => Ok(session),
+        Ok(None) => Err(api_error(
+            StatusCode::NOT_FOUND,
+            ErrorCode::SessionNotFound,
+            format!("Session {} not found", id),
+        )),
+        Err(e) => Err(api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::DbError,
+            e.to_string(),
+        )),
+    }
+}
+
+/// Build a failed response carrying both a message and an error code
+fn api_error<T>(
+    status: StatusCode,
+    code: ErrorCode,
+    error: String,
+) -> (StatusCode, Json<ApiResponse<T>>) {
+    (
+        status,
+        Json(ApiResponse {
+            success: false,
+            data: None,
+            error: Some(error),
+            error_code: Some(code),
+        }),
+    )
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,6 +107,24 @@ struct InboxMessageInfo {
     session_name: String,
     message: String,
     created_at: String,
+    acknowledged_at: Option<String>,
+    ack_reply: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AckPayload {
+    #[serde(default)]
+    reply: Option<String>,
+}
+
+/// Event payload emitted when a session asks for the user's attention
+#[derive(Debug, Clone, Serialize)]
+struct NeedsAttentionEvent {
+    session_id: String,
+    session_name: String,
+    message: String,
+    /// Status before this message flipped the session to "ready"
+    previous_status: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -59,18 +140,61 @@ struct CommentInfo {
     session_id: String,
     file_path: String,
     line_number: Option<i32>,
+    end_line_number: Option<i32>,
     line_type: Option<String>,
     author: String,
     content: String,
     status: String,
+    severity: String,
     parent_id: Option<String>,
     created_at: String,
 }
 
+impl From<db::DiffComment> for CommentInfo {
+    fn from(c: db::DiffComment) -> Self {
+        CommentInfo {
+            id: c.id,
+            session_id: c.session_id,
+            file_path: c.file_path,
+            line_number: c.line_number,
+            end_line_number: c.end_line_number,
+            line_type: c.line_type,
+            author: c.author,
+            content: c.content,
+            status: c.status,
+            severity: c.severity,
+            parent_id: c.parent_id,
+            created_at: c.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Open comments on one file
+#[derive(Debug, Serialize)]
+struct FileComments {
+    file_path: String,
+    open_count: u32,
+    comments: Vec<CommentInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct GroupedComments {
+    files: Vec<FileComments>,
+}
+
 #[derive(Debug, Serialize)]
 struct CommentsResponse {
     success: bool,
     comments: Vec<CommentInfo>,
+    total: u32, // All open top-level comments, not just this page
+    error: Option<String>,
+    error_code: Option<ErrorCode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageParams {
+    limit: Option<u32>,
+    offset: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -94,24 +218,65 @@ async fn get_session(Path(id): Path<String>) -> (StatusCode, Json<ApiResponse<Se
                     status: session.status,
                 }),
                 error: None,
+                error_code: None,
             }),
         ),
-        Ok(None) => (
+        Ok(None) => api_error(
             StatusCode::NOT_FOUND,
-            Json(ApiResponse {
-                success: false,
-                data: None,
-                error: Some("Session not found".to_string()),
-            }),
+            ErrorCode::SessionNotFound,
+            "Session not found".to_string(),
         ),
-        Err(e) => (
+        Err(e) => api_error(
             StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::DbError,
+            e.to_string(),
+        ),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ShouldStopInfo {
+    should_stop: bool,
+}
+
+// GET /api/session/:id/should-stop - Poll whether the agent was asked to stop
+async fn should_stop_handler(
+    Path(id): Path<String>,
+) -> (StatusCode, Json<ApiResponse<ShouldStopInfo>>) {
+    (
+        StatusCode::OK,
+        Json(ApiResponse {
+            success: true,
+            data: Some(ShouldStopInfo {
+                should_stop: crate::claude_headless::should_stop(&id),
+            }),
+            error: None,
+            error_code: None,
+        }),
+    )
+}
+
+// POST /api/session/:id/heartbeat - Agent is still alive and working
+async fn heartbeat_handler(Path(id): Path<String>) -> (StatusCode, Json<ApiResponse<()>>) {
+    if let Err(response) = require_session(&id) {
+        return response;
+    }
+
+    match db::record_heartbeat(&id) {
+        Ok(_) => (
+            StatusCode::OK,
             Json(ApiResponse {
-                success: false,
-                data: None,
-                error: Some(e.to_string()),
+                success: true,
+                data: Some(()),
+                error: None,
+                error_code: None,
             }),
         ),
+        Err(e) => api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::DbError,
+            e.to_string(),
+        ),
     }
 }
 
@@ -121,67 +286,121 @@ async fn update_status(
     Json(payload): Json<StatusUpdate>,
 ) -> (StatusCode, Json<ApiResponse<()>>) {
     // Validate status
-    if payload.status != "ready" && payload.status != "busy" {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ApiResponse {
-                success: false,
-                data: None,
-                error: Some("Status must be 'ready' or 'busy'".to_string()),
-            }),
-        );
+    let status: db::SessionStatus = match payload.status.parse() {
+        Ok(status) => status,
+        Err(e) => {
+            return api_error(StatusCode::BAD_REQUEST, ErrorCode::InvalidStatus, e);
+        }
+    };
+
+    if let Err(response) = require_session(&id) {
+        return response;
     }
 
-    match db::update_session_status(&id, &payload.status) {
+    match db::update_session_status(&id, status) {
         Ok(_) => {
-            println!(
-                "[Server] Session {} status updated to: {}",
-                id, payload.status
-            );
+            info!("[Server] Session {} status updated to: {}", id, status);
             (
                 StatusCode::OK,
                 Json(ApiResponse {
                     success: true,
                     data: Some(()),
                     error: None,
+                    error_code: None,
                 }),
             )
         }
-        Err(e) => (
+        Err(e) => api_error(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None,
-                error: Some(e.to_string()),
-            }),
+            ErrorCode::DbError,
+            e.to_string(),
         ),
     }
 }
 
+#[derive(Debug, Serialize)]
+struct HealthInfo {
+    version: String,
+    db_ok: bool,
+    schema_version: Option<i64>, // Highest applied DB migration
+    uptime_secs: u64,
+    active_sessions: usize, // Running headless/agent Claude processes
+}
+
 // GET /api/health - Health check
-async fn health_check() -> (StatusCode, Json<ApiResponse<String>>) {
+async fn health_check(
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, Json<ApiResponse<HealthInfo>>) {
+    let db_ok =
+        db::with_db(|conn| conn.query_row("SELECT 1", [], |row| row.get::<_, i32>(0))).is_ok();
+    let schema_version = db::get_schema_version().ok();
+
     (
         StatusCode::OK,
         Json(ApiResponse {
             success: true,
-            data: Some("Claude Sessions API is running".to_string()),
+            data: Some(HealthInfo {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                db_ok,
+                schema_version,
+                uptime_secs: state.started_at.elapsed().as_secs(),
+                active_sessions: crate::claude_headless::running_session_count(),
+            }),
             error: None,
+            error_code: None,
         }),
     )
 }
 
 // POST /api/session/:id/message - Send message to inbox and set status to ready
 async fn send_message(
+    State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
     Json(payload): Json<MessagePayload>,
 ) -> (StatusCode, Json<ApiResponse<InboxMessageInfo>>) {
+    // Remember the status before the flip so consumers can filter transitions
+    let previous_status = match require_session(&id) {
+        Ok(session) => Some(session.status),
+        Err(response) => return response,
+    };
+
+    if !state.message_limiter.try_acquire(&id) {
+        info!("[Server] Session {} is rate limited", id);
+        return api_error(
+            StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::RateLimited,
+            format!(
+                "Session {} exceeded {} messages per {}s",
+                id,
+                state.message_limiter.capacity,
+                MESSAGE_RATE_WINDOW.as_secs()
+            ),
+        );
+    }
+
     // Create inbox message
     match db::create_inbox_message(&id, &payload.message) {
         Ok(msg) => {
             // Also update session status to ready
-            let _ = db::update_session_status(&id, "ready");
+            let _ = db::update_session_status(&id, db::SessionStatus::Ready);
+
+            info!("[Server] Session {} sent message: {}", id, payload.message);
 
-            println!("[Server] Session {} sent message: {}", id, payload.message);
+            // Emit a dedicated event so the frontend can fire a native notification
+            if let Some(app_handle) = &state.app_handle {
+                let event = NeedsAttentionEvent {
+                    session_id: msg.session_id.clone(),
+                    session_name: msg.session_name.clone(),
+                    message: msg.message.clone(),
+                    previous_status,
+                };
+                if let Err(e) = app_handle.emit("claude-needs-attention", &event) {
+                    warn!(
+                        "[Server] Failed to emit claude-needs-attention event: {}",
+                        e
+                    );
+                }
+            }
             (
                 StatusCode::OK,
                 Json(ApiResponse {
@@ -192,67 +411,174 @@ async fn send_message(
                         session_name: msg.session_name,
                         message: msg.message,
                         created_at: msg.created_at.to_rfc3339(),
+                        acknowledged_at: None,
+                        ack_reply: None,
                     }),
                     error: None,
+                    error_code: None,
                 }),
             )
         }
-        Err(e) => (
+        Err(e) => api_error(
             StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::DbError,
+            e.to_string(),
+        ),
+    }
+}
+
+// GET /api/session/:id/messages - Get this session's inbox messages with ack state
+async fn get_messages(
+    Path(id): Path<String>,
+) -> (StatusCode, Json<ApiResponse<Vec<InboxMessageInfo>>>) {
+    match db::get_inbox_messages_for_session(&id) {
+        Ok(messages) => (
+            StatusCode::OK,
             Json(ApiResponse {
-                success: false,
-                data: None,
-                error: Some(e.to_string()),
+                success: true,
+                data: Some(
+                    messages
+                        .into_iter()
+                        .map(|m| InboxMessageInfo {
+                            id: m.id,
+                            session_id: m.session_id,
+                            session_name: m.session_name,
+                            message: m.message,
+                            created_at: m.created_at.to_rfc3339(),
+                            acknowledged_at: m.acknowledged_at.map(|dt| dt.to_rfc3339()),
+                            ack_reply: m.ack_reply,
+                        })
+                        .collect(),
+                ),
+                error: None,
+                error_code: None,
             }),
         ),
+        Err(e) => api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::DbError,
+            e.to_string(),
+        ),
+    }
+}
+
+// POST /api/session/:id/messages/:message_id/ack - Acknowledge an inbox message
+async fn ack_message_handler(
+    Path((session_id, message_id)): Path<(String, String)>,
+    Json(payload): Json<AckPayload>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match db::acknowledge_message(&message_id, Some(&session_id), payload.reply.as_deref()) {
+        Ok(true) => {
+            info!("[Server] Message {} acknowledged", message_id);
+            (
+                StatusCode::OK,
+                Json(ApiResponse {
+                    success: true,
+                    data: Some(()),
+                    error: None,
+                    error_code: None,
+                }),
+            )
+        }
+        Ok(false) => api_error(
+            StatusCode::NOT_FOUND,
+            ErrorCode::MessageNotFound,
+            "Message not found".to_string(),
+        ),
+        Err(e) => api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::DbError,
+            e.to_string(),
+        ),
     }
 }
 
 // GET /api/session/:id/comments - Get open comments for session
-async fn get_comments(Path(id): Path<String>) -> (StatusCode, Json<CommentsResponse>) {
-    match db::get_open_comments_for_session(&id) {
-        Ok(comments) => {
-            let comment_infos: Vec<CommentInfo> = comments
-                .into_iter()
-                .map(|c| CommentInfo {
-                    id: c.id,
-                    session_id: c.session_id,
-                    file_path: c.file_path,
-                    line_number: c.line_number,
-                    line_type: c.line_type,
-                    author: c.author,
-                    content: c.content,
-                    status: c.status,
-                    parent_id: c.parent_id,
-                    created_at: c.created_at.to_rfc3339(),
-                })
-                .collect();
-            println!(
-                "[Server] Session {} has {} open comments",
+async fn get_comments(
+    Path(id): Path<String>,
+    Query(page): Query<PageParams>,
+) -> (StatusCode, Json<CommentsResponse>) {
+    let limit = page
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = page.offset.unwrap_or(0);
+
+    match db::get_open_comments_for_session_paged(&id, limit, offset) {
+        Ok((comments, total)) => {
+            let comment_infos: Vec<CommentInfo> =
+                comments.into_iter().map(CommentInfo::from).collect();
+            info!(
+                "[Server] Session {} has {} open comments (returning {} from offset {})",
                 id,
-                comment_infos.len()
+                total,
+                comment_infos.len(),
+                offset
             );
             (
                 StatusCode::OK,
                 Json(CommentsResponse {
                     success: true,
                     comments: comment_infos,
+                    total,
+                    error: None,
+                    error_code: None,
                 }),
             )
         }
         Err(e) => {
-            println!("[Server] Error getting comments for session {}: {}", id, e);
+            warn!("[Server] Error getting comments for session {}: {}", id, e);
             (
-                StatusCode::OK,
+                StatusCode::INTERNAL_SERVER_ERROR,
                 Json(CommentsResponse {
                     success: false,
                     comments: vec![],
+                    total: 0,
+                    error: Some(e.to_string()),
+                    error_code: Some(ErrorCode::DbError),
                 }),
             )
         }
     }
 }
 
+// GET /api/session/:id/comments/grouped - Open comments grouped by file
+async fn get_comments_grouped(
+    Path(id): Path<String>,
+) -> (StatusCode, Json<ApiResponse<GroupedComments>>) {
+    match db::get_open_comments_grouped_by_file(&id) {
+        Ok(groups) => {
+            let files: Vec<FileComments> = groups
+                .into_iter()
+                .map(|(file_path, comments)| FileComments {
+                    file_path,
+                    open_count: comments.len() as u32,
+                    comments: comments.into_iter().map(CommentInfo::from).collect(),
+                })
+                .collect();
+            info!(
+                "[Server] Session {} has open comments on {} files",
+                id,
+                files.len()
+            );
+            (
+                StatusCode::OK,
+                Json(ApiResponse {
+                    success: true,
+                    data: Some(GroupedComments { files }),
+                    error: None,
+                    error_code: None,
+                }),
+            )
+        }
+        Err(e) => api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::DbError,
+            e.to_string(),
+        ),
+    }
+}
+
 // POST /api/session/:id/comments/:comment_id/reply - Reply to a comment
 async fn reply_to_comment_handler(
     Path((session_id, comment_id)): Path<(String, String)>,
@@ -264,9 +590,14 @@ async fn reply_to_comment_handler(
         _ => session_id.clone(),
     };
 
-    match db::reply_to_comment(&comment_id, &author, &payload.message) {
+    match db::reply_to_comment(
+        &comment_id,
+        &author,
+        &payload.message,
+        db::CommentAuthorKind::Agent,
+    ) {
         Ok(comment) => {
-            println!(
+            info!(
                 "[Server] Reply added to comment {} by {}",
                 comment_id, author
             );
@@ -274,29 +605,21 @@ async fn reply_to_comment_handler(
                 StatusCode::OK,
                 Json(ApiResponse {
                     success: true,
-                    data: Some(CommentInfo {
-                        id: comment.id,
-                        session_id: comment.session_id,
-                        file_path: comment.file_path,
-                        line_number: comment.line_number,
-                        line_type: comment.line_type,
-                        author: comment.author,
-                        content: comment.content,
-                        status: comment.status,
-                        parent_id: comment.parent_id,
-                        created_at: comment.created_at.to_rfc3339(),
-                    }),
+                    data: Some(CommentInfo::from(comment)),
                     error: None,
+                    error_code: None,
                 }),
             )
         }
-        Err(e) => (
+        Err(rusqlite::Error::QueryReturnedNoRows) => api_error(
+            StatusCode::NOT_FOUND,
+            ErrorCode::CommentNotFound,
+            format!("Comment {} not found", comment_id),
+        ),
+        Err(e) => api_error(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None,
-                error: Some(e.to_string()),
-            }),
+            ErrorCode::DbError,
+            e.to_string(),
         ),
     }
 }
@@ -307,32 +630,120 @@ async fn resolve_comment_handler(
     Json(_payload): Json<ResolvePayload>,
 ) -> (StatusCode, Json<ApiResponse<()>>) {
     match db::resolve_comment(&comment_id) {
-        Ok(_) => {
-            println!("[Server] Comment {} resolved", comment_id);
+        Ok(false) => api_error(
+            StatusCode::NOT_FOUND,
+            ErrorCode::CommentNotFound,
+            format!("Comment {} not found", comment_id),
+        ),
+        Ok(true) => {
+            info!("[Server] Comment {} resolved", comment_id);
             (
                 StatusCode::OK,
                 Json(ApiResponse {
                     success: true,
                     data: Some(()),
                     error: None,
+                    error_code: None,
                 }),
             )
         }
-        Err(e) => (
+        Err(e) => api_error(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None,
-                error: Some(e.to_string()),
-            }),
+            ErrorCode::DbError,
+            e.to_string(),
+        ),
+    }
+}
+
+// POST /api/session/:id/comments/:comment_id/reopen - Reopen a resolved comment
+async fn reopen_comment_handler(
+    Path((_session_id, comment_id)): Path<(String, String)>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    match db::reopen_comment(&comment_id) {
+        Ok(false) => api_error(
+            StatusCode::NOT_FOUND,
+            ErrorCode::CommentNotFound,
+            format!("Comment {} not found", comment_id),
+        ),
+        Ok(true) => {
+            info!("[Server] Comment {} reopened", comment_id);
+            (
+                StatusCode::OK,
+                Json(ApiResponse {
+                    success: true,
+                    data: Some(()),
+                    error: None,
+                    error_code: None,
+                }),
+            )
+        }
+        Err(e) => api_error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::DbError,
+            e.to_string(),
         ),
     }
 }
 
 /// App state shared with axum handlers
-#[derive(Clone)]
 struct AppState {
     app_handle: Option<tauri::AppHandle>,
+    started_at: Instant,
+    message_limiter: RateLimiter,
+}
+
+/// Per-key token buckets: each key may burst up to `capacity` requests,
+/// refilling at `capacity` per window. Kept in memory only.
+struct RateLimiter {
+    capacity: u32,
+    refill_per_sec: f64,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(capacity: u32, window: Duration) -> Self {
+        RateLimiter {
+            capacity,
+            refill_per_sec: capacity as f64 / window.as_secs_f64(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take one token for `key`; false means the request should be rejected
+    fn try_acquire(&self, key: &str) -> bool {
+        let now = Instant::now();
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        // A bucket that has refilled completely acts like a new one, so idle
+        // keys don't need to be kept
+        let capacity = self.capacity as f64;
+        buckets.retain(|_, bucket| {
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+            bucket.tokens + elapsed * self.refill_per_sec < capacity
+        });
+        let bucket = buckets.entry(key.to_string()).or_insert(TokenBucket {
+            tokens: self.capacity as f64,
+            last_refill: now,
+        });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity as f64);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
 }
 
 // POST /api/session/:id/permission-request - Request permission for a tool
@@ -347,7 +758,7 @@ async fn permission_request_handler(
 
     // Check if tool is always-allowed for this session
     if permissions::is_always_allowed(&session_id, &request.tool_name) {
-        println!(
+        info!(
             "[Server] Tool {} auto-allowed for session {}",
             request.tool_name, session_id
         );
@@ -363,11 +774,12 @@ async fn permission_request_handler(
                     always_allow: Some(true),
                 }),
                 error: None,
+                error_code: None,
             }),
         );
     }
 
-    println!(
+    info!(
         "[Server] Permission request for tool {} in session {}",
         request.tool_name, session_id
     );
@@ -380,18 +792,15 @@ async fn permission_request_handler(
     // Emit event to frontend
     if let Some(app_handle) = &state.app_handle {
         if let Err(e) = app_handle.emit("permission-request", &request) {
-            println!("[Server] Failed to emit permission-request event: {}", e);
-            return (
+            warn!("[Server] Failed to emit permission-request event: {}", e);
+            return api_error(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Failed to emit event: {}", e)),
-                }),
+                ErrorCode::EventEmitFailed,
+                format!("Failed to emit event: {}", e),
             );
         }
     } else {
-        println!("[Server] No app handle available to emit events");
+        warn!("[Server] No app handle available to emit events");
         // In development/testing, auto-allow if no UI available
         return (
             StatusCode::OK,
@@ -405,6 +814,7 @@ async fn permission_request_handler(
                     always_allow: None,
                 }),
                 error: None,
+                error_code: None,
             }),
         );
     }
@@ -428,21 +838,23 @@ async fn permission_request_handler(
                 // Get the tool name from the request we just processed
                 // We need to look it up before it's removed
                 let tool_name = {
-                    let pending = permissions::PENDING_PERMISSIONS.lock().unwrap();
+                    let pending = permissions::PENDING_PERMISSIONS
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner);
                     pending
                         .get(&request_id)
                         .map(|p| p.request.tool_name.clone())
                 };
                 if let Some(tool_name) = tool_name {
                     permissions::set_always_allowed(&session_id, &tool_name);
-                    println!(
+                    info!(
                         "[Server] Tool {} now always-allowed for session {}",
                         tool_name, session_id
                     );
                 }
             }
 
-            println!(
+            info!(
                 "[Server] Permission response for {}: {:?}",
                 request_id, response.behavior
             );
@@ -452,32 +864,27 @@ async fn permission_request_handler(
                     success: true,
                     data: Some(response),
                     error: None,
+                    error_code: None,
                 }),
             )
         }
         Ok(Err(_)) => {
             // Channel closed (request was cancelled)
             permissions::take_pending(&request_id);
-            (
+            api_error(
                 StatusCode::GONE,
-                Json(ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some("Permission request was cancelled".to_string()),
-                }),
+                ErrorCode::PermissionCancelled,
+                "Permission request was cancelled".to_string(),
             )
         }
         Err(_) => {
             // Timeout
             permissions::take_pending(&request_id);
-            println!("[Server] Permission request {} timed out", request_id);
-            (
+            info!("[Server] Permission request {} timed out", request_id);
+            api_error(
                 StatusCode::REQUEST_TIMEOUT,
-                Json(ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some("Permission request timed out".to_string()),
-                }),
+                ErrorCode::PermissionTimeout,
+                "Permission request timed out".to_string(),
             )
         }
     }
@@ -498,14 +905,36 @@ async fn start_server_internal(app_handle: Option<tauri::AppHandle>) {
         .allow_methods(Any)
         .allow_headers(Any);
 
-    let state = Arc::new(AppState { app_handle });
+    let message_rate_limit = app_handle
+        .as_ref()
+        .and_then(|app| crate::get_u64_setting(app, MESSAGE_RATE_LIMIT_KEY))
+        .and_then(|limit| u32::try_from(limit).ok())
+        .filter(|limit| *limit > 0)
+        .unwrap_or(DEFAULT_MESSAGE_RATE_LIMIT);
+
+    let state = Arc::new(AppState {
+        app_handle,
+        started_at: Instant::now(),
+        message_limiter: RateLimiter::new(message_rate_limit, MESSAGE_RATE_WINDOW),
+    });
 
     let app = Router::new()
         .route("/api/health", get(health_check))
         .route("/api/session/:id", get(get_session))
         .route("/api/session/:id/status", post(update_status))
+        .route("/api/session/:id/should-stop", get(should_stop_handler))
+        .route("/api/session/:id/heartbeat", post(heartbeat_handler))
         .route("/api/session/:id/message", post(send_message))
+        .route("/api/session/:id/messages", get(get_messages))
+        .route(
+            "/api/session/:id/messages/:message_id/ack",
+            post(ack_message_handler),
+        )
         .route("/api/session/:id/comments", get(get_comments))
+        .route(
+            "/api/session/:id/comments/grouped",
+            get(get_comments_grouped),
+        )
         .route(
             "/api/session/:id/comments/:comment_id/reply",
             post(reply_to_comment_handler),
@@ -514,15 +943,21 @@ async fn start_server_internal(app_handle: Option<tauri::AppHandle>) {
             "/api/session/:id/comments/:comment_id/resolve",
             post(resolve_comment_handler),
         )
+        .route(
+            "/api/session/:id/comments/:comment_id/reopen",
+            post(reopen_comment_handler),
+        )
         .route(
             "/api/session/:id/permission-request",
             post(permission_request_handler),
         )
         .with_state(state)
-        .layer(cors);
+        .layer(cors)
+        // gzip/deflate when the client sends Accept-Encoding
+        .layer(CompressionLayer::new());
 
     let addr = SocketAddr::from(([127, 0, 0, 1], SERVER_PORT));
-    println!("[Server] Starting HTTP server on http://{}", addr);
+    info!("[Server] Starting HTTP server on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
@@ -26,6 +26,8 @@ interface HeadlessChatProps {
 // Types for Tauri events
 interface ClaudeEvent {
   session_id: string;
+  turn_index: number; // Messages of one turn share this; increments after each result
+  seq: number | null; // Transcript position; a replay's last_seq covers events at or below it
   message: ClaudeMessagePayload;
 }
 
@@ -128,9 +128,14 @@ export function PermissionDialog() {
       console.log("[PermissionDialog] Received request:", event.payload);
       setRequest(event.payload);
     });
+    // The run exited before we answered; drop its stale prompt
+    const unlistenCancelled = listen<{ request_id: string }>("permission-cancelled", (event) => {
+      setRequest((current) => (current?.request_id === event.payload.request_id ? null : current));
+    });
 
     return () => {
       unlisten.then((fn) => fn());
+      unlistenCancelled.then((fn) => fn());
     };
   }, []);
 
@@ -3,7 +3,7 @@ import { useInboxStore, isManuallyUnread, isNaturallyUnread, InboxMessage } from
 
 // Mock the API module
 vi.mock('../api', () => ({
-  getInboxMessages: vi.fn(() => Promise.resolve([])),
+  getInboxMessages: vi.fn(() => Promise.resolve({ messages: [], total: 0 })),
   markInboxMessageRead: vi.fn(() => Promise.resolve()),
   markInboxMessageUnread: vi.fn(() => Promise.resolve()),
   markSessionMessagesRead: vi.fn(() => Promise.resolve()),
@@ -19,8 +19,10 @@ export interface SessionData {
   cwd: string;
   workspace_id: string | null;
   worktree_name: string | null;
-  status: string; // "ready" or "busy"
+  status: string; // "ready", "busy", "waiting" or "error"
   base_commit: string | null; // Git commit SHA to diff against (stable reference)
+  tags: string[];
+  last_error: string | null; // Set while status is "error"
 }
 
 // Workspace API
@@ -28,6 +30,10 @@ export async function getWorkspaces(): Promise<WorkspaceData[]> {
   return invoke<WorkspaceData[]>("get_workspaces");
 }
 
+export async function getWorkspace(id: string): Promise<WorkspaceData | null> {
+  return invoke<WorkspaceData | null>("get_workspace", { id });
+}
+
 export async function createWorkspace(
   name: string,
   folder: string,
@@ -46,6 +52,30 @@ export async function deleteWorkspace(id: string): Promise<void> {
   return invoke<void>("delete_workspace", { id });
 }
 
+// Panel layout per workspace; the backend stores it as an opaque JSON blob
+export async function saveWorkspaceUiState(workspaceId: string, state: unknown): Promise<void> {
+  return invoke<void>("save_workspace_ui_state", {
+    workspaceId,
+    stateJson: JSON.stringify(state),
+  });
+}
+
+// Resolves to null when nothing is saved, so callers fall back to the default layout
+export async function getWorkspaceUiState<T = unknown>(workspaceId: string): Promise<T | null> {
+  const json = await invoke<string | null>("get_workspace_ui_state", { workspaceId });
+  return json === null ? null : (JSON.parse(json) as T);
+}
+
+export interface WorkspaceStats {
+  session_count: number;
+  open_comment_count: number; // Root comments only
+  unread_message_count: number;
+}
+
+export async function getWorkspaceStats(workspaceId: string): Promise<WorkspaceStats> {
+  return invoke<WorkspaceStats>("get_workspace_stats", { workspaceId });
+}
+
 // Session API
 export async function getSessions(): Promise<SessionData[]> {
   return invoke<SessionData[]>("get_sessions");
@@ -56,7 +86,8 @@ export async function createSession(
   cwd: string,
   workspaceId: string | null,
   worktreeName: string | null,
-  baseCommit: string | null = null
+  baseCommit: string | null = null,
+  baseRef: string | null = null // Resolved to a SHA at creation time; overrides baseCommit
 ): Promise<SessionData> {
   return invoke<SessionData>("create_session", {
     name,
@@ -64,9 +95,159 @@ export async function createSession(
     workspaceId,
     worktreeName,
     baseCommit,
+    baseRef,
   });
 }
 
+export interface SessionSpec {
+  name: string;
+  worktree_name: string | null;
+  base_ref: string | null; // Resolved to a SHA at creation time
+}
+
+// Create several workspace sessions at once; all are created or none
+export async function createSessionsBatch(workspaceId: string, specs: SessionSpec[]): Promise<SessionData[]> {
+  return invoke<SessionData[]>("create_sessions_batch", { workspaceId, specs });
+}
+
+// Ids of sessions whose cwd/worktree no longer exists on disk
+export async function findStaleSessions(): Promise<string[]> {
+  return invoke<string[]>("find_stale_sessions");
+}
+
+// Folders of recently created sessions, most recent first (default 10)
+export async function getRecentFolders(limit: number | null = null): Promise<string[]> {
+  return invoke<string[]>("get_recent_folders", { limit });
+}
+
+// Prompt templates for new runs. Bodies are stored raw; fill these tokens
+// before sending:
+//   {{cwd}}    - the session's working directory
+//   {{branch}} - the session's current branch
+export interface SessionTemplate {
+  name: string;
+  body: string;
+  updated_at: string;
+}
+
+export async function saveTemplate(name: string, body: string): Promise<void> {
+  return invoke<void>("save_template", { name, body });
+}
+
+// All templates, by name
+export async function getTemplates(): Promise<SessionTemplate[]> {
+  return invoke<SessionTemplate[]>("get_templates");
+}
+
+export async function deleteTemplate(name: string): Promise<void> {
+  return invoke<void>("delete_template", { name });
+}
+
+// How a queued run is launched; fields mirror start_claude_headless / start_claude_agent
+export type RunOptions =
+  | {
+      launcher: "cli";
+      resume_id?: string;
+      env?: Record<string, string>;
+      clean_env?: boolean;
+      permission_prompt?: boolean;
+    }
+  | { launcher: "agent"; resume_id?: string; permission_mode?: string };
+
+export interface PendingRun {
+  id: string;
+  session_id: string;
+  prompt: string;
+  cwd: string;
+  options_json: string;
+  queued_at: string;
+}
+
+// Starts the run now if under the max_concurrent_runs setting, otherwise
+// queues it. Resolves to the queue entry, or null if it started.
+export async function queueClaudeRun(
+  sessionId: string,
+  prompt: string,
+  cwd: string,
+  options: RunOptions
+): Promise<PendingRun | null> {
+  return invoke<PendingRun | null>("queue_claude_run", { sessionId, prompt, cwd, options });
+}
+
+export async function getQueuedRuns(): Promise<PendingRun[]> {
+  return invoke<PendingRun[]>("get_queued_runs");
+}
+
+export async function cancelQueuedRun(id: string): Promise<void> {
+  return invoke<void>("cancel_queued_run", { id });
+}
+
+// Runs queued before the app was restarted are held (and announced with a
+// "claude-queue-restored" event carrying PendingRun[]) until this is called
+export async function resumeQueuedRuns(): Promise<void> {
+  return invoke<void>("resume_queued_runs");
+}
+
+// Approximate token count (chars / 4) for showing "~3,200 tokens"
+export async function estimateTokens(text: string): Promise<number> {
+  return invoke<number>("estimate_tokens", { text });
+}
+
+// Directory containing sessions.db
+export async function getAppDataDir(): Promise<string> {
+  return invoke<string>("get_app_data_dir");
+}
+
+export type LogLevel = "off" | "error" | "warn" | "info" | "debug" | "trace";
+
+// Today's backend log file; rejects if file logging couldn't be set up
+export async function getLogPath(): Promise<string> {
+  return invoke<string>("get_log_path");
+}
+
+// Persisted, so it also applies on the next launch (unless RUST_LOG is set)
+export async function setLogLevel(level: LogLevel): Promise<void> {
+  return invoke<void>("set_log_level", { level });
+}
+
+// Open the app data directory in the OS file manager
+export async function revealAppDataDir(): Promise<void> {
+  return invoke<void>("reveal_app_data_dir");
+}
+
+export interface TableImportCounts {
+  imported: number;
+  skipped: number; // Already present by id
+}
+
+export interface ImportStats {
+  workspaces: TableImportCounts;
+  sessions: TableImportCounts;
+  inbox_messages: TableImportCounts;
+  comments: TableImportCounts;
+}
+
+// Merge an exported sessions.db; existing ids are skipped, not overwritten
+export async function importDatabase(path: string): Promise<ImportStats> {
+  return invoke<ImportStats>("import_database", { path });
+}
+
+export async function addSessionTag(sessionId: string, tag: string): Promise<void> {
+  return invoke<void>("add_session_tag", { sessionId, tag });
+}
+
+export async function removeSessionTag(sessionId: string, tag: string): Promise<void> {
+  return invoke<void>("remove_session_tag", { sessionId, tag });
+}
+
+export async function getSessionTags(sessionId: string): Promise<string[]> {
+  return invoke<string[]>("get_session_tags", { sessionId });
+}
+
+export async function getSessionsByTag(tag: string): Promise<SessionData[]> {
+  return invoke<SessionData[]>("get_sessions_by_tag", { tag });
+}
+
 export async function deleteSession(id: string): Promise<void> {
   return invoke<void>("delete_session", { id });
 }
@@ -87,9 +268,80 @@ export async function setSessionStatus(id: string, status: string): Promise<void
   return invoke<void>("set_session_status", { id, status });
 }
 
+export interface StalledSessionData {
+  id: string;
+  name: string;
+  last_heartbeat_at: string;
+}
+
+// Busy sessions with no agent heartbeat in thresholdSecs (e.g. 300 for "no activity for 5 minutes")
+export async function getStalledSessions(thresholdSecs: number): Promise<StalledSessionData[]> {
+  return invoke<StalledSessionData[]>("get_stalled_sessions", { thresholdSecs });
+}
+
+// Put a session into "error" with a reason shown in the UI
+export async function setSessionError(id: string, message: string): Promise<void> {
+  return invoke<void>("set_session_error", { id, message });
+}
+
+// Messages persisted from headless runs, in stream order across all runs
+export interface RunMessageData {
+  id: string;
+  session_id: string;
+  seq: number;
+  role: "assistant" | "user" | "result";
+  content: unknown; // The stream-json message, as in the claude-message event
+  created_at: string;
+}
+
+export async function getRunMessages(sessionId: string): Promise<RunMessageData[]> {
+  return invoke<RunMessageData[]>("get_run_messages", { sessionId });
+}
+
+export interface SessionCost {
+  session_id: string;
+  session_name: string | null; // null once the session is deleted
+  cost_usd: number;
+  duration_ms: number;
+  runs: number;
+}
+
+export interface CostSummary {
+  total_cost_usd: number;
+  total_duration_ms: number;
+  sessions: SessionCost[]; // Most expensive first
+}
+
+// Cost across all sessions between two RFC3339 timestamps (to is exclusive)
+export async function getCostSummary(from: string, to: string): Promise<CostSummary> {
+  return invoke<CostSummary>("get_cost_summary", { from, to });
+}
+
 // Configure a worktree with MCP settings for Claude Code
-export async function configureWorktree(worktreePath: string, sessionId: string): Promise<void> {
-  return invoke<void>("configure_worktree", { worktreePath, sessionId });
+export interface WorktreeConfigPreview {
+  mcp_json: string | null; // null = file left untouched
+  settings_local_json: string | null;
+}
+
+export async function configureWorktree(
+  worktreePath: string,
+  sessionId: string,
+  dryRun: boolean = false
+): Promise<WorktreeConfigPreview> {
+  return invoke<WorktreeConfigPreview>("configure_worktree", { worktreePath, sessionId, dryRun });
+}
+
+export interface WorktreeConfigStatus {
+  config_required: boolean; // false while tools come from the agent SDK
+  mcp_json_present: boolean; // Exists and is valid JSON
+  server_entry_present: boolean;
+  bridge_script_exists: boolean;
+  settings_local_present: boolean;
+  permissions_present: boolean;
+}
+
+export async function verifyWorktreeConfig(worktreePath: string): Promise<WorktreeConfigStatus> {
+  return invoke<WorktreeConfigStatus>("verify_worktree_config", { worktreePath });
 }
 
 // Inbox Message API
@@ -101,10 +353,38 @@ export interface InboxMessageData {
   created_at: string;
   read_at: string | null;
   first_read_at: string | null;  // Set once when first read, never cleared
+  acknowledged: boolean;
+  acknowledged_at: string | null;
+  ack_reply: string | null; // Optional canned reply the agent can poll
+  starred: boolean; // Starred messages are kept by clearInbox
+}
+
+export interface InboxMessagePage {
+  messages: InboxMessageData[];
+  total: number; // Messages matching the filters, across all pages
+}
+
+// With no options, returns every message (newest first)
+export async function getInboxMessages(options?: {
+  limit?: number;
+  offset?: number;
+  unreadOnly?: boolean;
+  sessionId?: string;
+}): Promise<InboxMessagePage> {
+  return invoke<InboxMessagePage>("get_inbox_messages", { ...options });
+}
+
+export async function getStarredMessages(): Promise<InboxMessageData[]> {
+  return invoke<InboxMessageData[]>("get_starred_messages");
+}
+
+// All inbox messages serialized for saving to a file
+export async function exportInbox(format: "json" | "csv"): Promise<string> {
+  return invoke<string>("export_inbox", { format });
 }
 
-export async function getInboxMessages(): Promise<InboxMessageData[]> {
-  return invoke<InboxMessageData[]>("get_inbox_messages");
+export async function setInboxMessageStarred(id: string, starred: boolean): Promise<void> {
+  return invoke<void>("set_inbox_message_starred", { id, starred });
 }
 
 export async function markInboxMessageRead(id: string): Promise<void> {
@@ -119,6 +399,19 @@ export async function markSessionMessagesRead(sessionId: string): Promise<number
   return invoke<number>("mark_session_messages_read", { sessionId });
 }
 
+export async function markSessionMessagesUnread(sessionId: string): Promise<number> {
+  return invoke<number>("mark_session_messages_unread", { sessionId });
+}
+
+// Oldest unread message in the session, or null if everything is read
+export async function getFirstUnreadMessage(sessionId: string): Promise<InboxMessageData | null> {
+  return invoke<InboxMessageData | null>("get_first_unread_message", { sessionId });
+}
+
+export async function acknowledgeInboxMessage(id: string, reply: string | null = null): Promise<void> {
+  return invoke<void>("acknowledge_inbox_message", { id, reply });
+}
+
 export async function deleteInboxMessage(id: string): Promise<void> {
   return invoke<void>("delete_inbox_message", { id });
 }
@@ -127,12 +420,28 @@ export async function clearInbox(): Promise<void> {
   return invoke<void>("clear_inbox");
 }
 
+// Maintenance: delete messages/comments whose session no longer exists
+export interface PruneOrphansData {
+  messages: number;
+  comments: number;
+}
+
+export async function pruneOrphans(): Promise<PruneOrphansData> {
+  return invoke<PruneOrphansData>("prune_orphans");
+}
+
 // Git Diff API
+export interface DiffSegment {
+  kind: "context" | "add" | "delete";
+  text: string;
+}
+
 export interface DiffLine {
   line_type: "context" | "add" | "delete";
   old_line: number | null;
   new_line: number | null;
   content: string;
+  segments: DiffSegment[]; // Word-level spans; empty unless wordDiff was requested
 }
 
 export interface DiffHunk {
@@ -151,6 +460,13 @@ export interface FileDiff {
   insertions: number;
   deletions: number;
   hunks: DiffHunk[];
+  mode_change: [string, string] | null; // [old, new] file mode, e.g. ["100644", "100755"]
+  is_symlink: boolean; // The single hunk line is the link target
+  language: string | null; // Syntax highlighting hint from the file name/extension
+  has_conflict_markers: boolean; // Unresolved <<<<<<< / ======= / >>>>>>> blocks
+  content_hash: string; // SHA-256 of the raw diff; "" in summaries (no hunks)
+  generated: boolean; // Lockfile, bundle, snapshot or linguist-generated; collapse by default
+  large: boolean; // Over 1000 changed lines
 }
 
 export interface DiffSummary {
@@ -164,8 +480,234 @@ export async function getDiffSummary(worktreePath: string, baseBranch: string):
   return invoke<DiffSummary>("get_diff_summary", { worktreePath, baseBranch });
 }
 
-export async function getFileDiff(worktreePath: string, filePath: string, baseBranch: string): Promise<FileDiff> {
-  return invoke<FileDiff>("get_file_diff", { worktreePath, filePath, baseBranch });
+// null uses git's default (myers)
+export type DiffAlgorithm = "myers" | "minimal" | "patience" | "histogram";
+
+export async function getFileDiff(
+  worktreePath: string,
+  filePath: string,
+  baseBranch: string,
+  wordDiff: boolean = false,
+  diffAlgorithm: DiffAlgorithm | null = null
+): Promise<FileDiff> {
+  return invoke<FileDiff>("get_file_diff", { worktreePath, filePath, baseBranch, wordDiff, diffAlgorithm });
+}
+
+// Compare with a cached FileDiff's content_hash to skip re-fetching unchanged diffs
+export async function getFileDiffHash(
+  worktreePath: string,
+  filePath: string,
+  baseBranch: string,
+  diffAlgorithm: DiffAlgorithm | null = null
+): Promise<string> {
+  return invoke<string>("get_file_diff_hash", { worktreePath, filePath, baseBranch, diffAlgorithm });
+}
+
+// Side-by-side layout: context rows fill both sides, null marks a blank cell
+export interface SplitRow {
+  left: DiffLine | null;
+  right: DiffLine | null;
+}
+
+export interface SplitHunk {
+  header: string;
+  rows: SplitRow[];
+}
+
+export interface SplitDiff {
+  path: string;
+  old_path: string | null;
+  status: FileDiff["status"];
+  hunks: SplitHunk[];
+}
+
+export async function getFileDiffSplit(
+  worktreePath: string,
+  filePath: string,
+  baseBranch: string,
+  wordDiff: boolean = false,
+  diffAlgorithm: DiffAlgorithm | null = null
+): Promise<SplitDiff> {
+  return invoke<SplitDiff>("get_file_diff_split", { worktreePath, filePath, baseBranch, wordDiff, diffAlgorithm });
+}
+
+export interface FileDiffHunk {
+  hunk: DiffHunk;
+  hunk_index: number;
+  total_hunks: number;
+}
+
+// One hunk of a file's diff (0-based), for lazy-loading large files
+export async function getFileDiffHunk(
+  worktreePath: string,
+  filePath: string,
+  baseBranch: string,
+  hunkIndex: number,
+  diffAlgorithm: DiffAlgorithm | null = null
+): Promise<FileDiffHunk> {
+  return invoke<FileDiffHunk>("get_file_diff_hunk", {
+    worktreePath,
+    filePath,
+    baseBranch,
+    hunkIndex,
+    diffAlgorithm,
+  });
+}
+
+// Diffs between two commits/refs (from..to), ignoring the worktree
+export async function getDiffBetween(
+  worktreePath: string,
+  fromRef: string,
+  toRef: string
+): Promise<DiffSummary> {
+  return invoke<DiffSummary>("get_diff_between", { worktreePath, fromRef, toRef });
+}
+
+export async function getFileDiffBetween(
+  worktreePath: string,
+  fromRef: string,
+  toRef: string,
+  filePath: string,
+  oldPath: string | null = null
+): Promise<FileDiff> {
+  return invoke<FileDiff>("get_file_diff_between", { worktreePath, fromRef, toRef, filePath, oldPath });
+}
+
+// Only what's staged in the index (git diff --cached)
+export async function getStagedDiffSummary(worktreePath: string): Promise<DiffSummary> {
+  return invoke<DiffSummary>("get_staged_diff_summary", { worktreePath });
+}
+
+export async function getStagedFileDiff(
+  worktreePath: string,
+  filePath: string,
+  oldPath: string | null = null
+): Promise<FileDiff> {
+  return invoke<FileDiff>("get_staged_file_diff", { worktreePath, filePath, oldPath });
+}
+
+// Cheap line counts for one file; use to warn before getFileDiff on huge files
+export interface FileDiffStats {
+  insertions: number;
+  deletions: number;
+}
+
+export async function getFileDiffStats(
+  worktreePath: string,
+  filePath: string,
+  baseBranch: string
+): Promise<FileDiffStats> {
+  return invoke<FileDiffStats>("get_file_diff_stats", { worktreePath, filePath, baseBranch });
+}
+
+export interface FileVersions {
+  old_content: string | null; // null if the file is new (or binary)
+  new_content: string | null; // null if the file was deleted (or binary)
+  is_binary: boolean;
+}
+
+// Whole file at the base and in the worktree, for a merge view
+export async function getFileVersions(
+  worktreePath: string,
+  filePath: string,
+  baseBranch: string
+): Promise<FileVersions> {
+  return invoke<FileVersions>("get_file_versions", { worktreePath, filePath, baseBranch });
+}
+
+export interface FileClassification {
+  path: string;
+  generated: boolean;
+  large: boolean; // Over 1000 lines changed against the base (HEAD by default)
+}
+
+export async function classifyFiles(
+  worktreePath: string,
+  files: string[],
+  baseBranch: string | null = null
+): Promise<FileClassification[]> {
+  return invoke<FileClassification[]>("classify_files", { worktreePath, files, baseBranch });
+}
+
+export interface BlameLine {
+  line_number: number; // In the file at the blamed ref
+  commit_sha: string;
+  author: string;
+  date: string; // RFC3339 author time
+  summary: string;
+}
+
+// Rejects binary files and files over 1 MB
+export async function blameFile(
+  worktreePath: string,
+  filePath: string,
+  refName: string,
+  opId: string | null = null
+): Promise<BlameLine[]> {
+  return invoke<BlameLine[]>("blame_file", { worktreePath, filePath, refName, opId });
+}
+
+// All changed files with hunks in one call; rejects if more than maxFiles changed
+export async function getFullDiff(
+  worktreePath: string,
+  baseBranch: string,
+  maxFiles: number | null = null,
+  diffAlgorithm: DiffAlgorithm | null = null,
+  opId: string | null = null
+): Promise<FileDiff[]> {
+  return invoke<FileDiff[]>("get_full_diff", { worktreePath, baseBranch, maxFiles, diffAlgorithm, opId });
+}
+
+// Raw patch text (applicable with `git apply`), including untracked and binary files
+export async function getSessionPatch(sessionId: string): Promise<string> {
+  return invoke<string>("get_session_patch", { sessionId });
+}
+
+export interface ComparedFileDelta {
+  path: string;
+  net_lines: number;
+}
+
+export interface ComparedCommonFile {
+  path: string;
+  a_net_lines: number;
+  b_net_lines: number;
+}
+
+export interface SessionComparison {
+  common_files: ComparedCommonFile[];
+  a_only: ComparedFileDelta[];
+  b_only: ComparedFileDelta[];
+}
+
+// Which files two sessions changed (each against its own base), with net line deltas
+export async function compareSessions(sessionA: string, sessionB: string): Promise<SessionComparison> {
+  return invoke<SessionComparison>("compare_sessions", { sessionA, sessionB });
+}
+
+export async function isGitRepo(path: string): Promise<boolean> {
+  return invoke<boolean>("is_git_repo", { path });
+}
+
+export async function getRepoRoot(path: string): Promise<string> {
+  return invoke<string>("get_repo_root", { path });
+}
+
+// Read-only git escape hatch, e.g. ["log", "--graph", "--oneline"]. Only
+// log/show/diff/status/rev-parse/branch/remote are accepted.
+export async function runGitReadonly(worktreePath: string, args: string[]): Promise<string> {
+  return invoke<string>("run_git_readonly", { worktreePath, args });
+}
+
+// Branch names for the origin branch picker, default branch first.
+// Remote branches on origin are listed without the "origin/" prefix.
+export async function listBranches(repoPath: string, includeRemote: boolean = false): Promise<string[]> {
+  return invoke<string[]>("list_branches", { repoPath, includeRemote });
+}
+
+// origin/HEAD's branch, else the first of main/master/trunk that exists
+export async function detectDefaultBranch(repoPath: string): Promise<string> {
+  return invoke<string>("detect_default_branch", { repoPath });
 }
 
 export async function getCurrentBranch(worktreePath: string): Promise<string> {
@@ -176,12 +718,65 @@ export async function getCommitSha(worktreePath: string, refName: string): Promi
   return invoke<string>("get_commit_sha", { worktreePath, refName });
 }
 
+export async function getNewCommits(worktreePath: string, baseBranch: string): Promise<string[]> {
+  return invoke<string[]>("get_new_commits", { worktreePath, baseBranch });
+}
+
 export async function updateSessionBaseCommit(id: string, baseCommit: string): Promise<void> {
   return invoke<void>("update_session_base_commit", { id, baseCommit });
 }
 
-export async function fetchOrigin(worktreePath: string): Promise<void> {
-  return invoke<void>("fetch_origin", { worktreePath });
+export interface BaseCommitEntryData {
+  base_commit: string;
+  set_at: string;
+}
+
+// Every base the session was diffed against, oldest first
+export async function getBaseCommitHistory(sessionId: string): Promise<BaseCommitEntryData[]> {
+  return invoke<BaseCommitEntryData[]>("get_base_commit_history", { sessionId });
+}
+
+// Re-activate a base commit from the history (recorded as a new entry)
+export async function restoreBaseCommit(sessionId: string, baseCommit: string): Promise<void> {
+  return invoke<void>("restore_base_commit", { sessionId, baseCommit });
+}
+
+export async function fetchOrigin(worktreePath: string, opId: string | null = null): Promise<void> {
+  return invoke<void>("fetch_origin", { worktreePath, opId });
+}
+
+// Kill a fetchOrigin / blameFile / getFullDiff call started with this opId
+// (e.g. crypto.randomUUID()); that call then rejects with a "cancelled" error
+export async function cancelGitOperation(opId: string): Promise<void> {
+  return invoke<void>("cancel_git_operation", { opId });
+}
+
+export interface RebaseOutcome {
+  success: boolean;
+  conflicts: string[]; // Non-empty means the rebase is paused; resolve or abortRebase
+  output: string;
+}
+
+export async function rebaseOntoBase(worktreePath: string, baseBranch: string): Promise<RebaseOutcome> {
+  return invoke<RebaseOutcome>("rebase_onto_base", { worktreePath, baseBranch });
+}
+
+export async function abortRebase(worktreePath: string): Promise<void> {
+  return invoke<void>("abort_rebase", { worktreePath });
+}
+
+// Files with unresolved merge conflicts (git diff --diff-filter=U)
+export async function getConflicts(worktreePath: string): Promise<string[]> {
+  return invoke<string[]>("get_conflicts", { worktreePath });
+}
+
+// Opens in the editor configured under "editor_command" (default: code --goto {file}:{line})
+export async function openInEditor(
+  worktreePath: string,
+  filePath: string,
+  lineNumber: number | null = null
+): Promise<void> {
+  return invoke<void>("open_in_editor", { worktreePath, filePath, lineNumber });
 }
 
 // Comment API
@@ -190,15 +785,31 @@ export interface DiffCommentData {
   session_id: string;
   file_path: string;
   line_number: number | null;
+  end_line_number: number | null; // Last line of a multi-line selection
   line_type: string | null;
   author: string;
   content: string;
   status: "open" | "resolved";
+  severity: CommentSeverity;
   parent_id: string | null;
   created_at: string;
   updated_at: string;
 }
 
+// Review intent of a comment; only open "blocker" root comments hold back approval
+export type CommentSeverity = "blocker" | "suggestion" | "question" | "praise";
+
+// Content around a commented line, used to re-locate it when the diff changes
+export interface CommentAnchor {
+  content: string;
+  before: string[]; // Lines directly above, top to bottom
+  after: string[]; // Lines directly below, top to bottom
+}
+
+export interface ReanchoredCommentData extends DiffCommentData {
+  outdated: boolean; // Commented line no longer exists in the current diff
+}
+
 export async function createComment(
   sessionId: string,
   filePath: string,
@@ -206,7 +817,10 @@ export async function createComment(
   lineType: string | null,
   author: string,
   content: string,
-  parentId: string | null = null
+  parentId: string | null = null,
+  anchor: CommentAnchor | null = null,
+  endLineNumber: number | null = null,
+  severity: CommentSeverity = "suggestion"
 ): Promise<DiffCommentData> {
   return invoke<DiffCommentData>("create_comment", {
     sessionId,
@@ -216,6 +830,52 @@ export async function createComment(
     author,
     content,
     parentId,
+    anchor,
+    endLineNumber,
+    severity,
+  });
+}
+
+// Display name for comments the user writes (defaults to "user")
+export async function getCommentAuthor(): Promise<string> {
+  return invoke<string>("get_comment_author");
+}
+
+export async function setCommentAuthor(name: string): Promise<void> {
+  return invoke<void>("set_comment_author", { name });
+}
+
+export async function createUserComment(
+  sessionId: string,
+  filePath: string,
+  lineNumber: number | null,
+  lineType: string | null,
+  content: string,
+  severity: CommentSeverity = "suggestion"
+): Promise<DiffCommentData> {
+  return invoke<DiffCommentData>("create_user_comment", {
+    sessionId,
+    filePath,
+    lineNumber,
+    lineType,
+    content,
+    severity,
+  });
+}
+
+export async function reanchorComments(
+  sessionId: string,
+  worktreePath: string,
+  filePath: string,
+  baseBranch: string,
+  diffAlgorithm: DiffAlgorithm | null = null
+): Promise<ReanchoredCommentData[]> {
+  return invoke<ReanchoredCommentData[]>("reanchor_comments", {
+    sessionId,
+    worktreePath,
+    filePath,
+    baseBranch,
+    diffAlgorithm,
   });
 }
 
@@ -227,6 +887,41 @@ export async function getOpenCommentsForSession(sessionId: string): Promise<Diff
   return invoke<DiffCommentData[]>("get_open_comments_for_session", { sessionId });
 }
 
+// Counts root comments only; replies share their thread's status
+export interface CommentCountsData {
+  open: number;
+  resolved: number;
+}
+
+export async function getCommentCounts(sessionId: string): Promise<CommentCountsData> {
+  return invoke<CommentCountsData>("get_comment_counts", { sessionId });
+}
+
+// "user" returns comments written in the app; "agent" returns ones Claude
+// sessions posted through the session server. Comments from before this was
+// recorded are matched by the current display name instead
+export async function getCommentsByAuthor(
+  sessionId: string,
+  author: "user" | "agent"
+): Promise<DiffCommentData[]> {
+  return invoke<DiffCommentData[]>("get_comments_by_author", { sessionId, author });
+}
+
+// Open root comments marked "blocker"; approving should wait until this is 0
+export async function getBlockingCommentCount(sessionId: string): Promise<number> {
+  return invoke<number>("get_blocking_comment_count", { sessionId });
+}
+
+export interface FileOpenCommentsData {
+  file_path: string;
+  open_count: number;
+}
+
+// Files that still have open root comments, sorted by path
+export async function getFilesWithOpenComments(sessionId: string): Promise<FileOpenCommentsData[]> {
+  return invoke<FileOpenCommentsData[]>("get_files_with_open_comments", { sessionId });
+}
+
 export async function replyToComment(parentId: string, author: string, content: string): Promise<DiffCommentData> {
   return invoke<DiffCommentData>("reply_to_comment", { parentId, author, content });
 }
@@ -235,6 +930,34 @@ export async function resolveComment(id: string): Promise<void> {
   return invoke<void>("resolve_comment", { id });
 }
 
+export async function reopenComment(id: string): Promise<void> {
+  return invoke<void>("reopen_comment", { id });
+}
+
+// Manually move a root comment (and replies) to a line, which must exist in
+// the current diff; anchor describes the new line for re-anchoring
+export async function relocateComment(
+  id: string,
+  filePath: string,
+  lineNumber: number,
+  lineType: "add" | "delete" | "context",
+  anchor: CommentAnchor,
+  worktreePath: string,
+  baseBranch: string,
+  diffAlgorithm: DiffAlgorithm | null = null
+): Promise<DiffCommentData> {
+  return invoke<DiffCommentData>("relocate_comment", {
+    id,
+    filePath,
+    lineNumber,
+    lineType,
+    anchor,
+    worktreePath,
+    baseBranch,
+    diffAlgorithm,
+  });
+}
+
 export async function deleteComment(id: string): Promise<void> {
   return invoke<void>("delete_comment", { id });
 }
@@ -10,6 +10,7 @@ export interface Comment {
   author: string;
   content: string;
   status: "open" | "resolved";
+  severity: api.CommentSeverity;
   parentId: string | null;
   createdAt: Date;
   updatedAt: Date;
@@ -25,6 +26,7 @@ function apiToComment(data: api.DiffCommentData): Comment {
     author: data.author,
     content: data.content,
     status: data.status,
+    severity: data.severity,
     parentId: data.parent_id,
     createdAt: new Date(data.created_at),
     updatedAt: new Date(data.updated_at),
@@ -46,9 +46,9 @@ export const useInboxStore = create<InboxStore>((set, get) => ({
 
   loadMessages: async () => {
     try {
-      const data = await api.getInboxMessages();
+      const { messages } = await api.getInboxMessages();
       set({
-        messages: data.map((m) => ({
+        messages: messages.map((m) => ({
           id: m.id,
           sessionId: m.session_id,
           sessionName: m.session_name,